@@ -1,9 +1,42 @@
+use crate::migrations::SCHEMA_VERSION;
 use serde_derive::{Deserialize, Serialize};
 use serde_json;
 use std::env;
+use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+/// Errors which can happen when decoding a `BuildConfig`.
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    UnsupportedSchema(u32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Json(e) => write!(f, "Could not decode JSON build config: {}", e),
+            Error::Toml(e) => write!(f, "Could not decode TOML build config: {}", e),
+            Error::UnsupportedSchema(version) => write!(
+                f,
+                "Build config has schema version {}, but only up to {} is supported",
+                version, SCHEMA_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Describes where `roast build` picks up the build outputs and where it
+/// copies them to.
+///
+/// The target directories (`bin_target`, `java_target` and the android
+/// `jniLibs` folders) are created automatically during `roast build` if
+/// they don't exist yet.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BuildConfig {
     root: String,
@@ -12,6 +45,18 @@ pub struct BuildConfig {
     bin_target: String,
     java_source: String,
     java_target: String,
+    android_abis: Option<Vec<String>>,
+    module_name: Option<String>,
+    jni_include_path: Option<String>,
+    java_codegen_dir: Option<String>,
+    out_dir: Option<String>,
+    #[serde(default = "legacy_schema_version")]
+    schema_version: u32,
+}
+
+/// Configs without a `schema_version` were written before it was added.
+fn legacy_schema_version() -> u32 {
+    1
 }
 
 impl BuildConfig {
@@ -38,6 +83,50 @@ impl BuildConfig {
     pub fn java_target(&self) -> &str {
         &self.java_target
     }
+
+    pub fn android_abis(&self) -> Option<&[String]> {
+        self.android_abis.as_deref()
+    }
+
+    pub fn module_name(&self) -> Option<&str> {
+        self.module_name.as_deref()
+    }
+
+    pub fn jni_include_path(&self) -> Option<&str> {
+        self.jni_include_path.as_deref()
+    }
+
+    /// Returns the directories a C compiler needs to find `<jni.h>`, which
+    /// are the JDK `include` directory and its platform subdirectory with
+    /// `jni_md.h`, like `include/linux`.
+    ///
+    /// Pass them to the compiler of the crate's own C code, for example with
+    /// `cc::Build::includes`, since `cargo:include` only reaches dependents.
+    pub fn jni_include_paths(&self) -> Vec<String> {
+        match self.jni_include_path() {
+            Some(path) => vec![
+                path.to_string(),
+                format!("{}/{}", path, jni_platform_dir(env::consts::OS)),
+            ],
+            None => vec![],
+        }
+    }
+
+    pub fn java_codegen_dir(&self) -> Option<&str> {
+        self.java_codegen_dir.as_deref()
+    }
+
+    /// The `OUT_DIR` of the build script, where the derive writes the C
+    /// headers, swift bindings and JNI symbols next to the default java
+    /// output directory.
+    pub fn out_dir(&self) -> Option<&str> {
+        self.out_dir.as_deref()
+    }
+
+    /// The version of the config format, see `roast::migrations`.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
+    }
 }
 
 #[derive(Debug, Default)]
@@ -48,6 +137,11 @@ pub struct BuildConfigBuilder {
     bin_target: Option<String>,
     java_source: Option<String>,
     java_target: Option<String>,
+    android_abis: Option<Vec<String>>,
+    module_name: Option<String>,
+    jni_include_path: Option<String>,
+    java_codegen_dir: Option<String>,
+    out_dir: Option<String>,
 }
 
 impl BuildConfigBuilder {
@@ -59,6 +153,11 @@ impl BuildConfigBuilder {
             bin_target: None,
             java_source: None,
             java_target: None,
+            android_abis: None,
+            module_name: None,
+            jni_include_path: None,
+            java_codegen_dir: None,
+            out_dir: None,
         }
     }
 
@@ -110,22 +209,98 @@ impl BuildConfigBuilder {
         self
     }
 
+    /// Builds the library for each of the given android ABIs (like
+    /// `arm64-v8a` or `x86_64`) instead of the host platform.
+    ///
+    /// Note that this requires `cargo-ndk` to be installed.
+    pub fn android_abis<S>(mut self, android_abis: Vec<S>) -> BuildConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.android_abis = Some(android_abis.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Generates a `module-info.java` for the given JPMS module name next
+    /// to the java sources during `roast build`.
+    pub fn module_name<S>(mut self, module_name: S) -> BuildConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.module_name = Some(module_name.into());
+        self
+    }
+
+    /// Points the `cargo:include` directive of the build script to the given
+    /// JDK `include` directory, so hand-written C code in the same crate can
+    /// `#include <jni.h>`.
+    ///
+    /// Defaults to the `include` directory of the JDK found by
+    /// `detect_java_home`, if any.
+    pub fn jni_include_path<S>(mut self, jni_include_path: S) -> BuildConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.jni_include_path = Some(jni_include_path.into());
+        self
+    }
+
+    /// Writes the generated java classes into the given directory instead of
+    /// `OUT_DIR/java`, for example to check them into the repository.
+    ///
+    /// Relative paths are resolved against the crate root. Defaults to the
+    /// `ROAST_JAVA_OUTPUT_DIR` environment variable if it is set.
+    pub fn java_codegen_dir<S>(mut self, java_codegen_dir: S) -> BuildConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.java_codegen_dir = Some(java_codegen_dir.into());
+        self
+    }
+
+    /// Uses the given directory instead of the `OUT_DIR` of the build script
+    /// for the derive outputs and the defaults derived from it.
+    pub fn out_dir<S>(mut self, out_dir: S) -> BuildConfigBuilder
+    where
+        S: Into<String>,
+    {
+        self.out_dir = Some(out_dir.into());
+        self
+    }
+
     pub fn finish(self) -> BuildConfig {
         let root = self.root.unwrap_or_else(|| env::var("CARGO_MANIFEST_DIR").unwrap());
-        let out_dir = env::var("OUT_DIR").unwrap();
-        let default_bin_path = Path::new(&out_dir).join("../../../");
-        let default_bin_source = default_bin_path.to_str().unwrap();
+        let out_dir = self.out_dir.or_else(|| env::var("OUT_DIR").ok());
+        // OUT_DIR is only needed for the defaults, so tests can do without it
+        let default_out_dir = || out_dir.clone().expect("OUT_DIR is not set");
+        let default_bin_source = || {
+            Path::new(&default_out_dir()).join("../../../").display().to_string()
+        };
+        let java_codegen_dir = self
+            .java_codegen_dir
+            .or_else(|| env::var("ROAST_JAVA_OUTPUT_DIR").ok())
+            .map(|dir| Path::new(&root).join(dir).display().to_string());
         BuildConfig {
             root: root.clone(),
             name: self.name.unwrap_or_else(|| env::var("CARGO_PKG_NAME").unwrap()),
-            bin_source: self.bin_source.unwrap_or_else(|| default_bin_source.to_string()),
+            bin_source: self.bin_source.unwrap_or_else(default_bin_source),
             bin_target: self
                 .bin_target
                 .unwrap_or_else(|| format!("{}/src/main/resources", root)),
-            java_source: self
-                .java_source
-                .unwrap_or_else(|| format!("{}/java", env::var("OUT_DIR").unwrap())),
+            java_source: self.java_source.unwrap_or_else(|| {
+                java_codegen_dir
+                    .clone()
+                    .unwrap_or_else(|| format!("{}/java", default_out_dir()))
+            }),
             java_target: self.java_target.unwrap_or_else(|| format!("{}/src/main", root)),
+            android_abis: self.android_abis,
+            module_name: self.module_name,
+            jni_include_path: self.jni_include_path.or_else(|| {
+                detect_java_home().map(|java_home| java_home.join("include").display().to_string())
+            }),
+            java_codegen_dir,
+            out_dir,
+            schema_version: SCHEMA_VERSION,
         }
     }
 }
@@ -137,12 +312,505 @@ impl Default for BuildConfig {
 }
 
 pub fn build(config: BuildConfig) {
-    let encoded = serde_json::to_string_pretty(&config).expect("could not convert config");
+    write_config(&config);
+}
+
+/// Finishes the builder, writes the `roast.json` like `build` and returns
+/// the config for further use in the build script.
+pub fn build_and_write(builder: BuildConfigBuilder) -> BuildConfig {
+    let config = builder.finish();
+    write_config(&config);
+    config
+}
+
+/// Prints the cargo directives which rerun the build script only when the
+/// crate manifest, the sources below `src` or the `ROAST_EXTRA_RS_FILES`
+/// change, instead of on every change in the package. The environment
+/// variables read by the build are watched as well.
+pub fn print_cargo_rerun_directives(config: &BuildConfig) {
+    let extra_rs_files = env::var("ROAST_EXTRA_RS_FILES").unwrap_or_default();
+    for directive in cargo_rerun_directives(config, &extra_rs_files) {
+        println!("{}", directive);
+    }
+}
+
+/// The outputs `roast_export` writes into `OUT_DIR`, relative to it.
+const DERIVE_OUTPUTS: [&str; 7] = [
+    "java",
+    "java_tests",
+    "c",
+    "swift",
+    "symbols",
+    "kotlin",
+    "roast_export.json",
+];
+
+/// Removes the outputs of the previous build from `OUT_DIR` before the crate
+/// is compiled again, so the derive leaves no files of removed structs.
+///
+/// A `java_codegen_dir` is left alone, since its unchanged files are kept on
+/// purpose to not trigger the next build.
+fn clear_derive_outputs(out_dir: &Path) {
+    for output in DERIVE_OUTPUTS {
+        let path = out_dir.join(output);
+        if path.is_dir() {
+            fs::remove_dir_all(&path).expect("could not clear derive output");
+        } else if path.is_file() {
+            fs::remove_file(&path).expect("could not clear derive output");
+        }
+    }
+}
+
+/// Returns the name of the JDK include subdirectory with `jni_md.h` for the
+/// given operating system, as in `std::env::consts::OS`.
+fn jni_platform_dir(os: &str) -> &str {
+    match os {
+        "macos" => "darwin",
+        "windows" => "win32",
+        other => other,
+    }
+}
+
+/// The environment variables which change the build config or the scanned
+/// sources.
+const RERUN_ENV_VARS: [&str; 3] = ["ROAST_JAVA_OUTPUT_DIR", "JAVA_HOME", "ROAST_EXTRA_RS_FILES"];
+
+/// Returns the rerun directives for the config, with `extra_rs_files` being
+/// the newline-separated `ROAST_EXTRA_RS_FILES` the derive scans as well.
+fn cargo_rerun_directives(config: &BuildConfig, extra_rs_files: &str) -> Vec<String> {
+    let extra_paths = extra_rs_files.lines().map(str::trim).filter(|path| !path.is_empty());
+    let changed = ["Cargo.toml", "src"]
+        .iter()
+        .copied()
+        .chain(extra_paths)
+        .map(|path| {
+            format!(
+                "cargo:rerun-if-changed={}",
+                Path::new(&config.root).join(path).display()
+            )
+        });
+    let env_changed = RERUN_ENV_VARS
+        .iter()
+        .map(|var| format!("cargo:rerun-if-env-changed={}", var));
+    changed.chain(env_changed).collect()
+}
+
+fn write_config(config: &BuildConfig) {
+    if let Some(out_dir) = config.out_dir() {
+        clear_derive_outputs(Path::new(out_dir));
+    }
+    let encoded = serde_json::to_string_pretty(config).expect("could not convert config");
     let path = format!("{}/roast.json", config.root);
     fs::write(path, encoded.as_bytes()).expect("could not write config");
+    if let Some(jni_include_path) = config.jni_include_path() {
+        println!("cargo:include={}", jni_include_path);
+    }
+    if let Some(java_codegen_dir) = config.java_codegen_dir() {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+        for directive in java_codegen_directives(java_codegen_dir, &manifest_dir) {
+            println!("{}", directive);
+        }
+    }
+}
+
+/// Returns the cargo directives which hand the java output directory to
+/// `roast_export`. The build script is rerun when the generated classes
+/// change inside the crate, so deleting them triggers a regeneration.
+fn java_codegen_directives(java_codegen_dir: &str, manifest_dir: &str) -> Vec<String> {
+    let mut directives = vec![format!(
+        "cargo:rustc-env=ROAST_JAVA_OUTPUT_DIR={}",
+        java_codegen_dir
+    )];
+    if !manifest_dir.is_empty() && Path::new(java_codegen_dir).starts_with(manifest_dir) {
+        directives.push(format!("cargo:rerun-if-changed={}", java_codegen_dir));
+    }
+    directives
+}
+
+/// The directories JDKs are usually installed into on macOS, Linux and
+/// Windows, with one subdirectory per JDK.
+const JDK_INSTALL_DIRS: [&str; 3] = [
+    "/Library/Java/JavaVirtualMachines",
+    "/usr/lib/jvm",
+    "C:\\Program Files\\Java",
+];
+
+/// Finds the home directory of the JDK to build against.
+///
+/// `JAVA_HOME` is used if it is set. Otherwise the `java` binary on the
+/// `PATH` is followed through its symlinks, like `/usr/bin/java` to
+/// `/usr/lib/jvm/java-17-openjdk/bin/java`, and as a last resort the usual
+/// installation directories are searched. Only directories which contain an
+/// `include` directory are picked up that way, so a plain JRE is skipped.
+pub fn detect_java_home() -> Option<PathBuf> {
+    if let Some(java_home) = env::var_os("JAVA_HOME").filter(|home| !home.is_empty()) {
+        return Some(PathBuf::from(java_home));
+    }
+    env::var_os("PATH")
+        .and_then(|path| java_home_from_path(&path))
+        .or_else(|| {
+            JDK_INSTALL_DIRS
+                .iter()
+                .find_map(|dir| java_home_in(Path::new(dir)))
+        })
+}
+
+/// Returns the JDK home of the first `java` binary found in the given
+/// `PATH`, which lives in the `bin` directory of the JDK.
+fn java_home_from_path(path: &OsStr) -> Option<PathBuf> {
+    let binary = if cfg!(windows) { "java.exe" } else { "java" };
+    env::split_paths(path)
+        .map(|dir| dir.join(binary))
+        .find(|java| java.is_file())
+        .and_then(|java| fs::canonicalize(java).ok())
+        .and_then(|java| Some(java.parent()?.parent()?.to_path_buf()))
+        .filter(|home| is_jdk(home))
+}
+
+/// Returns the JDK with the highest major version in the given installation
+/// directory, where the macOS bundles keep the actual JDK in `Contents/Home`.
+///
+/// The version is parsed from the directory name, see `jdk_major_version`,
+/// and ties are broken by the name.
+fn java_home_in(dir: &Path) -> Option<PathBuf> {
+    let mut homes: Vec<(Option<u32>, PathBuf)> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| {
+            let version = jdk_major_version(&entry.file_name().to_string_lossy());
+            let bundled = entry.path().join("Contents/Home");
+            if bundled.is_dir() {
+                (version, bundled)
+            } else {
+                (version, entry.path())
+            }
+        })
+        .filter(|(_, home)| is_jdk(home))
+        .collect();
+    homes.sort();
+    homes.pop().map(|(_, home)| home)
+}
+
+/// Parses the major java version from the name of a JDK directory, like 17
+/// for `jdk-17.0.2` or `java-17-openjdk-amd64` and 8 for the old `1.8`
+/// scheme of `jdk1.8.0_292.jdk`.
+fn jdk_major_version(name: &str) -> Option<u32> {
+    let mut numbers = name
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|n| !n.is_empty())
+        .map(|n| n.parse::<u32>().ok());
+    match numbers.next()?? {
+        1 => numbers.next()?,
+        major => Some(major),
+    }
+}
+
+/// Returns true if the directory looks like a JDK with JNI headers.
+fn is_jdk(home: &Path) -> bool {
+    home.join("include").is_dir()
 }
 
 pub fn config_from_path(path: &str) -> BuildConfig {
     let read = String::from_utf8(fs::read(path).unwrap()).unwrap();
     serde_json::from_str(&read).expect("could not decode build config")
 }
+
+/// Generates the `module-info.java` descriptor of a JPMS module, which
+/// exports the given packages.
+pub fn export_module_info(module_name: &str, packages: &[String]) -> String {
+    let exports: String = packages
+        .iter()
+        .map(|package| format!("\texports {};\n", package))
+        .collect();
+    format!("module {} {{\n{}}}\n", module_name, exports)
+}
+
+/// Decodes a `BuildConfig` from a JSON string, in the same format as the
+/// generated `roast.json`.
+pub fn from_json_str(s: &str) -> Result<BuildConfig, Error> {
+    serde_json::from_str(s).map_err(Error::Json)
+}
+
+/// Decodes a `BuildConfig` from a TOML string with the same keys as the
+/// generated `roast.json`.
+pub fn from_toml_str(s: &str) -> Result<BuildConfig, Error> {
+    toml::from_str(s).map_err(Error::Toml)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn config_from_json_str() {
+        let config = from_json_str(
+            r#"{
+                "root": "/roast",
+                "name": "hello",
+                "bin_source": "/roast/target/debug",
+                "bin_target": "/roast/src/main/resources",
+                "java_source": "/roast/target/java",
+                "java_target": "/roast/src/main",
+                "android_abis": ["arm64-v8a"],
+                "module_name": "com.example.hello",
+                "jni_include_path": "/usr/lib/jvm/java-17/include",
+                "out_dir": "/roast/target/debug/build/hello-1/out"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!("/roast", config.root());
+        assert_eq!("hello", config.name());
+        assert_eq!("/roast/target/debug", config.bin_source());
+        assert_eq!("/roast/src/main/resources", config.bin_target());
+        assert_eq!("/roast/target/java", config.java_source());
+        assert_eq!("/roast/src/main", config.java_target());
+        assert_eq!(Some(&[String::from("arm64-v8a")][..]), config.android_abis());
+        assert_eq!(Some("com.example.hello"), config.module_name());
+        assert_eq!(Some("/usr/lib/jvm/java-17/include"), config.jni_include_path());
+        assert_eq!(Some("/roast/target/debug/build/hello-1/out"), config.out_dir());
+        assert_eq!(
+            vec![
+                String::from("/usr/lib/jvm/java-17/include"),
+                format!("/usr/lib/jvm/java-17/include/{}", jni_platform_dir(env::consts::OS)),
+            ],
+            config.jni_include_paths()
+        );
+        assert_eq!(1, config.schema_version());
+    }
+
+    #[test]
+    fn config_from_toml_str() {
+        let config = from_toml_str(
+            r#"
+            root = "/roast"
+            name = "hello"
+            bin_source = "/roast/target/debug"
+            bin_target = "/roast/src/main/resources"
+            java_source = "/roast/target/java"
+            java_target = "/roast/src/main"
+            "#,
+        )
+        .unwrap();
+        assert_eq!("/roast", config.root());
+        assert_eq!("hello", config.name());
+        assert_eq!("/roast/target/debug", config.bin_source());
+        assert_eq!("/roast/src/main/resources", config.bin_target());
+        assert_eq!("/roast/target/java", config.java_source());
+        assert_eq!("/roast/src/main", config.java_target());
+        assert_eq!(None, config.android_abis());
+        assert_eq!(None, config.module_name());
+        assert_eq!(None, config.jni_include_path());
+        assert!(config.jni_include_paths().is_empty());
+    }
+
+    #[test]
+    fn roast_build_macro() {
+        mod generated {
+            crate::roast_build! {
+                root = std::env::temp_dir().join("roast_build_macro").display().to_string(),
+                name = "hello",
+                bin_source = "/roast/target/debug",
+                java_source = "/roast/target/java",
+                java_target = "android/app/src/main/java",
+                jni_include_path = "/opt/jdk/include",
+            }
+
+            pub fn run() {
+                main()
+            }
+        }
+
+        let root = env::temp_dir().join("roast_build_macro");
+        fs::create_dir_all(&root).unwrap();
+        if env::var_os("OUT_DIR").is_none() {
+            env::set_var("OUT_DIR", &root);
+        }
+        generated::run();
+
+        let config = config_from_path(root.join("roast.json").to_str().unwrap());
+        assert_eq!("hello", config.name());
+        assert_eq!("/roast/target/debug", config.bin_source());
+        assert_eq!("/roast/target/java", config.java_source());
+        assert_eq!("android/app/src/main/java", config.java_target());
+        assert_eq!(Some("/opt/jdk/include"), config.jni_include_path());
+        assert_eq!(SCHEMA_VERSION, config.schema_version());
+        assert_eq!(format!("{}/src/main/resources", config.root()), config.bin_target());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn java_codegen_dir_relative_to_root() {
+        let config = BuildConfigBuilder::new()
+            .set_root("/roast")
+            .set_name("hello")
+            .bin_source("/roast/target/debug")
+            .java_codegen_dir("src/generated/java")
+            .finish();
+        assert_eq!(Some("/roast/src/generated/java"), config.java_codegen_dir());
+        assert_eq!("/roast/src/generated/java", config.java_source());
+
+        let config = BuildConfigBuilder::new()
+            .set_root("/roast")
+            .set_name("hello")
+            .bin_source("/roast/target/debug")
+            .java_codegen_dir("/tmp/java")
+            .java_source("/roast/target/java")
+            .finish();
+        assert_eq!(Some("/tmp/java"), config.java_codegen_dir());
+        assert_eq!("/roast/target/java", config.java_source());
+    }
+
+    #[test]
+    fn build_and_write_returns_config() {
+        let root = env::temp_dir().join("roast_build_and_write");
+        fs::create_dir_all(&root).unwrap();
+        let root_path = root.display().to_string();
+        let config = build_and_write(
+            BuildConfigBuilder::new()
+                .set_root(root_path.clone())
+                .set_name("hello")
+                .out_dir(format!("{}/out", root_path)),
+        );
+        assert_eq!("hello", config.name());
+        let written = config_from_path(root.join("roast.json").to_str().unwrap());
+        assert_eq!(config.java_target(), written.java_target());
+        assert_eq!(
+            vec![
+                format!("cargo:rerun-if-changed={}/Cargo.toml", root_path),
+                format!("cargo:rerun-if-changed={}/src", root_path),
+                format!("cargo:rerun-if-changed={}/gen/bindings.rs", root_path),
+                String::from("cargo:rerun-if-changed=/tmp/extra.rs"),
+                String::from("cargo:rerun-if-env-changed=ROAST_JAVA_OUTPUT_DIR"),
+                String::from("cargo:rerun-if-env-changed=JAVA_HOME"),
+                String::from("cargo:rerun-if-env-changed=ROAST_EXTRA_RS_FILES"),
+            ],
+            cargo_rerun_directives(&config, "gen/bindings.rs\n /tmp/extra.rs\n\n")
+        );
+        assert_eq!(Some(format!("{}/out", root_path).as_str()), config.out_dir());
+        assert_eq!(format!("{}/out/java", root_path), config.java_source());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn java_codegen_rerun_directive() {
+        assert_eq!(
+            vec![
+                "cargo:rustc-env=ROAST_JAVA_OUTPUT_DIR=/roast/src/generated/java",
+                "cargo:rerun-if-changed=/roast/src/generated/java",
+            ],
+            java_codegen_directives("/roast/src/generated/java", "/roast")
+        );
+        assert_eq!(
+            vec!["cargo:rustc-env=ROAST_JAVA_OUTPUT_DIR=/tmp/java"],
+            java_codegen_directives("/tmp/java", "/roast")
+        );
+    }
+
+    #[test]
+    fn derive_outputs_cleared() {
+        let dir = env::temp_dir().join("roast_clear_derive_outputs");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("java/com/example")).unwrap();
+        fs::write(dir.join("java/com/example/Removed.java"), "class Removed {}").unwrap();
+        fs::create_dir_all(dir.join("symbols")).unwrap();
+        fs::write(dir.join("roast_export.json"), "{}").unwrap();
+        fs::write(dir.join("other.txt"), "kept").unwrap();
+
+        clear_derive_outputs(&dir);
+        assert!(!dir.join("java").exists());
+        assert!(!dir.join("symbols").exists());
+        assert!(!dir.join("roast_export.json").exists());
+        assert!(dir.join("other.txt").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jni_platform_dirs() {
+        assert_eq!("linux", jni_platform_dir("linux"));
+        assert_eq!("darwin", jni_platform_dir("macos"));
+        assert_eq!("win32", jni_platform_dir("windows"));
+    }
+
+    #[test]
+    fn java_home_in_install_dir() {
+        let dir = env::temp_dir().join("roast_java_home_in");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("jdk-9/include")).unwrap();
+        fs::create_dir_all(dir.join("jdk-11/include")).unwrap();
+        fs::create_dir_all(dir.join("jdk-17.jdk/Contents/Home/include")).unwrap();
+        fs::create_dir_all(dir.join("jdk1.8.0_292.jdk/Contents/Home/include")).unwrap();
+        fs::create_dir_all(dir.join("jre-21/bin")).unwrap();
+        assert_eq!(
+            Some(dir.join("jdk-17.jdk/Contents/Home")),
+            java_home_in(&dir)
+        );
+        assert_eq!(None, java_home_in(&dir.join("missing")));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn jdk_major_versions() {
+        assert_eq!(Some(17), jdk_major_version("jdk-17.0.2"));
+        assert_eq!(Some(21), jdk_major_version("java-21-openjdk-amd64"));
+        assert_eq!(Some(8), jdk_major_version("jdk1.8.0_292.jdk"));
+        assert_eq!(Some(8), jdk_major_version("java-1.8.0-openjdk"));
+        assert_eq!(Some(11), jdk_major_version("temurin-11.jdk"));
+        assert_eq!(None, jdk_major_version("default-java"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn java_home_from_symlinked_path() {
+        let dir = env::temp_dir().join("roast_java_home_from_path");
+        let _ = fs::remove_dir_all(&dir);
+        let home = dir.join("jvm/jdk-17");
+        fs::create_dir_all(home.join("include")).unwrap();
+        fs::create_dir_all(home.join("bin")).unwrap();
+        fs::write(home.join("bin/java"), "").unwrap();
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        std::os::unix::fs::symlink(home.join("bin/java"), dir.join("bin/java")).unwrap();
+
+        let path = env::join_paths([dir.join("empty"), dir.join("bin")]).unwrap();
+        assert_eq!(
+            Some(fs::canonicalize(&home).unwrap()),
+            java_home_from_path(&path)
+        );
+        fs::remove_dir_all(home.join("include")).unwrap();
+        assert_eq!(None, java_home_from_path(&path));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn module_info() {
+        assert_eq!(
+            "module com.example.hello {\n\texports com.example.hello;\n\
+             \texports com.example.hello.util;\n}\n",
+            export_module_info(
+                "com.example.hello",
+                &[
+                    String::from("com.example.hello"),
+                    String::from("com.example.hello.util")
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn config_from_malformed_json_str() {
+        assert!(matches!(from_json_str("{ \"root\": "), Err(Error::Json(_))));
+        assert!(matches!(
+            from_json_str(r#"{ "root": "/roast" }"#),
+            Err(Error::Json(_))
+        ));
+    }
+
+    #[test]
+    fn config_from_malformed_toml_str() {
+        assert!(matches!(from_toml_str("root = "), Err(Error::Toml(_))));
+        assert!(matches!(
+            from_toml_str(r#"root = "/roast""#),
+            Err(Error::Toml(_))
+        ));
+    }
+}
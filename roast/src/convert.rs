@@ -1,6 +1,34 @@
-use jni::objects::JString;
+use jni::objects::{JObject, JString, JValue};
 use jni::sys::*;
 use jni::JNIEnv;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+/// Provides the value handed back to java when an exception has been
+/// thrown and the actual return value is ignored by the JVM.
+pub trait JniSentinel {
+    fn sentinel() -> Self;
+}
+
+macro_rules! impl_zero_sentinel {
+    ($($t:ty),*) => {
+        $(impl JniSentinel for $t {
+            #[inline]
+            fn sentinel() -> Self {
+                Default::default()
+            }
+        })*
+    };
+}
+
+impl_zero_sentinel!(jbyte, jboolean, jshort, jchar, jint, jlong, jfloat, jdouble);
+
+impl JniSentinel for jobject {
+    #[inline]
+    fn sentinel() -> Self {
+        std::ptr::null_mut()
+    }
+}
 
 #[inline]
 pub fn convert_retval_i8(_env: &JNIEnv, input: i8) -> jbyte {
@@ -12,6 +40,11 @@ pub fn convert_arg_jbyte(_env: &JNIEnv, input: jbyte) -> i8 {
     input
 }
 
+#[inline]
+pub fn convert_retval_u8(_env: &JNIEnv, input: u8) -> jboolean {
+    input
+}
+
 #[inline]
 pub fn convert_retval_i32(_env: &JNIEnv, input: i32) -> jint {
     input
@@ -38,7 +71,7 @@ pub fn convert_retval_u16(_env: &JNIEnv, input: u16) -> jchar {
 }
 
 #[inline]
-pub fn convet_arg_jchar(_env: &JNIEnv, input: jchar) -> u16 {
+pub fn convert_arg_jchar(_env: &JNIEnv, input: jchar) -> u16 {
     input
 }
 
@@ -47,11 +80,119 @@ pub fn convert_retval_i64(_env: &JNIEnv, input: i64) -> jlong {
     input
 }
 
+/// Converts a timestamp, which is an `i64` of milliseconds since the unix
+/// epoch, into a java long.
+#[inline]
+pub fn convert_retval_timestamp(_env: &JNIEnv, input: i64) -> jlong {
+    input
+}
+
 #[inline]
 pub fn convert_arg_jlong(_env: &JNIEnv, input: jlong) -> i64 {
     input
 }
 
+/// Converts a u32 into a java long, which can hold all of its values.
+#[inline]
+pub fn convert_retval_u32(_env: &JNIEnv, input: u32) -> jlong {
+    jlong::from(input)
+}
+
+/// Converts a u64 into a java long by reinterpreting its bits.
+///
+/// Values above `i64::MAX` show up as negative numbers in java.
+#[inline]
+pub fn convert_retval_u64(_env: &JNIEnv, input: u64) -> jlong {
+    input as jlong
+}
+
+/// Converts a java long into a u64 by reinterpreting its bits.
+///
+/// Negative numbers turn into values above `i64::MAX`, so `-1` is
+/// `u64::MAX`.
+#[inline]
+pub fn convert_arg_jlong_to_u64(_env: &JNIEnv, input: jlong) -> u64 {
+    input as u64
+}
+
+/// Turns a native handle back into a reference to the struct it points to.
+///
+/// The handle is usually created from `Box::into_raw(Box::new(value))` with
+/// `convert_retval_ptr_to_jlong` and handed to java, which passes it back
+/// into `#[roast(handle)]` arguments.
+///
+/// # Safety
+///
+/// The handle must point to a live `T` which is not mutated while the
+/// reference is in use.
+#[inline]
+pub unsafe fn convert_handle_to_ref<'a, T>(env: &JNIEnv, handle: jlong) -> &'a T {
+    assert!(handle != 0, "native handle is null");
+    &*convert_arg_jlong_to_ptr::<T>(env, handle)
+}
+
+/// Turns a native handle back into a mutable reference to the struct it
+/// points to, see `convert_handle_to_ref`.
+///
+/// # Safety
+///
+/// The handle must point to a live `T` which is not accessed by anyone else
+/// while the reference is in use.
+#[inline]
+pub unsafe fn convert_handle_to_mut_ref<'a, T>(env: &JNIEnv, handle: jlong) -> &'a mut T {
+    assert!(handle != 0, "native handle is null");
+    &mut *convert_arg_jlong_to_ptr::<T>(env, handle)
+}
+
+/// Moves a boxed trait object to the heap and returns its native handle.
+///
+/// A `Box<dyn Trait>` is a fat pointer which doesn't fit into a java long,
+/// so it is boxed once more. The handle is turned back into a reference with
+/// `convert_handle_to_ref::<Box<dyn Trait>>` and freed with `drop_handle`.
+#[inline]
+pub fn convert_retval_trait_object<T: ?Sized>(env: &JNIEnv, input: Box<T>) -> jlong {
+    convert_retval_ptr_to_jlong(env, Box::into_raw(Box::new(input)))
+}
+
+/// Frees the value behind a native handle, where a null handle is ignored.
+///
+/// # Safety
+///
+/// The handle must have been created from a `Box<T>` and must not be used
+/// anymore afterwards.
+#[inline]
+pub unsafe fn drop_handle<T>(env: &JNIEnv, handle: jlong) {
+    if handle != 0 {
+        drop(Box::from_raw(convert_arg_jlong_to_ptr::<T>(env, handle)));
+    }
+}
+
+/// Turns a java long back into the raw pointer it was created from with
+/// `convert_retval_ptr_to_jlong`.
+///
+/// A plain `input as *mut T` cast hides where the pointer comes from. Under
+/// strict provenance it has no provenance at all, so dereferencing it is
+/// undefined behavior, and Miri rejects it with `-Zmiri-strict-provenance`.
+/// Going through the exposed provenance API of `sptr` instead states that
+/// the address escaped to java and picks up the provenance it was exposed
+/// with, which keeps Miri and checkers like address sanitizer able to track
+/// it. `sptr` is the polyfill of `std::ptr::with_exposed_provenance_mut`,
+/// which works on older compilers as well.
+#[inline]
+pub fn convert_arg_jlong_to_ptr<T>(_env: &JNIEnv, input: jlong) -> *mut T {
+    sptr::from_exposed_addr_mut(input as usize)
+}
+
+/// Turns a raw pointer into a java long, which can be passed back into
+/// `convert_arg_jlong_to_ptr` later on.
+///
+/// The provenance of the pointer is exposed, so the pointer recreated from
+/// the address is valid to dereference, see `convert_arg_jlong_to_ptr`.
+#[inline]
+pub fn convert_retval_ptr_to_jlong<T>(_env: &JNIEnv, ptr: *mut T) -> jlong {
+    sptr::Strict::expose_addr(ptr) as jlong
+}
+
 #[inline]
 pub fn convert_retval_f32(_env: &JNIEnv, input: f32) -> jfloat {
     input
@@ -72,6 +213,14 @@ pub fn convert_arg_jdouble(_env: &JNIEnv, input: jdouble) -> f64 {
     input
 }
 
+/// Narrows a java double into an `f32` for `#[roast(lossy)]` arguments and
+/// hand-written JNI code, where the value is rounded to the nearest `f32` and
+/// values out of its range turn into infinity.
+#[inline]
+pub fn convert_arg_jdouble_to_f32_lossy(_env: &JNIEnv, input: jdouble) -> f32 {
+    input as f32
+}
+
 #[inline]
 pub fn convert_retval_bool(_env: &JNIEnv, input: bool) -> jboolean {
     if input {
@@ -99,6 +248,10 @@ pub fn convert_retval_string(env: &JNIEnv, input: String) -> jstring {
 }
 
 /// Converts a string argument from java into a heap owned rust string.
+///
+/// This goes through java's modified UTF-8 encoding, which is the right
+/// choice for regular text. See `convert_arg_jstring_lossy` for strings
+/// which might not be valid UTF-16.
 #[inline]
 pub fn convert_arg_jstring(env: &JNIEnv, input: JString) -> String {
     env.get_string(input)
@@ -106,6 +259,61 @@ pub fn convert_arg_jstring(env: &JNIEnv, input: JString) -> String {
         .into()
 }
 
+/// Converts a string argument from java into a heap owned rust string,
+/// replacing invalid UTF-16 like lone surrogates with U+FFFD.
+///
+/// Java strings can hold arbitrary UTF-16 code units, so this variant is
+/// useful for input which is not under your control. It reads the raw
+/// UTF-16 data instead of the modified UTF-8 representation, so each
+/// invalid code unit turns into exactly one replacement character.
+#[inline]
+pub fn convert_arg_jstring_lossy(env: &JNIEnv, input: JString) -> String {
+    let raw = env.get_native_interface();
+    let input = input.into_inner();
+    let chars = unsafe {
+        let len = (**raw).GetStringLength.expect("GetStringLength not available")(raw, input);
+        let mut chars = vec![0u16; len as usize];
+        (**raw).GetStringRegion.expect("GetStringRegion not available")(
+            raw,
+            input,
+            0,
+            len,
+            chars.as_mut_ptr(),
+        );
+        chars
+    };
+    String::from_utf16_lossy(&chars)
+}
+
+/// Converts a borrowed rust string into a new java string.
+///
+/// The characters are copied since the JVM owns the returned object.
+#[inline]
+pub fn convert_retval_str(env: &JNIEnv, input: &str) -> jstring {
+    env.new_string(input)
+        .expect("Could not create Java String for return value!")
+        .into_inner()
+}
+
+/// Converts a rust path into a java string.
+///
+/// Paths are not guaranteed to be valid unicode, so invalid sequences are
+/// replaced with U+FFFD rather than failing the call.
+#[inline]
+pub fn convert_retval_pathbuf(env: &JNIEnv, input: PathBuf) -> jstring {
+    convert_retval_string(env, path_to_string(input))
+}
+
+/// Converts a java string argument into a rust path.
+#[inline]
+pub fn convert_arg_jpathstring(env: &JNIEnv, input: JString) -> PathBuf {
+    PathBuf::from(convert_arg_jstring(env, input))
+}
+
+fn path_to_string(input: PathBuf) -> String {
+    input.to_string_lossy().into_owned()
+}
+
 #[inline]
 pub fn convert_arg_jbytearray(env: &JNIEnv, input: jbyteArray) -> Vec<u8> {
     env.convert_byte_array(input)
@@ -117,3 +325,587 @@ pub fn convert_retval_vecu8(env: &JNIEnv, input: Vec<u8>) -> jbyteArray {
     env.byte_array_from_slice(input.as_slice())
         .expect("Could not convert u8 vec into java byte array!")
 }
+
+/// Copies the bytes of a `&mut [u8]` argument back into the java byte array
+/// it was converted from, so that java sees the changes made in place.
+#[inline]
+pub fn convert_arg_jbytearray_write_back(env: &JNIEnv, output: jbyteArray, input: &[u8]) {
+    let bytes: Vec<jbyte> = input.iter().map(|b| *b as jbyte).collect();
+    env.set_byte_array_region(output, 0, &bytes)
+        .expect("Could not write u8 slice back into java byte array!");
+}
+
+/// Converts a borrowed byte slice into a new java byte array.
+///
+/// The bytes are copied since the JVM owns the returned object.
+#[inline]
+pub fn convert_retval_sliceu8(env: &JNIEnv, input: &[u8]) -> jbyteArray {
+    env.byte_array_from_slice(input)
+        .expect("Could not convert u8 slice into java byte array!")
+}
+
+/// Converts a java float array into a rust float vec.
+///
+/// If the array is `null`, a `java.lang.NullPointerException` is thrown
+/// and an empty vec is returned, which is ignored since the exception is
+/// pending once the native method returns.
+#[inline]
+pub fn convert_arg_jfloatarray(env: &JNIEnv, input: jfloatArray) -> Vec<f32> {
+    if input.is_null() {
+        env.throw_new("java/lang/NullPointerException", "float array must not be null")
+            .expect("Could not throw java exception for null float array!");
+        return vec![];
+    }
+    let len = env
+        .get_array_length(input)
+        .expect("Could not get length of java float array!");
+    let mut output = vec![0f32; len as usize];
+    env.get_float_array_region(input, 0, &mut output)
+        .expect("Could not convert java float array into a f32 vec!");
+    output
+}
+
+/// Converts a rust float vec into a new java float array.
+///
+/// If an exception is already pending, like after a `null` argument has
+/// been passed to `convert_arg_jfloatarray`, `null` is returned instead.
+#[inline]
+pub fn convert_retval_vecf32(env: &JNIEnv, input: Vec<f32>) -> jfloatArray {
+    if env
+        .exception_check()
+        .expect("Could not check for pending java exception!")
+    {
+        return std::ptr::null_mut();
+    }
+    let output = env
+        .new_float_array(input.len() as jsize)
+        .expect("Could not create java float array!");
+    env.set_float_array_region(output, 0, &input)
+        .expect("Could not convert f32 vec into java float array!");
+    output
+}
+
+/// Converts a java boolean array into a rust bool vec.
+///
+/// Each `jboolean` is read as a byte, where everything but `0` is `true`.
+/// If the array is `null`, a `java.lang.NullPointerException` is thrown
+/// and an empty vec is returned.
+#[inline]
+pub fn convert_arg_jbooleanarray(env: &JNIEnv, input: jbooleanArray) -> Vec<bool> {
+    if input.is_null() {
+        env.throw_new("java/lang/NullPointerException", "boolean array must not be null")
+            .expect("Could not throw java exception for null boolean array!");
+        return vec![];
+    }
+    let len = env
+        .get_array_length(input)
+        .expect("Could not get length of java boolean array!");
+    let mut bytes = vec![0 as jboolean; len as usize];
+    env.get_boolean_array_region(input, 0, &mut bytes)
+        .expect("Could not convert java boolean array into a bool vec!");
+    bytes.into_iter().map(|b| b != 0).collect()
+}
+
+/// Converts a rust bool vec into a new java boolean array.
+///
+/// If an exception is already pending, like after a `null` argument has
+/// been passed to `convert_arg_jbooleanarray`, `null` is returned instead.
+#[inline]
+pub fn convert_retval_vecbool(env: &JNIEnv, input: Vec<bool>) -> jbooleanArray {
+    if env
+        .exception_check()
+        .expect("Could not check for pending java exception!")
+    {
+        return std::ptr::null_mut();
+    }
+    let bytes: Vec<jboolean> = input.into_iter().map(|b| convert_retval_bool(env, b)).collect();
+    let output = env
+        .new_boolean_array(bytes.len() as jsize)
+        .expect("Could not create java boolean array!");
+    env.set_boolean_array_region(output, 0, &bytes)
+        .expect("Could not convert bool vec into java boolean array!");
+    output
+}
+
+/// Converts a java int array into a rust u32 vec by reinterpreting the bits
+/// of each value, so negative numbers turn into values above `i32::MAX`.
+///
+/// If the array is `null`, a `java.lang.NullPointerException` is thrown
+/// and an empty vec is returned.
+#[inline]
+pub fn convert_arg_juintarray(env: &JNIEnv, input: jintArray) -> Vec<u32> {
+    if input.is_null() {
+        env.throw_new("java/lang/NullPointerException", "int array must not be null")
+            .expect("Could not throw java exception for null int array!");
+        return vec![];
+    }
+    let len = env
+        .get_array_length(input)
+        .expect("Could not get length of java int array!");
+    let mut ints = vec![0 as jint; len as usize];
+    env.get_int_array_region(input, 0, &mut ints)
+        .expect("Could not convert java int array into a u32 vec!");
+    ints.into_iter().map(|i| i as u32).collect()
+}
+
+/// Converts a rust u32 vec into a new java int array by reinterpreting the
+/// bits of each value.
+///
+/// Values above `i32::MAX` show up as negative numbers in java, so
+/// `u32::MAX` turns into `-1`. If an exception is already pending, `null`
+/// is returned instead.
+#[inline]
+pub fn convert_retval_vecu32(env: &JNIEnv, input: Vec<u32>) -> jintArray {
+    if env
+        .exception_check()
+        .expect("Could not check for pending java exception!")
+    {
+        return std::ptr::null_mut();
+    }
+    let ints: Vec<jint> = input.into_iter().map(|i| i as jint).collect();
+    let output = env
+        .new_int_array(ints.len() as jsize)
+        .expect("Could not create java int array!");
+    env.set_int_array_region(output, 0, &ints)
+        .expect("Could not convert u32 vec into java int array!");
+    output
+}
+
+/// Converts a java int array into a rust i32 vec.
+///
+/// If the array is `null`, a `java.lang.NullPointerException` is thrown
+/// and an empty vec is returned.
+#[inline]
+pub fn convert_arg_jintarray(env: &JNIEnv, input: jintArray) -> Vec<i32> {
+    if input.is_null() {
+        env.throw_new("java/lang/NullPointerException", "int array must not be null")
+            .expect("Could not throw java exception for null int array!");
+        return vec![];
+    }
+    let len = env
+        .get_array_length(input)
+        .expect("Could not get length of java int array!");
+    let mut ints = vec![0 as jint; len as usize];
+    env.get_int_array_region(input, 0, &mut ints)
+        .expect("Could not convert java int array into a i32 vec!");
+    ints
+}
+
+/// Converts a rust i32 vec into a new java int array.
+///
+/// If an exception is already pending, `null` is returned instead.
+#[inline]
+pub fn convert_retval_veci32(env: &JNIEnv, input: Vec<i32>) -> jintArray {
+    if env
+        .exception_check()
+        .expect("Could not check for pending java exception!")
+    {
+        return std::ptr::null_mut();
+    }
+    let output = env
+        .new_int_array(input.len() as jsize)
+        .expect("Could not create java int array!");
+    env.set_int_array_region(output, 0, &input)
+        .expect("Could not convert i32 vec into java int array!");
+    output
+}
+
+/// A rust value which can be stored in a java `Object[]`.
+///
+/// Primitives are boxed into their wrapper classes like `java.lang.Integer`,
+/// all other types are converted like a regular return value.
+pub trait IntoJavaObject {
+    fn into_java_object(self, env: &JNIEnv) -> jobject;
+}
+
+macro_rules! impl_into_java_object_boxed {
+    ($($t:ty => $convert:ident, $class:expr, $sig:expr, $variant:ident;)*) => {
+        $(impl IntoJavaObject for $t {
+            #[inline]
+            fn into_java_object(self, env: &JNIEnv) -> jobject {
+                let value = JValue::$variant($convert(env, self));
+                env.call_static_method($class, "valueOf", $sig, &[value])
+                    .and_then(|v| v.l())
+                    .expect("Could not box java primitive!")
+                    .into_inner()
+            }
+        })*
+    };
+}
+
+impl_into_java_object_boxed! {
+    i8 => convert_retval_i8, "java/lang/Byte", "(B)Ljava/lang/Byte;", Byte;
+    u8 => convert_retval_u8, "java/lang/Boolean", "(Z)Ljava/lang/Boolean;", Bool;
+    i16 => convert_retval_i16, "java/lang/Short", "(S)Ljava/lang/Short;", Short;
+    u16 => convert_retval_u16, "java/lang/Character", "(C)Ljava/lang/Character;", Char;
+    i32 => convert_retval_i32, "java/lang/Integer", "(I)Ljava/lang/Integer;", Int;
+    i64 => convert_retval_i64, "java/lang/Long", "(J)Ljava/lang/Long;", Long;
+    u64 => convert_retval_u64, "java/lang/Long", "(J)Ljava/lang/Long;", Long;
+    f32 => convert_retval_f32, "java/lang/Float", "(F)Ljava/lang/Float;", Float;
+    f64 => convert_retval_f64, "java/lang/Double", "(D)Ljava/lang/Double;", Double;
+    bool => convert_retval_bool, "java/lang/Boolean", "(Z)Ljava/lang/Boolean;", Bool;
+}
+
+macro_rules! impl_into_java_object {
+    ($($t:ty => $convert:ident;)*) => {
+        $(impl IntoJavaObject for $t {
+            #[inline]
+            fn into_java_object(self, env: &JNIEnv) -> jobject {
+                $convert(env, self)
+            }
+        })*
+    };
+}
+
+impl_into_java_object! {
+    String => convert_retval_string;
+    PathBuf => convert_retval_pathbuf;
+    Vec<u8> => convert_retval_vecu8;
+    Vec<f32> => convert_retval_vecf32;
+    Vec<bool> => convert_retval_vecbool;
+    Vec<u32> => convert_retval_vecu32;
+    Vec<i32> => convert_retval_veci32;
+}
+
+/// Converts a rust pair into a java `Object[]` of length two.
+///
+/// The generated java code unpacks the array into a `Tuple2` or a named
+/// result class, so the array itself never reaches the caller.
+#[inline]
+pub fn convert_retval_tuple2<A, B>(env: &JNIEnv, input: (A, B)) -> jobjectArray
+where
+    A: IntoJavaObject,
+    B: IntoJavaObject,
+{
+    let (first, second) = input;
+    let output = env
+        .new_object_array(2, "java/lang/Object", JObject::null())
+        .expect("Could not create java object array!");
+    for (i, element) in [first.into_java_object(env), second.into_java_object(env)]
+        .into_iter()
+        .enumerate()
+    {
+        env.set_object_array_element(output, i as jsize, JObject::from(element))
+            .expect("Could not convert tuple into java object array!");
+    }
+    output
+}
+
+/// Converts an optional rust value into a nullable java object.
+///
+/// Primitives are boxed, so an `Option<i32>` turns into an `Integer` which
+/// is `null` for `None`.
+#[inline]
+pub fn convert_retval_option<T: IntoJavaObject>(env: &JNIEnv, input: Option<T>) -> jobject {
+    match input {
+        Some(v) => v.into_java_object(env),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Converts an optional rust value into a `java.util.Optional`, which is
+/// used instead of a nullable object with `#[roast(use_optional)]`.
+#[inline]
+pub fn convert_retval_option_optional<T: IntoJavaObject>(
+    env: &JNIEnv,
+    input: Option<T>,
+) -> jobject {
+    match input {
+        Some(v) => env.call_static_method(
+            "java/util/Optional",
+            "of",
+            "(Ljava/lang/Object;)Ljava/util/Optional;",
+            &[JValue::Object(JObject::from(v.into_java_object(env)))],
+        ),
+        None => {
+            env.call_static_method("java/util/Optional", "empty", "()Ljava/util/Optional;", &[])
+        }
+    }
+    .and_then(|v| v.l())
+    .expect("Could not create java optional!")
+    .into_inner()
+}
+
+macro_rules! impl_convert_retval_option {
+    ($($name:ident => $t:ty;)*) => {
+        $(
+            /// Converts an optional rust value into a nullable java object,
+            /// see `convert_retval_option`.
+            #[inline]
+            pub fn $name(env: &JNIEnv, input: Option<$t>) -> jobject {
+                convert_retval_option(env, input)
+            }
+        )*
+    };
+}
+
+impl_convert_retval_option! {
+    convert_retval_option_i32 => i32;
+    convert_retval_option_i64 => i64;
+    convert_retval_option_f64 => f64;
+    convert_retval_option_bool => bool;
+    convert_retval_option_string => String;
+}
+
+macro_rules! impl_convert_arg_nullable {
+    ($($name:ident => $t:ty, $method:expr, $sig:expr, $variant:ident;)*) => {
+        $(
+            /// Converts a nullable boxed java primitive into an optional rust
+            /// value, where `null` turns into `None`.
+            #[inline]
+            pub fn $name(env: &JNIEnv, input: JObject) -> Option<$t> {
+                if input.is_null() {
+                    return None;
+                }
+                let value = env
+                    .call_method(input, $method, $sig, &[])
+                    .and_then(|v| v.$variant())
+                    .expect("Could not unbox java primitive!");
+                Some(value.into())
+            }
+        )*
+    };
+}
+
+impl_convert_arg_nullable! {
+    convert_arg_jint_nullable => i32, "intValue", "()I", i;
+    convert_arg_jlong_nullable => i64, "longValue", "()J", j;
+    convert_arg_jdouble_nullable => f64, "doubleValue", "()D", d;
+    convert_arg_jboolean_nullable => bool, "booleanValue", "()Z", z;
+}
+
+/// Converts a nullable java string into an optional rust string, where
+/// `null` turns into `None`.
+#[inline]
+pub fn convert_arg_jstring_nullable(env: &JNIEnv, input: JString) -> Option<String> {
+    if input.is_null() {
+        return None;
+    }
+    Some(convert_arg_jstring(env, input))
+}
+
+/// Converts a `java.util.List<Integer>` into a rust i32 vec, which is used
+/// instead of an `int[]` with `#[roast(java_collection)]`.
+///
+/// If the list is `null`, a `java.lang.NullPointerException` is thrown
+/// and an empty vec is returned. `null` elements are not supported.
+#[inline]
+pub fn convert_arg_jlist_i32(env: &JNIEnv, input: JObject) -> Vec<i32> {
+    if input.is_null() {
+        env.throw_new("java/lang/NullPointerException", "list must not be null")
+            .expect("Could not throw java exception for null list!");
+        return vec![];
+    }
+    env.get_list(input)
+        .and_then(|list| {
+            list.iter()?
+                .map(|element| env.call_method(element, "intValue", "()I", &[])?.i())
+                .collect()
+        })
+        .expect("Could not convert java list into a i32 vec!")
+}
+
+/// Converts a rust i32 vec into a new `java.util.ArrayList` of boxed
+/// `Integer` values.
+///
+/// If an exception is already pending, `null` is returned instead.
+#[inline]
+pub fn convert_retval_list_i32(env: &JNIEnv, input: Vec<i32>) -> jobject {
+    if env
+        .exception_check()
+        .expect("Could not check for pending java exception!")
+    {
+        return std::ptr::null_mut();
+    }
+    let output = env
+        .new_object("java/util/ArrayList", "(I)V", &[JValue::Int(input.len() as jint)])
+        .expect("Could not create java array list!");
+    let list = env.get_list(output).expect("Could not access java list!");
+    for element in input {
+        list.add(JObject::from(element.into_java_object(env)))
+            .expect("Could not add value to java list!");
+    }
+    output.into_inner()
+}
+
+/// Serializes a rust value into a JSON java string, which is used for
+/// return values with `#[roast(json)]`.
+///
+/// If the value cannot be serialized, a `java.lang.RuntimeException` is
+/// thrown and `null` is returned.
+#[inline]
+pub fn convert_retval_json<T: serde::Serialize>(env: &JNIEnv, input: T) -> jstring {
+    match serde_json::to_string(&input) {
+        Ok(json) => convert_retval_string(env, json),
+        Err(e) => {
+            env.throw_new(
+                "java/lang/RuntimeException",
+                format!("Could not serialize return value into JSON: {}", e),
+            )
+            .expect("Could not throw java exception for JSON error!");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Converts a `Result<String, String>`, as commonly returned by parsing
+/// functions, into a nullable java string.
+///
+/// The `Err` message is thrown as a `java.lang.IllegalArgumentException`,
+/// since it usually describes why the input was rejected, and `null` is
+/// returned while the exception is pending.
+#[inline]
+pub fn convert_retval_result_string_string(
+    env: &JNIEnv,
+    input: Result<String, String>,
+) -> jstring {
+    match input {
+        Ok(s) => convert_retval_string(env, s),
+        Err(e) => {
+            env.throw_new("java/lang/IllegalArgumentException", e)
+                .expect("Could not throw java exception for error return value!");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Converts a rust result into its java equivalent.
+///
+/// The `Ok` value is passed through the given conversion function, while
+/// the `Err` value is thrown as a `java.lang.RuntimeException` carrying the
+/// error message. In the error case a zero sentinel is returned which the
+/// JVM ignores since an exception is pending.
+///
+/// While named after the most common `Box<dyn Error>` case, every error
+/// which implements `Display` (like `anyhow::Error`) is supported.
+#[inline]
+pub fn convert_retval_result_box_dyn_error<T, E, R>(
+    env: &JNIEnv,
+    input: Result<T, E>,
+    convert: fn(&JNIEnv, T) -> R,
+) -> R
+where
+    E: Display,
+    R: JniSentinel,
+{
+    match input {
+        Ok(v) => convert(env, v),
+        Err(e) => {
+            env.throw_new("java/lang/RuntimeException", e.to_string())
+                .expect("Could not throw java exception for error return value!");
+            R::sentinel()
+        }
+    }
+}
+
+/// Converts a `java.util.function.IntSupplier` argument into a rust closure.
+///
+/// The java object is pinned through a global reference for as long as the
+/// closure is alive, so it may be stored and called after the native method
+/// returned. Every call attaches the current thread to the JVM if needed,
+/// which makes it possible to call the closure from other threads as well.
+/// Note though that the java side needs to be thread-safe in that case and
+/// that the closure must not outlive the JVM itself.
+#[inline]
+pub fn convert_arg_jcallback_int_supplier(
+    env: &JNIEnv,
+    input: JObject,
+) -> Box<dyn Fn() -> i32 + Send + Sync> {
+    let vm = env.get_java_vm().expect("Could not get java vm for callback!");
+    let callback = crate::ffi_utils::global_ref(env, input);
+    Box::new(move || {
+        crate::ffi_utils::with_jni_env(&vm, |env| {
+            env.call_method(callback.as_obj(), "getAsInt", "()I", &[])
+                .and_then(|v| v.i())
+                .expect("Could not call IntSupplier callback!")
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::ptr::NonNull;
+
+    /// Runs the closure with an env that must never be dereferenced, which
+    /// is fine for the primitive conversions since they ignore it.
+    fn with_dummy_env<F: FnOnce(&JNIEnv)>(f: F) {
+        let raw = NonNull::<jni::sys::JNIEnv>::dangling();
+        let env = unsafe { JNIEnv::from_raw(raw.as_ptr()) }.unwrap();
+        f(&env)
+    }
+
+    #[test]
+    fn retval_u8() {
+        with_dummy_env(|env| {
+            assert_eq!(0, convert_retval_u8(env, u8::MIN));
+            assert_eq!(255, convert_retval_u8(env, u8::MAX));
+        });
+    }
+
+    #[test]
+    fn arg_jdouble_to_f32_lossy() {
+        with_dummy_env(|env| {
+            assert_eq!(1.5, convert_arg_jdouble_to_f32_lossy(env, 1.5));
+            assert_eq!(0.1f32, convert_arg_jdouble_to_f32_lossy(env, 0.1));
+            assert_eq!(f32::INFINITY, convert_arg_jdouble_to_f32_lossy(env, f64::MAX));
+            assert!(convert_arg_jdouble_to_f32_lossy(env, f64::NAN).is_nan());
+        });
+    }
+
+    #[test]
+    fn retval_u16() {
+        with_dummy_env(|env| {
+            assert_eq!(0, convert_retval_u16(env, u16::MIN));
+            assert_eq!(65535, convert_retval_u16(env, u16::MAX));
+            assert_eq!(u16::MAX, convert_arg_jchar(env, 65535));
+        });
+    }
+
+    #[test]
+    fn retval_u32() {
+        with_dummy_env(|env| {
+            assert_eq!(0, convert_retval_u32(env, u32::MIN));
+            assert_eq!(4_294_967_295, convert_retval_u32(env, u32::MAX));
+        });
+    }
+
+    #[test]
+    fn path_to_string_valid() {
+        assert_eq!("/tmp/roast.txt", path_to_string(PathBuf::from("/tmp/roast.txt")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn path_to_string_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = PathBuf::from(OsStr::from_bytes(b"/tmp/ro\xffst"));
+        assert_eq!("/tmp/ro\u{FFFD}st", path_to_string(path));
+    }
+
+    #[test]
+    fn ptr_round_trip() {
+        with_dummy_env(|env| {
+            let ptr = Box::into_raw(Box::new(42));
+            let handle = convert_retval_ptr_to_jlong(env, ptr);
+            assert_eq!(ptr as usize as jlong, handle);
+            let restored = convert_arg_jlong_to_ptr::<i32>(env, handle);
+            assert_eq!(ptr, restored);
+            assert_eq!(42, *unsafe { Box::from_raw(restored) });
+            assert!(convert_arg_jlong_to_ptr::<i32>(env, 0).is_null());
+        });
+    }
+
+    #[test]
+    fn retval_u64() {
+        with_dummy_env(|env| {
+            assert_eq!(0, convert_retval_u64(env, u64::MIN));
+            assert_eq!(i64::MAX, convert_retval_u64(env, i64::MAX as u64));
+            assert_eq!(i64::MIN, convert_retval_u64(env, i64::MAX as u64 + 1));
+            assert_eq!(-1, convert_retval_u64(env, u64::MAX));
+        });
+    }
+}
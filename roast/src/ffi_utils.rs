@@ -0,0 +1,48 @@
+//! Safe wrappers for JNI patterns which come up in hand-written JNI code
+//! again and again.
+
+use jni::objects::{GlobalRef, JObject, JValue};
+use jni::{JNIEnv, JavaVM};
+
+/// Calls the closure with the env of the current thread, which is attached
+/// to the JVM for the duration of the call if needed.
+///
+/// Threads which are attached already, like the one a native method is
+/// called on, stay attached afterwards. Others are detached again, so the
+/// closure is meant for the occasional call from a rust thread, and a
+/// thread calling into java all the time should stay attached instead.
+///
+/// Panics if the thread can't be attached.
+pub fn with_jni_env<F, R>(vm: &JavaVM, f: F) -> R
+where
+    F: FnOnce(&JNIEnv) -> R,
+{
+    let env = vm
+        .attach_current_thread()
+        .expect("Could not attach thread to the JVM!");
+    f(&env)
+}
+
+/// Creates a global reference to the object, which keeps it alive after
+/// the native method returned and may be sent to other threads.
+///
+/// Panics if the reference can't be created, which only happens if the JVM
+/// is out of memory.
+pub fn global_ref(env: &JNIEnv, obj: JObject) -> GlobalRef {
+    env.new_global_ref(obj).expect("Could not create global reference!")
+}
+
+/// Calls a java method returning `void`, where `sig` is the JNI descriptor
+/// of the method like `()V`.
+///
+/// Fails if the method can't be found, doesn't return `void` or throws an
+/// exception, which is left pending in the latter case.
+pub fn call_void_method(
+    env: &JNIEnv,
+    obj: JObject,
+    name: &str,
+    sig: &str,
+    args: &[JValue],
+) -> jni::errors::Result<()> {
+    env.call_method(obj, name, sig, args)?.v()
+}
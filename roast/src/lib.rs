@@ -1,11 +1,48 @@
 #[doc(hidden)]
 pub use roast_derives::*;
+pub use roast_macros::{jni_call, jni_string, jni_try};
 
-pub use jni::objects::{JClass, JString};
+pub use jni::objects::{JClass, JObject, JString};
 pub use jni::sys::*;
-pub use jni::JNIEnv;
+pub use jni::{JNIEnv, NativeMethod};
 
 pub mod build;
 pub mod convert;
+pub mod ffi_utils;
+pub mod migrations;
+pub mod swift;
 
 pub use convert::*;
+pub use ffi_utils::*;
+
+/// Generates the `main` function of a `build.rs` which writes the
+/// `roast.json` picked up by `roast build`.
+///
+/// Without arguments the defaults of `BuildConfigBuilder` are used, every
+/// setting can be overridden as a keyword argument:
+///
+/// ```ignore
+/// roast::roast_build! {
+///     name = "mylib",
+///     java_target = "android/app/src/main/java",
+/// }
+/// ```
+#[macro_export]
+macro_rules! roast_build {
+    (@set $builder:expr, root, $value:expr) => {
+        $builder.set_root($value)
+    };
+    (@set $builder:expr, name, $value:expr) => {
+        $builder.set_name($value)
+    };
+    (@set $builder:expr, $key:ident, $value:expr) => {
+        $builder.$key($value)
+    };
+    ($($key:ident = $value:expr),* $(,)?) => {
+        fn main() {
+            let builder = $crate::build::BuildConfigBuilder::new();
+            $(let builder = $crate::roast_build!(@set builder, $key, $value);)*
+            $crate::build::build(builder.finish());
+        }
+    };
+}
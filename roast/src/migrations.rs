@@ -0,0 +1,128 @@
+//! Migrations of `roast.json` files written by older versions of roast.
+//!
+//! Every change to the `BuildConfig` format bumps the `SCHEMA_VERSION` and
+//! adds a `migrate_vN_to_vM` step, which `migrate` applies in order until the
+//! config is up to date.
+
+use crate::build::Error;
+use serde_json::Value;
+
+/// The schema version of the `roast.json` written by this version of roast.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Returns the schema version of a decoded config.
+///
+/// Configs without a `schema_version` predate the field and are version 1.
+pub fn schema_version(config: &Value) -> u32 {
+    config
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .map_or(1, |version| version as u32)
+}
+
+/// Migrates a decoded config to the current `SCHEMA_VERSION`.
+///
+/// Fails if the config was written by a newer version of roast, since the
+/// meaning of its fields is unknown.
+pub fn migrate(mut config: Value) -> Result<Value, Error> {
+    loop {
+        config = match schema_version(&config) {
+            1 => migrate_v1_to_v2(config),
+            2 => migrate_v2_to_v3(config),
+            SCHEMA_VERSION => return Ok(config),
+            version => return Err(Error::UnsupportedSchema(version)),
+        };
+    }
+}
+
+/// Adds the `schema_version` and the optional settings which were added
+/// after the first release, set to `null` so they fall back to their
+/// defaults.
+pub fn migrate_v1_to_v2(mut config: Value) -> Value {
+    if let Some(object) = config.as_object_mut() {
+        for key in &[
+            "android_abis",
+            "module_name",
+            "jni_include_path",
+            "java_codegen_dir",
+        ] {
+            object.entry(*key).or_insert(Value::Null);
+        }
+        object.insert(String::from("schema_version"), Value::from(2));
+    }
+    config
+}
+
+/// Adds the `out_dir` of the build script, set to `null` since it is only
+/// known to the next build.
+pub fn migrate_v2_to_v3(mut config: Value) -> Value {
+    if let Some(object) = config.as_object_mut() {
+        object.entry("out_dir").or_insert(Value::Null);
+        object.insert(String::from("schema_version"), Value::from(3));
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn v1_to_v2() {
+        let migrated = migrate_v1_to_v2(json!({
+            "root": "/roast",
+            "name": "hello",
+            "module_name": "com.example.hello"
+        }));
+        assert_eq!(
+            json!({
+                "root": "/roast",
+                "name": "hello",
+                "android_abis": null,
+                "module_name": "com.example.hello",
+                "jni_include_path": null,
+                "java_codegen_dir": null,
+                "schema_version": 2
+            }),
+            migrated
+        );
+    }
+
+    #[test]
+    fn v2_to_v3() {
+        let migrated = migrate_v2_to_v3(json!({ "root": "/roast", "schema_version": 2 }));
+        assert_eq!(
+            json!({ "root": "/roast", "out_dir": null, "schema_version": 3 }),
+            migrated
+        );
+    }
+
+    #[test]
+    fn migrate_to_current_version() {
+        let config = json!({
+            "root": "/roast",
+            "name": "hello",
+            "bin_source": "/roast/target/debug",
+            "bin_target": "/roast/src/main/resources",
+            "java_source": "/roast/target/java",
+            "java_target": "/roast/src/main"
+        });
+        assert_eq!(1, schema_version(&config));
+        let migrated = migrate(config).unwrap();
+        assert_eq!(SCHEMA_VERSION, schema_version(&migrated));
+        assert_eq!(migrated, migrate(migrated.clone()).unwrap());
+        let decoded = crate::build::from_json_str(&migrated.to_string()).unwrap();
+        assert_eq!(SCHEMA_VERSION, decoded.schema_version());
+    }
+
+    #[test]
+    fn migrate_newer_version() {
+        let config = json!({ "root": "/roast", "schema_version": SCHEMA_VERSION + 1 });
+        assert!(matches!(
+            migrate(config),
+            Err(Error::UnsupportedSchema(version)) if version == SCHEMA_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+//! Conversions for the experimental swift bindings, which pass strings as
+//! C strings.
+//!
+//! These are called by the functions generated for `export_swift_syntax`,
+//! which are only compiled for apple targets.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+
+/// Converts a C string argument from swift into a rust string, replacing
+/// invalid UTF-8 with the replacement character.
+///
+/// # Safety
+///
+/// The pointer must point to a valid, nul-terminated C string.
+#[inline]
+pub unsafe fn convert_arg_cstring(input: *const c_char) -> String {
+    assert!(!input.is_null(), "C string argument is null");
+    CStr::from_ptr(input).to_string_lossy().into_owned()
+}
+
+/// Converts a rust string into a C string handed to swift, which has to
+/// pass it back into `free_cstring` once it copied it.
+///
+/// Strings containing a nul byte are cut off there, since C strings can't
+/// represent them.
+#[inline]
+pub fn convert_retval_cstring(input: String) -> *mut c_char {
+    let mut bytes = input.into_bytes();
+    if let Some(nul) = bytes.iter().position(|&b| b == 0) {
+        bytes.truncate(nul);
+    }
+    CString::new(bytes)
+        .expect("C string contains no nul byte anymore")
+        .into_raw()
+}
+
+/// Frees a C string created by `convert_retval_cstring`, where a null
+/// pointer is ignored.
+///
+/// # Safety
+///
+/// The string must have been created by `convert_retval_cstring` and must
+/// not be used anymore afterwards.
+#[inline]
+pub unsafe fn free_cstring(input: *mut c_char) {
+    if !input.is_null() {
+        drop(CString::from_raw(input));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn cstring_round_trip() {
+        let raw = convert_retval_cstring(String::from("roast ✓"));
+        assert_eq!("roast ✓", unsafe { convert_arg_cstring(raw) });
+        unsafe { free_cstring(raw) };
+    }
+
+    #[test]
+    fn cstring_cut_at_nul() {
+        let raw = convert_retval_cstring(String::from("ro\0ast"));
+        assert_eq!("ro", unsafe { convert_arg_cstring(raw) });
+        unsafe { free_cstring(raw) };
+        unsafe { free_cstring(std::ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,25 @@
+//! Runs `roast analyze` as the cargo subcommand `cargo roast-analyze`.
+//!
+//! Cargo passes the subcommand name as the first argument, which is skipped
+//! before the remaining arguments are handed to `roast analyze`.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::{exit, Command};
+
+fn main() {
+    let args = env::args().skip(1).skip_while(|arg| arg == "roast-analyze");
+    // prefer the roast binary installed next to this one over the PATH
+    let roast = env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(format!("roast{}", env::consts::EXE_SUFFIX)))
+        .filter(|roast| roast.exists())
+        .unwrap_or_else(|| PathBuf::from("roast"));
+    match Command::new(&roast).arg("analyze").args(args).status() {
+        Ok(status) => exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            eprintln!("Could not run {}: {}", roast.display(), e);
+            exit(1);
+        }
+    }
+}
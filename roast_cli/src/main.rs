@@ -1,28 +1,124 @@
 use git2::{Config, Repository};
-use log::{debug, error, info};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
 use roast::build;
+use roast::build::BuildConfig;
+use roast::migrations;
+use serde_derive::Serialize;
+use std::env;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{exit, Command, Output};
-use std::str::from_utf8;
+use std::str::{from_utf8, FromStr};
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info, info_span, instrument, warn, Level};
+use tracing_subscriber::EnvFilter;
 
 use structopt::StructOpt;
-use clap::arg_enum;
+use clap::{arg_enum, AppSettings};
 
 include!(concat!(env!("OUT_DIR"), "/templates.rs"));
 
+/// The build flavor of a new project, which picks its templates.
+#[derive(Debug)]
+enum Flavor {
+    Maven,
+    /// a swift package calling into rust, which is experimental
+    SwiftSpm,
+}
+
+impl Flavor {
+    fn variants() -> [&'static str; 2] {
+        ["maven", "swift-spm"]
+    }
+}
+
+impl FromStr for Flavor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "maven" => Ok(Flavor::Maven),
+            "swift-spm" => Ok(Flavor::SwiftSpm),
+            _ => Err(format!("valid values: {}", Flavor::variants().join(", "))),
+        }
+    }
+}
+
+impl fmt::Display for Flavor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Flavor::Maven => "maven",
+            Flavor::SwiftSpm => "swift-spm",
+        })
+    }
+}
+
+arg_enum! {
+    #[derive(Debug, PartialEq)]
+    enum Format {
+        Text,
+        Json,
+    }
+}
+
+arg_enum! {
+    #[derive(Debug, PartialEq)]
+    enum LogFormat {
+        Text,
+        Json,
+    }
+}
+
+arg_enum! {
+    #[derive(Debug, Clone, Copy)]
+    enum Profile {
+        Debug,
+        Release,
+    }
+}
+
+arg_enum! {
+    #[derive(Debug)]
+    enum Ide {
+        Vscode,
+        Intellij,
+    }
+}
+
 arg_enum! {
     #[derive(Debug)]
-    enum Flavor {
-        Maven,
+    enum License {
+        Apache2,
+        Mit,
+        None,
     }
 }
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "roast")]
+#[structopt(name = "roast", global_settings = &[AppSettings::VersionlessSubcommands])]
 struct Roast {
     #[structopt(short = "v", long = "verbose", parse(from_occurrences))]
     verbose: u8,
+    #[structopt(
+        long = "format",
+        help = "Sets the output format, json prints a single result object",
+        possible_values = &Format::variants(),
+        case_insensitive = true,
+        default_value = "Text",
+        global = true
+    )]
+    format: Format,
+    #[structopt(
+        long = "log-format",
+        help = "Sets the format of the log output",
+        possible_values = &LogFormat::variants(),
+        case_insensitive = true,
+        default_value = "Text",
+        global = true
+    )]
+    log_format: LogFormat,
     #[structopt(subcommand)]
     cmd: RoastCommand,
 }
@@ -33,7 +129,97 @@ enum RoastCommand {
         name = "build",
         about = "Builds and generates the artifacts and source files"
     )]
-    Build,
+    Build {
+        #[structopt(long = "release", help = "Builds the rust project in release mode")]
+        release: bool,
+        #[structopt(long = "watch", help = "Rebuilds whenever a rust source file changes")]
+        watch: bool,
+        #[structopt(
+            long = "with-c-header",
+            help = "Copies the generated C headers into the include directory"
+        )]
+        with_c_header: bool,
+        #[structopt(
+            long = "manifest-path",
+            help = "Builds the crate at the given Cargo.toml or directory",
+            parse(from_os_str)
+        )]
+        manifest_path: Option<PathBuf>,
+        #[structopt(
+            long = "module-name",
+            help = "Generates a module-info.java for the given JPMS module name"
+        )]
+        module_name: Option<String>,
+        #[structopt(
+            long = "no-java-copy",
+            help = "Only builds the rust project without copying the library and java sources"
+        )]
+        no_java_copy: bool,
+        #[structopt(
+            long = "symbols-output",
+            help = "Writes the list of generated JNI symbols to the given path \
+                    instead of roast-symbols.json",
+            parse(from_os_str)
+        )]
+        symbols_output: Option<PathBuf>,
+        #[structopt(
+            long = "experimental-swift",
+            help = "Copies the experimental swift bindings into the swift directory"
+        )]
+        experimental_swift: bool,
+    },
+    #[structopt(
+        name = "init",
+        about = "Generates a profile specific roast-<profile>.json from roast.json"
+    )]
+    Init {
+        #[structopt(
+            long = "profile",
+            help = "Sets the build profile to generate the config for",
+            possible_values = &Profile::variants(),
+            case_insensitive = true,
+            default_value = "Release"
+        )]
+        profile: Profile,
+    },
+    #[structopt(
+        name = "info",
+        about = "Shows the roast.json of the project and the built artifacts"
+    )]
+    Info {
+        #[structopt(long = "release", help = "Shows the config of the release profile")]
+        release: bool,
+        #[structopt(
+            long = "manifest-path",
+            help = "Shows the crate at the given Cargo.toml or directory",
+            parse(from_os_str)
+        )]
+        manifest_path: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "analyze",
+        about = "Writes the exported methods to roast_export.json for ROAST_METADATA_PATH"
+    )]
+    Analyze {
+        #[structopt(
+            long = "manifest-path",
+            help = "Analyzes the crate at the given Cargo.toml or directory",
+            parse(from_os_str)
+        )]
+        manifest_path: Option<PathBuf>,
+        #[structopt(
+            long = "output",
+            help = "Writes the methods to the given path instead of roast_export.json \
+                    in the crate root",
+            parse(from_os_str)
+        )]
+        output: Option<PathBuf>,
+    },
+    #[structopt(
+        name = "migrate",
+        about = "Updates roast.json and the profile configs to the current schema"
+    )]
+    Migrate,
     #[structopt(name = "new", about = "Generates a new roast project")]
     New {
         #[structopt(help = "The name of the project")]
@@ -45,32 +231,220 @@ enum RoastCommand {
             help = "Sets the group id for the java project"
         )]
         group_id: Option<String>,
+        #[structopt(
+            long = "artifact-id",
+            help = "Sets the artifact id for the java project, defaults to the name"
+        )]
+        artifact_id: Option<String>,
+        #[structopt(
+            long = "version",
+            help = "Sets the version of the project",
+            default_value = "0.1.0"
+        )]
+        version: String,
         #[structopt(
             short = "f",
             long = "flavor",
-            help = "Sets the java build flavor of the project",
+            help = "Sets the build flavor of the project, where swift-spm is experimental",
             possible_values = &Flavor::variants(), 
             case_insensitive = true,
             default_value = "Maven"
         )]
         flavor: Flavor,
+        #[structopt(
+            long = "description",
+            help = "Sets the description of the project",
+            default_value = "A roast-based JNI library"
+        )]
+        description: String,
+        #[structopt(
+            long = "license",
+            help = "Adds a license header to the generated source files",
+            possible_values = &License::variants(),
+            case_insensitive = true,
+            default_value = "None"
+        )]
+        license: License,
+        #[structopt(
+            long = "ide",
+            help = "Generates the configuration to run roast build from the IDE",
+            possible_values = &Ide::variants(),
+            case_insensitive = true
+        )]
+        ide: Option<Ide>,
+        #[structopt(
+            long = "maven-wrapper",
+            help = "Adds the Maven wrapper, so the project builds without a Maven installation"
+        )]
+        maven_wrapper: bool,
+        #[structopt(
+            long = "java-version",
+            help = "Sets the java version of the GitHub Actions workflow",
+            default_value = "17"
+        )]
+        java_version: String,
     },
 }
 
+/// The result of a command, printed to stdout in json format.
+#[derive(Debug, Serialize)]
+struct RoastResult {
+    status: String,
+    generated_files: Vec<String>,
+    errors: Vec<String>,
+    elapsed_ms: u64,
+}
+
 fn main() {
     let args = Roast::from_args();
+    let start = Instant::now();
 
-    // Always log info level as well (+1)
-    loggerv::init_with_verbosity(u64::from(args.verbose) + 1)
-        .expect("Could not initialize the logger");
+    // In json mode stdout must only contain the result, so no logging
+    if args.format == Format::Text {
+        init_tracing(args.verbose, &args.log_format);
+    }
 
-    match args.cmd {
-        RoastCommand::Build => run_build(),
+    let result = match args.cmd {
+        RoastCommand::Build {
+            release,
+            watch,
+            with_c_header,
+            manifest_path,
+            module_name,
+            no_java_copy,
+            symbols_output,
+            experimental_swift,
+        } => {
+            let profile = if release {
+                Profile::Release
+            } else {
+                Profile::Debug
+            };
+            let manifest_path = manifest_path.as_deref();
+            let module_name = module_name.as_deref();
+            let symbols_output = symbols_output.as_deref();
+            if watch {
+                run_watch(
+                    profile,
+                    with_c_header,
+                    manifest_path,
+                    module_name,
+                    no_java_copy,
+                    symbols_output,
+                    experimental_swift,
+                    &args.format,
+                )
+            } else {
+                run_build(
+                    profile,
+                    with_c_header,
+                    manifest_path,
+                    module_name,
+                    no_java_copy,
+                    symbols_output,
+                    experimental_swift,
+                )
+            }
+        }
+        RoastCommand::Init { profile } => run_init(profile),
+        RoastCommand::Info {
+            release,
+            manifest_path,
+        } => {
+            let profile = if release {
+                Profile::Release
+            } else {
+                Profile::Debug
+            };
+            run_info(profile, manifest_path.as_deref(), &args.format)
+        }
+        RoastCommand::Analyze {
+            manifest_path,
+            output,
+        } => run_analyze(manifest_path.as_deref(), output.as_deref()),
+        RoastCommand::Migrate => run_migrate(),
         RoastCommand::New {
             name,
             group_id,
+            artifact_id,
+            version,
+            flavor,
+            description,
+            license,
+            ide,
+            maven_wrapper,
+            java_version,
+        } => run_new(
+            name,
+            group_id,
+            artifact_id,
+            version,
             flavor,
-        } => run_new(name, group_id, flavor),
+            description,
+            license,
+            ide,
+            maven_wrapper,
+            java_version,
+        ),
+    };
+
+    if report(&args.format, result, start) {
+        exit(1);
+    }
+}
+
+/// Reports the result of a command in the given format.
+///
+/// Returns true if the command failed.
+fn report(format: &Format, result: Result<Vec<String>, String>, start: Instant) -> bool {
+    let failed = result.is_err();
+    match format {
+        Format::Text => {
+            if let Err(e) = result {
+                error!("{}", e);
+            }
+        }
+        Format::Json => {
+            let (status, generated_files, errors) = match result {
+                Ok(files) => ("success", files, vec![]),
+                Err(e) => ("error", vec![], vec![e]),
+            };
+            let output = RoastResult {
+                status: status.into(),
+                generated_files,
+                errors,
+                elapsed_ms: start.elapsed().as_millis() as u64,
+            };
+            println!(
+                "{}",
+                serde_json::to_string(&output).expect("could not convert result")
+            );
+        }
+    }
+    failed
+}
+
+/// Sets up the tracing subscriber which prints the log output to stderr.
+///
+/// The level is derived from the verbosity flags (`-v` for info, `-vv` for
+/// debug and `-vvv` for trace) unless `RUST_LOG` is set, which allows for
+/// more fine grained filtering.
+fn init_tracing(verbose: u8, log_format: &LogFormat) {
+    let level = match verbose {
+        0 => Level::WARN,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(level.to_string()));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+    match log_format {
+        LogFormat::Text => builder.without_time().init(),
+        LogFormat::Json => builder.json().init(),
     }
 }
 
@@ -80,59 +454,839 @@ fn main() {
 /// then copies the compiled library into a place where
 /// java can pick it up and then also copies the generated
 /// java files into java's scope.
-fn run_build() {
+///
+/// If a manifest path is given, that crate is built and its `roast.json`
+/// is used instead of the one in the current directory. The module name
+/// takes precedence over the `module_name` of the config.
+///
+/// With `no_java_copy` nothing is copied into the java project, which is
+/// useful if other tooling like a CMake superbuild takes care of that.
+///
+/// The generated JNI symbols are listed in `roast-symbols.json` in the root
+/// of the project, or at `symbols_output` if given.
+///
+/// Returns the list of files copied into the java project.
+#[instrument]
+fn run_build(
+    profile: Profile,
+    with_c_header: bool,
+    manifest_path: Option<&Path>,
+    module_name: Option<&str>,
+    no_java_copy: bool,
+    symbols_output: Option<&Path>,
+    experimental_swift: bool,
+) -> Result<Vec<String>, String> {
     info!("Building the rust project via `cargo build` (this may take a while)");
 
-    match Command::new("cargo").arg("build").arg("-vv").output() {
+    let cargo_span = info_span!("cargo_build").entered();
+    let mut cargo = Command::new("cargo");
+    cargo.arg("build").arg("-vv");
+    if let Profile::Release = profile {
+        cargo.arg("--release");
+    }
+    // the derive only generates the swift bindings on request
+    if experimental_swift {
+        cargo.env("ROAST_EXPERIMENTAL_SWIFT", "1");
+    }
+    let crate_dir = match manifest_path {
+        Some(p) => {
+            let (dir, manifest) = resolve_manifest_path(p);
+            cargo.arg("--manifest-path").arg(manifest);
+            dir
+        }
+        None => PathBuf::new(),
+    };
+    match cargo.output() {
         Ok(ref o) if o.status.success() => {
             debug!("`cargo build -vv` result {}", convert_output(&o))
         }
-        Ok(e) => {
-            error!("`cargo build -vv` failed! {}", convert_output(&e));
-            exit(1);
-        }
-        Err(e) => {
-            error!("`cargo build -vv` failed! {}", e);
-            exit(1);
-        }
+        Ok(e) => return Err(format!("`cargo build -vv` failed! {}", convert_output(&e))),
+        Err(e) => return Err(format!("`cargo build -vv` failed! {}", e)),
     };
-    let path = "roast.json";
+    cargo_span.exit();
+
+    let path = config_path(&profile, &crate_dir);
     let spec = build::config_from_path(&path);
     debug!("Spec loaded from path {}:\n{:#?}", &path, &spec);
 
+    let mut generated_files = vec![];
+    if no_java_copy {
+        info!("Skipping the copy of the build artifacts into java scope");
+    } else {
+        generated_files.extend(copy_build_outputs(&spec, profile)?);
+        if let Some(module_name) = module_name.or_else(|| spec.module_name()) {
+            generated_files.extend(write_module_info(&spec, module_name)?);
+        }
+    }
+    if with_c_header {
+        generated_files.extend(copy_c_headers(&spec)?);
+    }
+    if experimental_swift {
+        generated_files.extend(copy_swift_sources(&spec)?);
+    }
+    let symbols_output = match symbols_output {
+        Some(p) => p.to_path_buf(),
+        None => Path::new(spec.root()).join("roast-symbols.json"),
+    };
+    generated_files.push(write_jni_symbols(&spec, &symbols_output)?);
+
+    info!("Build complete! Enjoy your roast!");
+    Ok(generated_files)
+}
+
+/// The `analyze` command extracts the exported methods ahead of the build.
+///
+/// It builds the crate with the source scan of the derive, by setting an
+/// empty `ROAST_METADATA_PATH`, and copies the `roast_export.json` of the
+/// build into the crate root or to `output`. Later builds read the methods
+/// from there once `ROAST_METADATA_PATH` points to the file.
+///
+/// Returns the path of the written file.
+#[instrument]
+fn run_analyze(manifest_path: Option<&Path>, output: Option<&Path>) -> Result<Vec<String>, String> {
+    info!("Scanning the exported methods via `cargo build`");
+    let mut cargo = Command::new("cargo");
+    cargo.arg("build").env("ROAST_METADATA_PATH", "");
+    let crate_dir = match manifest_path {
+        Some(p) => {
+            let (dir, manifest) = resolve_manifest_path(p);
+            cargo.arg("--manifest-path").arg(manifest);
+            dir
+        }
+        None => PathBuf::new(),
+    };
+    match cargo.output() {
+        Ok(ref o) if o.status.success() => debug!("`cargo build` result {}", convert_output(o)),
+        Ok(e) => return Err(format!("`cargo build` failed! {}", convert_output(&e))),
+        Err(e) => return Err(format!("`cargo build` failed! {}", e)),
+    };
+
+    let spec = build::config_from_path(&config_path(&Profile::Debug, &crate_dir));
+    let to = match output {
+        Some(p) => p.to_path_buf(),
+        None => Path::new(spec.root()).join("roast_export.json"),
+    };
+    copy_export_metadata(&spec, &to)?;
+    Ok(vec![to.display().to_string()])
+}
+
+/// Copies the `roast_export.json` the derive wrote into the `OUT_DIR`.
+fn copy_export_metadata(spec: &BuildConfig, to: &Path) -> Result<(), String> {
+    let from = derive_output_dir(spec, "roast_export.json");
+    if !from.exists() {
+        return Err(format!(
+            "{} not found, does the crate derive RoastExport?",
+            from.display()
+        ));
+    }
+    fs::copy(&from, to)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to copy {} to {}: {}", from.display(), to.display(), e))
+}
+
+/// Resolves a `--manifest-path`, which either points to a `Cargo.toml` or
+/// the directory containing it, into the crate directory and the manifest.
+fn resolve_manifest_path(path: &Path) -> (PathBuf, PathBuf) {
+    if path.is_dir() {
+        (path.to_path_buf(), path.join("Cargo.toml"))
+    } else {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        (dir.to_path_buf(), path.to_path_buf())
+    }
+}
+
+/// Copies the compiled library and the generated java sources of a build
+/// into the java project, creating the target directories if needed.
+///
+/// Returns the list of copied files.
+fn copy_build_outputs(spec: &BuildConfig, profile: Profile) -> Result<Vec<String>, String> {
+    let artifact_span = info_span!("copy_artifacts").entered();
     info!("Copying build artifact into java scope");
-    let extension = if cfg!(target_os = "windows") {
+    let extension = library_extension(spec);
+    info!("{}", extension);
+    let mut generated_files = vec![];
+    match spec.android_abis() {
+        Some(abis) => {
+            for abi in abis {
+                generated_files.push(copy_android_artifact(spec, abi, extension, profile)?);
+            }
+        }
+        None => {
+            let from = format!("{}/lib{}.{}", spec.bin_source(), spec.name(), extension);
+            let to = format!("{}/lib{}.{}", spec.bin_target(), spec.name(), extension);
+            create_dir(Path::new(spec.bin_target()))?;
+            copy_artifact(&from, &to)?;
+            generated_files.push(to);
+        }
+    }
+    artifact_span.exit();
+
+    let java_span = info_span!("copy_java_sources").entered();
+    info!("Copying generated java sources into java scope");
+    let from = spec.java_source();
+    for to in &java_targets(spec) {
+        generated_files.extend(copy_java_sources(from, to)?);
+    }
+    java_span.exit();
+
+    Ok(generated_files)
+}
+
+/// Returns the file extension of the native library, which depends on the
+/// platform it is built for.
+fn library_extension(spec: &BuildConfig) -> &'static str {
+    if spec.android_abis().is_some() {
+        "so"
+    } else if cfg!(target_os = "windows") {
         "dll"
     } else if cfg!(target_os = "macos") {
         "dylib"
     } else {
         "so"
-    };
-    info!("{}", extension);
-    let from = format!("{}/lib{}.{}", spec.bin_source(), spec.name(), extension);
-    let to = format!("{}/lib{}.{}", spec.bin_target(), spec.name(), extension);
-    debug!("Copying from {} to {}", from, to);
-    match fs::copy(from, to) {
-        Ok(_) => debug!("Copying completed"),
-        Err(e) => {
-            error!("Failed to copy artifacts: {}", e);
-            exit(1);
+    }
+}
+
+/// Returns the directories the generated java sources are copied into, the
+/// `java_target` of the config plus any in `ROAST_EXTRA_JAVA_TARGETS`.
+fn java_targets(spec: &BuildConfig) -> Vec<PathBuf> {
+    let mut targets = vec![PathBuf::from(spec.java_target())];
+    if let Some(extra) = env::var_os("ROAST_EXTRA_JAVA_TARGETS") {
+        targets.extend(env::split_paths(&extra).filter(|p| !p.as_os_str().is_empty()));
+    }
+    targets
+}
+
+/// Writes the `module-info.java` for the given module name into the root of
+/// the copied java sources of every java target, which exports every
+/// package of the generated classes.
+///
+/// Since a named module can't contain classes of the unnamed package, this
+/// fails if any generated class has no `#[roast(package = "...")]`.
+///
+/// Returns the list of written files.
+fn write_module_info(spec: &BuildConfig, module_name: &str) -> Result<Vec<String>, String> {
+    info!("Generating module-info.java for module {}", module_name);
+    let mut packages = vec![];
+    for source in list_files(Path::new(spec.java_source())) {
+        if source.extension().and_then(|e| e.to_str()) != Some("java") {
+            continue;
         }
-    };
+        match source.parent().filter(|p| !p.as_os_str().is_empty()) {
+            Some(dir) => packages.push(
+                dir.iter()
+                    .map(|part| part.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("."),
+            ),
+            None => {
+                return Err(format!(
+                    "--module-name requires a package for every class, but {} is in the \
+                     unnamed package. Add #[roast(package = \"...\")] to its struct.",
+                    source.display()
+                ))
+            }
+        }
+    }
+    packages.sort();
+    packages.dedup();
+    let module_info = build::export_module_info(module_name, &packages);
 
-    info!("Copying generated java sources into java scope");
-    let from = spec.java_source();
-    let to = spec.java_target();
-    debug!("Copying from {} to {}", from, to);
+    let java_dir = Path::new(spec.java_source()).file_name().unwrap_or_default();
+    let mut written = vec![];
+    for to in java_targets(spec) {
+        let path = to.join(java_dir).join("module-info.java");
+        fs::write(&path, &module_info)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        written.push(path.display().to_string());
+    }
+    Ok(written)
+}
+
+/// Copies the generated C headers into the `include` directory of the project.
+///
+/// The headers are generated into the `c` directory of the `OUT_DIR`.
+///
+/// Returns the list of copied files.
+fn copy_c_headers(spec: &BuildConfig) -> Result<Vec<String>, String> {
+    let _span = info_span!("copy_c_headers").entered();
+    info!("Copying generated C headers into the include directory");
+    let from = derive_output_dir(spec, "c");
+    let to = Path::new(spec.root()).join("include");
+    create_dir(&to)?;
+    let entries =
+        fs::read_dir(&from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    let mut copied = vec![];
+    for entry in entries.flatten() {
+        let target = to.join(entry.file_name());
+        debug!("Copying from {} to {}", entry.path().display(), target.display());
+        fs::copy(entry.path(), &target)
+            .map_err(|e| format!("Failed to copy C header: {}", e))?;
+        copied.push(target.display().to_string());
+    }
+    Ok(copied)
+}
+
+/// Copies the experimental swift bindings into the `swift` directory of the
+/// project, where the package of the `swift-spm` flavor picks them up.
+///
+/// The bindings are generated into the `swift` directory of the `OUT_DIR`.
+///
+/// Returns the list of copied files.
+fn copy_swift_sources(spec: &BuildConfig) -> Result<Vec<String>, String> {
+    let _span = info_span!("copy_swift_sources").entered();
+    info!("Copying the experimental swift bindings into the swift directory");
+    let from = derive_output_dir(spec, "swift");
+    let to = Path::new(spec.root()).join("swift");
+    create_dir(&to)?;
+    let entries =
+        fs::read_dir(&from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    let mut copied = vec![];
+    for entry in entries.flatten() {
+        let target = to.join(entry.file_name());
+        debug!("Copying from {} to {}", entry.path().display(), target.display());
+        fs::copy(entry.path(), &target)
+            .map_err(|e| format!("Failed to copy swift source: {}", e))?;
+        copied.push(target.display().to_string());
+    }
+    Ok(copied)
+}
+
+/// Collects the JNI symbols of all derived entities into a single json file,
+/// sorted by symbol name.
+///
+/// The symbols are generated into the `symbols` directory of the `OUT_DIR`,
+/// which doesn't exist if nothing is derived. The list is empty then.
+///
+/// Returns the path of the written file.
+fn write_jni_symbols(spec: &BuildConfig, to: &Path) -> Result<String, String> {
+    let _span = info_span!("write_jni_symbols").entered();
+    info!("Writing the generated JNI symbols to {}", to.display());
+    let from = derive_output_dir(spec, "symbols");
+    let mut symbols = vec![];
+    match fs::read_dir(&from) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let content = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                let entity: Vec<serde_json::Value> = serde_json::from_str(&content)
+                    .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+                symbols.extend(entity);
+            }
+        }
+        Err(_) => warn!("No JNI symbols found in {}", from.display()),
+    }
+    symbols.sort_by(|a, b| a["symbol"].as_str().cmp(&b["symbol"].as_str()));
+    let json = serde_json::to_string_pretty(&symbols).expect("could not convert symbols");
+    fs::write(to, json).map_err(|e| format!("Failed to write {}: {}", to.display(), e))?;
+    Ok(to.display().to_string())
+}
+
+/// Returns the directory in the `OUT_DIR` of the build script the derive
+/// writes the given kind of output to, like `c` for the C headers.
+///
+/// Configs written before the `out_dir` was recorded fall back to the
+/// directory next to the java sources, which is the same place unless the
+/// classes are written to a `java_codegen_dir`.
+fn derive_output_dir(spec: &BuildConfig, name: &str) -> PathBuf {
+    match spec.out_dir() {
+        Some(out_dir) => Path::new(out_dir).join(name),
+        None => Path::new(spec.java_source()).with_file_name(name),
+    }
+}
+
+/// The file listing the generated java sources copied by the last build,
+/// which is kept next to them in the target directory.
+const GENERATED_JAVA_LIST: &str = ".roast-generated";
+
+/// Recursively copies the generated java sources into the target directory.
+///
+/// Sources copied by an earlier build which are not generated anymore are
+/// removed, see `remove_stale_java_sources`.
+///
+/// Returns the list of copied files.
+fn copy_java_sources(from: &str, to: &Path) -> Result<Vec<String>, String> {
+    info!("Copying java sources to {}", to.display());
+    create_dir(to)?;
+    debug!("Copying from {} to {}", from, to.display());
     match Command::new("cp").arg("-r").arg(from).arg(to).output() {
         Ok(o) => debug!("`cp -r` result {}", convert_output(&o)),
-        Err(e) => {
-            error!("`cp -r` failed! {}", e);
-            exit(1);
+        Err(e) => return Err(format!("`cp -r` failed! {}", e)),
+    }
+    let java_dir = to.join(Path::new(from).file_name().unwrap_or_default());
+    remove_stale_java_sources(&java_dir, &list_files(Path::new(from)))?;
+    let entries = fs::read_dir(from).map_err(|e| format!("Failed to read {}: {}", from, e))?;
+    Ok(entries
+        .flatten()
+        .map(|entry| java_dir.join(entry.file_name()).display().to_string())
+        .collect())
+}
+
+/// Removes the java sources listed by the previous build which are not part
+/// of the given generated files anymore, and lists the current ones instead.
+///
+/// A source goes stale if its struct isn't derived anymore, for example
+/// because it is only compiled with a feature which is now disabled. Left
+/// behind, it would fail with an `UnsatisfiedLinkError` at runtime. Only
+/// listed files are removed, so hand-written sources are never touched.
+fn remove_stale_java_sources(java_dir: &Path, generated: &[PathBuf]) -> Result<(), String> {
+    let list = java_dir.join(GENERATED_JAVA_LIST);
+    let previous = fs::read_to_string(&list).unwrap_or_default();
+    for stale in previous
+        .lines()
+        .map(PathBuf::from)
+        .filter(|p| !generated.contains(p))
+    {
+        let path = java_dir.join(&stale);
+        if path.is_file() {
+            warn!(
+                "Removing stale generated java source {}, its struct is not derived anymore",
+                path.display()
+            );
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
         }
     }
+    let current: String = generated
+        .iter()
+        .map(|p| format!("{}\n", p.display()))
+        .collect();
+    fs::write(&list, current).map_err(|e| format!("Failed to write {}: {}", list.display(), e))
+}
 
-    info!("Build complete! Enjoy your roast!");
+/// Returns the paths of all files below the directory, relative to it and
+/// sorted, or an empty list if it can't be read.
+fn list_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let name = PathBuf::from(entry.file_name());
+        if entry.path().is_dir() {
+            files.extend(list_files(&entry.path()).into_iter().map(|f| name.join(f)));
+        } else {
+            files.push(name);
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Runs the `build` command and then again each time a rust source file
+/// under `src/` changes, until interrupted with Ctrl+C.
+///
+/// Changes are debounced so saving multiple files at once only triggers
+/// a single rebuild.
+#[allow(clippy::too_many_arguments)]
+fn run_watch(
+    profile: Profile,
+    with_c_header: bool,
+    manifest_path: Option<&Path>,
+    module_name: Option<&str>,
+    no_java_copy: bool,
+    symbols_output: Option<&Path>,
+    experimental_swift: bool,
+    format: &Format,
+) -> Result<Vec<String>, String> {
+    ctrlc::set_handler(|| {
+        warn!("Stopping watch mode, bye!");
+        exit(0);
+    })
+    .map_err(|e| format!("Could not set the Ctrl+C handler: {}", e))?;
+
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, Duration::from_millis(200))
+        .map_err(|e| format!("Could not create the file watcher: {}", e))?;
+    let src_dir = match manifest_path {
+        Some(p) => resolve_manifest_path(p).0.join("src"),
+        None => PathBuf::from("src"),
+    };
+    watcher
+        .watch(&src_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Could not watch {}: {}", src_dir.display(), e))?;
+
+    loop {
+        let start = Instant::now();
+        let result = run_build(
+            profile,
+            with_c_header,
+            manifest_path,
+            module_name,
+            no_java_copy,
+            symbols_output,
+            experimental_swift,
+        );
+        if *format == Format::Text {
+            let timestamp = humantime::format_rfc3339_seconds(SystemTime::now());
+            match &result {
+                Ok(files) => warn!(
+                    "[{}] Build succeeded in {}ms ({} files)",
+                    timestamp,
+                    start.elapsed().as_millis(),
+                    files.len()
+                ),
+                Err(_) => warn!("[{}] Build failed", timestamp),
+            }
+        }
+        report(format, result, start);
+
+        if *format == Format::Text {
+            warn!("Watching {} for changes...", src_dir.display());
+        }
+        loop {
+            match rx.recv() {
+                Ok(event) if is_rust_source_change(&event) => break,
+                Ok(_) => continue,
+                Err(e) => return Err(format!("File watcher disconnected: {}", e)),
+            }
+        }
+        // Drain changes which piled up in the meantime into the same rebuild
+        while rx.try_recv().is_ok() {}
+    }
+}
+
+/// Checks if a file watcher event touches a rust source file.
+fn is_rust_source_change(event: &DebouncedEvent) -> bool {
+    let path = match event {
+        DebouncedEvent::Create(p) | DebouncedEvent::Write(p) | DebouncedEvent::Remove(p) => p,
+        DebouncedEvent::Rename(_, p) => p,
+        _ => return false,
+    };
+    path.extension().and_then(|e| e.to_str()) == Some("rs")
+}
+
+/// Returns the path of the config for the given profile in the crate
+/// directory.
+///
+/// A `roast-debug.json` or `roast-release.json` takes precedence if present,
+/// otherwise the `roast.json` written by the build script is used.
+fn config_path(profile: &Profile, crate_dir: &Path) -> String {
+    let path = crate_dir.join(format!("roast-{}.json", profile).to_lowercase());
+    if path.exists() {
+        path.display().to_string()
+    } else {
+        crate_dir.join("roast.json").display().to_string()
+    }
+}
+
+/// The `init` command creates a profile specific config.
+///
+/// It takes the `roast.json` written by the last build and points its
+/// `bin_source` to the cargo target directory of the given profile. The
+/// resulting file is owned by the user and can be customized further.
+#[instrument]
+fn run_init(profile: Profile) -> Result<Vec<String>, String> {
+    let profile_name = format!("{}", profile).to_lowercase();
+    let path = format!("roast-{}.json", profile_name);
+    if Path::new(&path).exists() {
+        return Err(format!("{} already exists, aborting!", path));
+    }
+    if !Path::new("roast.json").exists() {
+        return Err(String::from(
+            "roast.json not found, run `roast build` first to generate it",
+        ));
+    }
+
+    let spec = build::config_from_path("roast.json");
+    let mut config = serde_json::to_value(&spec).expect("could not convert config");
+    // The default bin_source points into the profile directory of the cargo
+    // target dir, so swap out the last component if we can resolve it.
+    let target_dir = Path::new(spec.bin_source())
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.display().to_string()))
+        .unwrap_or_else(|| format!("{}/target", spec.root()));
+    config["bin_source"] = format!("{}/{}/", target_dir, profile_name).into();
+    let encoded = serde_json::to_string_pretty(&config).expect("could not convert config");
+    fs::write(&path, encoded.as_bytes()).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    info!("Created {}", path);
+    Ok(vec![path])
+}
+
+/// The `migrate` command updates the configs of the current project to the
+/// current schema version, see `roast::migrations`.
+///
+/// The `roast.json` is rewritten by every build anyway, so this is mostly
+/// useful for the hand-edited `roast-<profile>.json` files, which are
+/// migrated as well if they exist.
+///
+/// Returns the list of files which were rewritten.
+#[instrument]
+fn run_migrate() -> Result<Vec<String>, String> {
+    let paths: Vec<&str> = ["roast.json", "roast-debug.json", "roast-release.json"]
+        .iter()
+        .copied()
+        .filter(|path| Path::new(path).exists())
+        .collect();
+    if paths.is_empty() {
+        return Err(String::from(
+            "roast.json not found, run `roast build` first to generate it",
+        ));
+    }
+
+    let mut migrated_files = vec![];
+    for path in paths {
+        if migrate_config(Path::new(path))? {
+            migrated_files.push(path.to_string());
+        }
+    }
+    Ok(migrated_files)
+}
+
+/// Migrates a single config file in place.
+///
+/// Returns false if the file was up to date already.
+fn migrate_config(path: &Path) -> Result<bool, String> {
+    let read = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let config: serde_json::Value = serde_json::from_str(&read)
+        .map_err(|e| format!("Could not decode {}: {}", path.display(), e))?;
+    let version = migrations::schema_version(&config);
+    if version == migrations::SCHEMA_VERSION {
+        info!("{} is up to date", path.display());
+        return Ok(false);
+    }
+
+    let migrated =
+        migrations::migrate(config).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let encoded = serde_json::to_string_pretty(&migrated).expect("could not convert config");
+    build::from_json_str(&encoded).map_err(|e| format!("{}: {}", path.display(), e))?;
+    fs::write(path, encoded.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    info!(
+        "Migrated {} from schema version {} to {}",
+        path.display(),
+        version,
+        migrations::SCHEMA_VERSION
+    );
+    Ok(true)
+}
+
+/// The `info` command shows the `roast.json` of the current project.
+///
+/// Each config value is listed together with whether the path exists,
+/// followed by the native library and java sources built so far. The
+/// roast version and the JDK found by `detect_java_home` are shown as well,
+/// since most build problems come from a mismatch between the two.
+///
+/// In json format nothing but the result is printed, so the config is
+/// best read from the `roast.json` directly.
+#[instrument]
+fn run_info(
+    profile: Profile,
+    manifest_path: Option<&Path>,
+    format: &Format,
+) -> Result<Vec<String>, String> {
+    let crate_dir = match manifest_path {
+        Some(p) => resolve_manifest_path(p).0,
+        None => PathBuf::new(),
+    };
+    let path = config_path(&profile, &crate_dir);
+    if !Path::new(&path).exists() {
+        return Err(format!(
+            "{} not found, run `roast build` first to generate it",
+            path
+        ));
+    }
+
+    let spec = build::config_from_path(&path);
+    let jdk_version = build::detect_java_home().and_then(|home| jdk_version(&home));
+    if *format == Format::Text {
+        print!("{}", format_info(&spec, &path, jdk_version.as_deref()));
+    }
+    Ok(vec![])
+}
+
+/// Formats the config read from the given path, the existing artifacts and
+/// the environment of a project for the `info` command.
+fn format_info(spec: &BuildConfig, path: &str, jdk_version: Option<&str>) -> String {
+    let mut output = format!(
+        "roast {}
+jdk   {}
+
+{}
+",
+        env!("CARGO_PKG_VERSION"),
+        jdk_version.unwrap_or("not found, set JAVA_HOME"),
+        path
+    );
+
+    let paths = [
+        ("root", Some(spec.root())),
+        ("bin_source", Some(spec.bin_source())),
+        ("bin_target", Some(spec.bin_target())),
+        ("java_source", Some(spec.java_source())),
+        ("java_target", Some(spec.java_target())),
+        ("jni_include_path", spec.jni_include_path()),
+    ];
+    let mut rows = vec![("name", spec.name().to_string(), String::new())];
+    for (key, path) in paths {
+        let (value, exists) = match path {
+            Some(p) if Path::new(p).exists() => (p.to_string(), "exists"),
+            Some(p) => (p.to_string(), "missing"),
+            None => (String::from("-"), ""),
+        };
+        rows.push((key, value, exists.to_string()));
+    }
+    rows.push((
+        "android_abis",
+        spec.android_abis()
+            .map(|abis| abis.join(", "))
+            .unwrap_or_else(|| String::from("-")),
+        String::new(),
+    ));
+    rows.push((
+        "module_name",
+        spec.module_name().unwrap_or("-").to_string(),
+        String::new(),
+    ));
+    let width = rows.iter().map(|(_, value, _)| value.len()).max().unwrap_or(0);
+    for (key, value, exists) in rows {
+        let line = format!("  {:<18}{:<width$}  {}", key, value, exists, width = width);
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+
+    output.push_str("\nartifacts\n");
+    let artifacts = find_artifacts(spec);
+    if artifacts.is_empty() {
+        output.push_str("  none, run `roast build` first\n");
+    }
+    for (path, size) in artifacts {
+        output.push_str(&format!("  {:>10}  {}\n", format_size(size), path.display()));
+    }
+    output
+}
+
+/// Returns the native libraries and generated java sources of a project
+/// which exist already, together with their size in bytes.
+fn find_artifacts(spec: &BuildConfig) -> Vec<(PathBuf, u64)> {
+    let library = format!("lib{}.{}", spec.name(), library_extension(spec));
+    let mut candidates = match spec.android_abis() {
+        Some(abis) => abis
+            .iter()
+            .map(|abi| Path::new(spec.root()).join("src/main/jniLibs").join(abi).join(&library))
+            .collect(),
+        None => vec![
+            Path::new(spec.bin_source()).join(&library),
+            Path::new(spec.bin_target()).join(&library),
+        ],
+    };
+    if let Ok(entries) = fs::read_dir(spec.java_source()) {
+        let mut sources: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "java"))
+            .collect();
+        sources.sort();
+        candidates.extend(sources);
+    }
+    candidates
+        .into_iter()
+        .filter_map(|path| {
+            let size = fs::metadata(&path).ok()?.len();
+            Some((path, size))
+        })
+        .collect()
+}
+
+/// Formats a size in bytes in a human readable way, like `1.5 KiB`.
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    for unit in ["B", "KiB", "MiB"] {
+        if size < 1024.0 {
+            return if unit == "B" {
+                format!("{} {}", bytes, unit)
+            } else {
+                format!("{:.1} {}", size, unit)
+            };
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GiB", size)
+}
+
+/// Reads the version of the JDK at the given `JAVA_HOME` from its `release`
+/// file, like `17.0.2`.
+fn jdk_version(java_home: &Path) -> Option<String> {
+    let release = fs::read_to_string(java_home.join("release")).ok()?;
+    release.lines().find_map(|line| {
+        line.strip_prefix("JAVA_VERSION=")
+            .map(|v| v.trim_matches('"').to_string())
+    })
+}
+
+/// Builds the library for the given android ABI and profile via `cargo ndk`
+/// and copies it into the `jniLibs` folder where the android tooling picks
+/// it up.
+///
+/// Returns the path the library has been copied to.
+#[instrument(skip(spec, extension))]
+fn copy_android_artifact(
+    spec: &BuildConfig,
+    abi: &str,
+    extension: &str,
+    profile: Profile,
+) -> Result<String, String> {
+    info!("Building the rust project for android ABI {} via `cargo ndk`", abi);
+    let mut cargo = Command::new("cargo");
+    cargo.arg("ndk").arg("-t").arg(abi).arg("build");
+    let mut command = format!("cargo ndk -t {} build", abi);
+    if let Profile::Release = profile {
+        cargo.arg("--release");
+        command.push_str(" --release");
+    }
+    match cargo.output() {
+        Ok(ref o) if o.status.success() => {
+            debug!("`{}` result {}", command, convert_output(o))
+        }
+        Ok(e) => return Err(format!("`{}` failed! {}", command, convert_output(&e))),
+        Err(e) => return Err(format!("`{}` failed! {}", command, e)),
+    };
+
+    let triple = match android_abi_to_triple(abi) {
+        Some(t) => t,
+        None => return Err(format!("Unsupported android ABI {}", abi)),
+    };
+    let from = format!(
+        "{}/../{}/{}/lib{}.{}",
+        spec.bin_source(),
+        triple,
+        format!("{}", profile).to_lowercase(),
+        spec.name(),
+        extension
+    );
+    let target_dir = format!("{}/src/main/jniLibs/{}", spec.root(), abi);
+    create_dir(Path::new(&target_dir))?;
+    let to = format!("{}/lib{}.{}", target_dir, spec.name(), extension);
+    copy_artifact(&from, &to)?;
+    Ok(to)
+}
+
+/// Maps an android ABI name to the rust target triple `cargo ndk` builds for.
+fn android_abi_to_triple(abi: &str) -> Option<&'static str> {
+    Some(match abi {
+        "arm64-v8a" => "aarch64-linux-android",
+        "armeabi-v7a" => "armv7-linux-androideabi",
+        "x86_64" => "x86_64-linux-android",
+        "x86" => "i686-linux-android",
+        _ => return None,
+    })
+}
+
+/// Creates the directory and all of its parents if they don't exist yet.
+fn create_dir(path: &Path) -> Result<(), String> {
+    fs::create_dir_all(path)
+        .map_err(|e| format!("Failed to create directory {}: {}", path.display(), e))
+}
+
+/// Copies a compiled native library from one place to another.
+fn copy_artifact(from: &str, to: &str) -> Result<(), String> {
+    debug!("Copying from {} to {}", from, to);
+    match fs::copy(from, to) {
+        Ok(_) => debug!("Copying completed"),
+        Err(e) => return Err(format!("Failed to copy artifacts: {}", e)),
+    };
+    Ok(())
 }
 
 /// Takes a CLI output and formats it in a nice format for the CLI with
@@ -157,37 +1311,53 @@ fn convert_output(o: &Output) -> String {
 /// Note that it also initializes a git project since that's
 /// needed anyways mostly. We can add flags in the future to
 /// customize further.
-fn run_new(name: String, group_id: Option<String>, flavor: Flavor) {
+///
+/// Returns the list of files created for the project.
+#[instrument(skip(name, description), fields(project = %name))]
+#[allow(clippy::too_many_arguments)]
+fn run_new(
+    name: String,
+    group_id: Option<String>,
+    artifact_id: Option<String>,
+    version: String,
+    flavor: Flavor,
+    description: String,
+    license: License,
+    ide: Option<Ide>,
+    maven_wrapper: bool,
+    java_version: String,
+) -> Result<Vec<String>, String> {
+    if maven_wrapper && !matches!(flavor, Flavor::Maven) {
+        return Err(format!(
+            "--maven-wrapper is only supported by the maven flavor, not {}",
+            flavor
+        ));
+    }
     let group_id = group_id.unwrap_or_else(|| String::from("rs.roast.gen"));
+    let artifact_id = artifact_id.unwrap_or_else(|| name.clone());
 
     info!("Creating project {}", name);
 
     let project_root = Path::new(&name);
     if project_root.exists() {
-        error!(
+        return Err(format!(
             "Directory \"{}\" already exists, aborting!",
             project_root
                 .to_str()
                 .expect("Could not convert project root to string")
-        );
-        exit(1);
+        ));
     }
 
     match fs::create_dir(&project_root) {
         Ok(_) => debug!("Project root directory created"),
-        Err(e) => {
-            error!("Error while creating directory {}", e);
-            exit(1);
-        }
+        Err(e) => return Err(format!("Error while creating directory {}", e)),
     }
 
+    let git_span = info_span!("git_init").entered();
     debug!("Initializing git repository");
     let _repo = match Repository::init(&project_root) {
         Ok(repo) => repo,
-        Err(e) => {
-            error!("Error while initializing git {}", e);
-            exit(1);
-        }
+        Err(e) => return Err(format!("Error while initializing git {}", e)),
     };
 
     let git_config = Config::open_default().expect("Could not open default git config");
@@ -197,20 +1367,139 @@ fn run_new(name: String, group_id: Option<String>, flavor: Flavor) {
     let user_email = git_config
         .get_string("user.email")
         .expect("Could not extract git user email");
-    let author = format!("[\"{} <{}>\"]", user_name, user_email);
+    git_span.exit();
 
-    let template_path = format!("templates/{}/", &flavor);
+    let variables = template_variables(
+        &name,
+        &user_name,
+        &user_email,
+        group_id,
+        artifact_id,
+        version,
+        description,
+        java_version,
+    );
+    write_project(project_root, &flavor, &variables, &license, ide.as_ref(), maven_wrapper)
+}
 
-    let variables = vec![
-        ("$NAME$", format!("\"{}\"", &name)),
-        ("$AUTHORS$", author),
+/// Returns the values of the `$VARIABLE$` placeholders in the templates.
+#[allow(clippy::too_many_arguments)]
+fn template_variables(
+    name: &str,
+    user_name: &str,
+    user_email: &str,
+    group_id: String,
+    artifact_id: String,
+    version: String,
+    description: String,
+    java_version: String,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("$NAME$", format!("\"{}\"", name)),
+        ("$LIB_NAME$", name.replace('-', "_")),
+        ("$AUTHORS$", format!("[\"{} <{}>\"]", user_name, user_email)),
         ("$GROUPID$", group_id),
-        ("$ARTIFACT$", name.clone()),
-    ];
+        ("$ARTIFACT$", artifact_id),
+        ("$VERSION$", version),
+        ("$DESCRIPTION$", description),
+        ("$COPYRIGHT_HOLDER$", user_name.to_string()),
+        ("$JAVA_VERSION$", java_version),
+    ]
+}
+
+/// Writes the templates of the flavor and the optional extras into an
+/// existing project root.
+///
+/// Returns the list of files created for the project.
+fn write_project(
+    project_root: &Path,
+    flavor: &Flavor,
+    variables: &[(&str, String)],
+    license: &License,
+    ide: Option<&Ide>,
+    maven_wrapper: bool,
+) -> Result<Vec<String>, String> {
+    let license_header = match license {
+        License::None => String::new(),
+        l => {
+            let path = format!("templates/licenses/{}.in", l).to_lowercase();
+            let mut header = String::from_utf8(
+                FILES
+                    .get(&path)
+                    .expect("could not get license template file")
+                    .into_owned(),
+            )
+            .expect("Could not turn raw license template file into utf8");
+            header.push('\n');
+            header
+        }
+    };
+
+    let template_path = format!("templates/{}/", flavor).to_lowercase();
+    let mut generated_files =
+        write_templates(&template_path, project_root, variables, &license_header);
+    if let Some(ide) = ide {
+        let ide_path = format!("templates/ide/{}/", ide).to_lowercase();
+        generated_files.extend(write_templates(
+            &ide_path,
+            project_root,
+            variables,
+            &license_header,
+        ));
+    }
+    if maven_wrapper {
+        generated_files.extend(write_maven_wrapper(project_root, variables)?);
+    }
+    Ok(generated_files)
+}
 
+/// Writes the Maven wrapper scripts and their properties into the project
+/// root and makes the scripts executable.
+///
+/// The wrapper uses the script-only distribution type, which downloads
+/// Maven without a `maven-wrapper.jar`, so no binary needs to be embedded.
+///
+/// Returns the list of written files.
+fn write_maven_wrapper(
+    project_root: &Path,
+    variables: &[(&str, String)],
+) -> Result<Vec<String>, String> {
+    let generated_files = write_templates("templates/maven-wrapper/", project_root, variables, "");
+    for script in &["mvnw", "mvnw.cmd"] {
+        make_executable(&project_root.join(script))?;
+    }
+    Ok(generated_files)
+}
+
+/// Marks a file as executable for everyone.
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(|e| format!("Could not make {} executable: {}", path.display(), e))
+}
+
+/// Marks a file as executable, which is not needed outside of unix.
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+/// Writes all template files below the template path into the project root,
+/// substituting the variables and the license header.
+///
+/// Returns the list of written files.
+fn write_templates(
+    template_path: &str,
+    project_root: &Path,
+    variables: &[(&str, String)],
+    license_header: &str,
+) -> Vec<String> {
+    let _template_span = info_span!("write_templates", template = %template_path).entered();
+    let mut generated_files = vec![];
     for tpath in FILES.file_names() {
-        if tpath.starts_with(&template_path) {
-            let shortpath = tpath.replace(&template_path, "");
+        if tpath.starts_with(template_path) {
+            let shortpath = tpath.replace(template_path, "");
             let file_path = format!(
                 "{}/{}",
                 project_root
@@ -228,7 +1517,13 @@ fn run_new(name: String, group_id: Option<String>, flavor: Flavor) {
                     .into_owned(),
             )
             .expect("Could not turn raw template file into utf8");
-            for variable in &variables {
+            content = replace_block(
+                &content,
+                "// $LICENSE_HEADER_START$\n",
+                "// $LICENSE_HEADER_END$\n",
+                license_header,
+            );
+            for variable in variables {
                 content = content.replace(variable.0, &variable.1);
             }
 
@@ -240,6 +1535,458 @@ fn run_new(name: String, group_id: Option<String>, flavor: Flavor) {
                 file_path.replace(filename.to_str().expect("could not convert filename"), "");
             fs::create_dir_all(dirpath).expect("could not create directory");
             fs::write(&file_path, content.as_bytes()).expect("could not write file");
+            generated_files.push(file_path);
+        }
+    }
+    generated_files
+}
+
+/// Replaces a whole block, including its start and end markers, with
+/// the given replacement.
+///
+/// This is used for multi-line values like license headers, where the
+/// markers keep the template itself a valid source file.
+fn replace_block(content: &str, start: &str, end: &str, replacement: &str) -> String {
+    match (content.find(start), content.find(end)) {
+        (Some(s), Some(e)) if s <= e => format!(
+            "{}{}{}",
+            &content[..s],
+            replacement,
+            &content[e + end.len()..]
+        ),
+        _ => content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn copy_build_outputs_creates_target_dirs() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().display().to_string();
+        let extension = if cfg!(target_os = "windows") {
+            "dll"
+        } else if cfg!(target_os = "macos") {
+            "dylib"
+        } else {
+            "so"
+        };
+
+        fs::create_dir_all(format!("{}/target/debug", root)).unwrap();
+        fs::write(format!("{}/target/debug/libhello.{}", root, extension), b"lib").unwrap();
+        fs::create_dir_all(format!("{}/out/java/com/example", root)).unwrap();
+        fs::write(
+            format!("{}/out/java/com/example/Hello.java", root),
+            b"package com.example; class Hello {}",
+        )
+        .unwrap();
+
+        let spec = build::from_json_str(&format!(
+            r#"{{
+                "root": "{root}",
+                "name": "hello",
+                "bin_source": "{root}/target/debug",
+                "bin_target": "{root}/src/main/resources",
+                "java_source": "{root}/out/java",
+                "java_target": "{root}/src/main"
+            }}"#,
+            root = root
+        ))
+        .unwrap();
+
+        let generated_files = copy_build_outputs(&spec, Profile::Debug).unwrap();
+        assert_eq!(2, generated_files.len());
+        let lib = format!("{}/src/main/resources/libhello.{}", root, extension);
+        assert!(Path::new(&lib).exists());
+        assert!(Path::new(&format!("{}/src/main/java/com/example/Hello.java", root)).exists());
+
+        let module_info = format!("{}/src/main/java/module-info.java", root);
+        assert_eq!(
+            vec![module_info.clone()],
+            write_module_info(&spec, "com.example.hello").unwrap()
+        );
+        assert_eq!(
+            build::export_module_info("com.example.hello", &[String::from("com.example")]),
+            fs::read_to_string(&module_info).unwrap()
+        );
+
+        // classes of the unnamed package can't be part of a module
+        fs::write(format!("{}/out/java/Unnamed.java", root), b"class Unnamed {}").unwrap();
+        assert!(write_module_info(&spec, "com.example.hello")
+            .unwrap_err()
+            .contains("Unnamed.java is in the unnamed package"));
+    }
+
+    #[test]
+    fn jni_symbols_collected() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().display().to_string();
+        fs::create_dir_all(format!("{}/out/symbols", root)).unwrap();
+        fs::write(
+            format!("{}/out/symbols/Strings.json", root),
+            r#"[{"symbol": "Java_Strings_reverse", "rust_function": "Strings::reverse"}]"#,
+        )
+        .unwrap();
+        fs::write(
+            format!("{}/out/symbols/Primitive.json", root),
+            r#"[{"symbol": "Java_Primitive_addInt", "rust_function": "Primitive::add_int"}]"#,
+        )
+        .unwrap();
+        let spec = build::from_json_str(&format!(
+            r#"{{
+                "root": "{root}",
+                "name": "hello",
+                "bin_source": "{root}/target/debug",
+                "bin_target": "{root}/src/main/resources",
+                "java_source": "{root}/src/generated/java",
+                "java_target": "{root}/src/main",
+                "java_codegen_dir": "{root}/src/generated/java",
+                "out_dir": "{root}/out"
+            }}"#,
+            root = root
+        ))
+        .unwrap();
+
+        let to = dir.path().join("roast-symbols.json");
+        assert_eq!(to.display().to_string(), write_jni_symbols(&spec, &to).unwrap());
+        let symbols: Vec<serde_json::Value> =
+            serde_json::from_str(&fs::read_to_string(&to).unwrap()).unwrap();
+        let names: Vec<&str> = symbols.iter().map(|s| s["symbol"].as_str().unwrap()).collect();
+        assert_eq!(vec!["Java_Primitive_addInt", "Java_Strings_reverse"], names);
+
+        fs::remove_dir_all(format!("{}/out/symbols", root)).unwrap();
+        write_jni_symbols(&spec, &to).unwrap();
+        assert_eq!("[]", fs::read_to_string(&to).unwrap());
+    }
+
+    #[test]
+    fn export_metadata_copied() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().display().to_string();
+        let spec = build::from_json_str(&format!(
+            r#"{{
+                "root": "{root}",
+                "name": "hello",
+                "bin_source": "{root}/target/debug",
+                "bin_target": "{root}/src/main/resources",
+                "java_source": "{root}/out/java",
+                "java_target": "{root}/src/main",
+                "out_dir": "{root}/out"
+            }}"#,
+            root = root
+        ))
+        .unwrap();
+
+        let to = dir.path().join("roast_export.json");
+        let err = copy_export_metadata(&spec, &to).unwrap_err();
+        assert!(err.ends_with("does the crate derive RoastExport?"));
+
+        fs::create_dir_all(format!("{}/out", root)).unwrap();
+        fs::write(format!("{}/out/roast_export.json", root), r#"{"Basic": []}"#).unwrap();
+        copy_export_metadata(&spec, &to).unwrap();
+        assert_eq!(r#"{"Basic": []}"#, fs::read_to_string(&to).unwrap());
+    }
+
+    #[test]
+    fn stale_java_sources_removed() {
+        let dir = tempdir().unwrap();
+        let from = dir.path().join("out/java");
+        let to = dir.path().join("src/main");
+        fs::create_dir_all(from.join("rs/roast")).unwrap();
+        fs::write(from.join("Basic.java"), "class Basic {}").unwrap();
+        fs::write(from.join("rs/roast/Advanced.java"), "class Advanced {}").unwrap();
+        let from = from.display().to_string();
+
+        copy_java_sources(&from, &to).unwrap();
+        let java_dir = to.join("java");
+        assert_eq!(
+            "Basic.java\nrs/roast/Advanced.java\n",
+            fs::read_to_string(java_dir.join(GENERATED_JAVA_LIST)).unwrap()
+        );
+
+        fs::remove_file(Path::new(&from).join("rs/roast/Advanced.java")).unwrap();
+        fs::write(java_dir.join("Handwritten.java"), "class Handwritten {}").unwrap();
+        copy_java_sources(&from, &to).unwrap();
+        assert!(java_dir.join("Basic.java").exists());
+        assert!(java_dir.join("Handwritten.java").exists());
+        assert!(!java_dir.join("rs/roast/Advanced.java").exists());
+        assert_eq!(
+            "Basic.java\n",
+            fs::read_to_string(java_dir.join(GENERATED_JAVA_LIST)).unwrap()
+        );
+    }
+
+    #[test]
+    fn resolve_manifest_path_dir_and_file() {
+        let dir = tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(&manifest, b"").unwrap();
+
+        let expected = (dir.path().to_path_buf(), manifest.clone());
+        assert_eq!(expected, resolve_manifest_path(dir.path()));
+        assert_eq!(expected, resolve_manifest_path(&manifest));
+        assert_eq!(
+            (PathBuf::new(), PathBuf::from("Cargo.toml")),
+            resolve_manifest_path(Path::new("Cargo.toml"))
+        );
+    }
+
+    #[test]
+    fn config_path_in_crate_dir() {
+        let dir = tempdir().unwrap();
+        let default = dir.path().join("roast.json").display().to_string();
+        assert_eq!(default, config_path(&Profile::Release, dir.path()));
+
+        let release = dir.path().join("roast-release.json");
+        fs::write(&release, b"{}").unwrap();
+        assert_eq!(
+            release.display().to_string(),
+            config_path(&Profile::Release, dir.path())
+        );
+        assert_eq!(default, config_path(&Profile::Debug, dir.path()));
+        assert_eq!("roast.json", config_path(&Profile::Debug, Path::new("")));
+    }
+
+    #[test]
+    fn format_info_with_artifacts() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().display().to_string();
+        let spec = build::from_json_str(&format!(
+            r#"{{
+                "root": "{root}",
+                "name": "hello",
+                "bin_source": "{root}/target/debug",
+                "bin_target": "{root}/src/main/resources",
+                "java_source": "{root}/out/java",
+                "java_target": "{root}/src/main"
+            }}"#,
+            root = root
+        ))
+        .unwrap();
+
+        let info = format_info(&spec, "roast.json", Some("17.0.2"));
+        assert!(info.ends_with("artifacts\n  none, run `roast build` first\n"));
+
+        let library = format!("libhello.{}", library_extension(&spec));
+        fs::create_dir_all(format!("{}/target/debug", root)).unwrap();
+        fs::write(format!("{}/target/debug/{}", root, library), vec![0; 2048]).unwrap();
+        fs::create_dir_all(format!("{}/out/java", root)).unwrap();
+        fs::write(format!("{}/out/java/Hello.java", root), b"class Hello {}").unwrap();
+
+        let info = format_info(&spec, "crates/hello/roast-release.json", Some("17.0.2"));
+        assert!(info.starts_with(&format!("roast {}\njdk   17.0.2\n", env!("CARGO_PKG_VERSION"))));
+        assert!(info.contains("\n\ncrates/hello/roast-release.json\n"));
+        let row = |key: &str| -> Vec<String> {
+            let line = info.lines().find(|l| l.trim_start().starts_with(key)).unwrap();
+            line.split_whitespace().map(String::from).collect()
+        };
+        assert_eq!(vec!["name", "hello"], row("name"));
+        assert_eq!(
+            vec!["bin_source".into(), format!("{}/target/debug", root), "exists".into()],
+            row("bin_source")
+        );
+        assert_eq!(
+            vec!["bin_target".into(), format!("{}/src/main/resources", root), "missing".into()],
+            row("bin_target")
+        );
+        assert_eq!(vec!["jni_include_path", "-"], row("jni_include_path"));
+        assert!(info.contains(&format!("     2.0 KiB  {}/target/debug/{}\n", root, library)));
+        assert!(info.contains(&format!("        14 B  {}/out/java/Hello.java\n", root)));
+
+        let info = format_info(&spec, "roast.json", None);
+        assert!(info.contains("jdk   not found, set JAVA_HOME\n"));
+    }
+
+    #[test]
+    fn jdk_version_from_release_file() {
+        let dir = tempdir().unwrap();
+        assert_eq!(None, jdk_version(dir.path()));
+
+        fs::write(
+            dir.path().join("release"),
+            b"IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.2\"\n",
+        )
+        .unwrap();
+        assert_eq!(Some(String::from("17.0.2")), jdk_version(dir.path()));
+    }
+
+    #[test]
+    fn format_size_units() {
+        assert_eq!("14 B", format_size(14));
+        assert_eq!("1.5 KiB", format_size(1536));
+        assert_eq!("3.0 MiB", format_size(3 * 1024 * 1024));
+        assert_eq!("2.0 GiB", format_size(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn new_project_written() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("test_project");
+        fs::create_dir(&root).unwrap();
+        let variables = template_variables(
+            "test_project",
+            "Jane Doe",
+            "jane@example.com",
+            String::from("rs.roast.gen"),
+            String::from("test_project"),
+            String::from("0.1.0"),
+            String::from("A roast-based JNI library"),
+            String::from("17"),
+        );
+        let flavor = "maven".parse::<Flavor>().unwrap();
+        let generated_files =
+            write_project(&root, &flavor, &variables, &License::Apache2, None, false).unwrap();
+
+        for path in &[
+            "Cargo.toml",
+            "build.rs",
+            "pom.xml",
+            ".gitignore",
+            "src/lib.rs",
+            "src/main/java/.gitkeep",
+            "src/main/resources/.gitkeep",
+            "src/test/java/HelloWorldTest.java",
+            ".github/workflows/build.yml",
+        ] {
+            let file = root.join(path).display().to_string();
+            assert!(generated_files.contains(&file), "{} not generated", path);
         }
+        for file in &generated_files {
+            assert!(!file.ends_with(".in"), "{} kept the template extension", file);
+            let content = fs::read_to_string(file).unwrap();
+            for (placeholder, _) in &variables {
+                assert!(!content.contains(placeholder), "{} in {}", placeholder, file);
+            }
+        }
+
+        let manifest = fs::read_to_string(root.join("Cargo.toml")).unwrap();
+        assert!(manifest.contains("name = \"test_project\"\n"));
+        assert!(manifest.contains("authors = [\"Jane Doe <jane@example.com>\"]\n"));
+        let pom = fs::read_to_string(root.join("pom.xml")).unwrap();
+        assert!(pom.contains("<artifactId>test_project</artifactId>"));
+        let lib = fs::read_to_string(root.join("src/lib.rs")).unwrap();
+        assert!(lib.starts_with("// Copyright Jane Doe\n"));
+        let workflow = fs::read_to_string(root.join(".github/workflows/build.yml")).unwrap();
+        assert!(workflow.starts_with("name: \"test_project\"\n"));
+        assert!(workflow.contains("java-version: 17\n"));
+        assert!(workflow.contains("runs-on: ${{ matrix.os }}\n"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn swift_spm_project_written() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("swift-project");
+        fs::create_dir(&root).unwrap();
+        let variables = template_variables(
+            "swift-project",
+            "Jane Doe",
+            "jane@example.com",
+            String::from("rs.roast.gen"),
+            String::from("swift-project"),
+            String::from("0.1.0"),
+            String::from("A roast-based swift library"),
+            String::from("17"),
+        );
+        let flavor = "swift-spm".parse::<Flavor>().unwrap();
+        assert_eq!("swift-spm", flavor.to_string());
+        let generated_files =
+            write_project(&root, &flavor, &variables, &License::None, None, false).unwrap();
+
+        for path in &[
+            "Cargo.toml",
+            "build.rs",
+            "Package.swift",
+            ".gitignore",
+            "src/lib.rs",
+            "swift/.gitkeep",
+        ] {
+            let file = root.join(path).display().to_string();
+            assert!(generated_files.contains(&file), "{} not generated", path);
+        }
+        let package = fs::read_to_string(root.join("Package.swift")).unwrap();
+        assert!(package.contains("name: \"swift-project\","));
+        assert!(package.contains(".linkedLibrary(\"swift_project\")"));
+        assert!(package.contains("\"-Ltarget/release\"], .when(configuration: .release)"));
+        assert!(!root.join("pom.xml").exists());
+        assert!("gradle".parse::<Flavor>().is_err());
+    }
+
+    #[test]
+    fn maven_wrapper_rejected_for_swift_spm() {
+        let result = run_new(
+            String::from("swift_project"),
+            None,
+            None,
+            String::from("0.1.0"),
+            Flavor::SwiftSpm,
+            String::new(),
+            License::None,
+            None,
+            true,
+            String::from("11"),
+        );
+        assert_eq!(
+            Err(String::from(
+                "--maven-wrapper is only supported by the maven flavor, not swift-spm"
+            )),
+            result
+        );
+    }
+
+    #[test]
+    fn maven_wrapper_written() {
+        let dir = tempdir().unwrap();
+        let mut generated_files = write_maven_wrapper(dir.path(), &[]).unwrap();
+        generated_files.sort();
+
+        let root = dir.path().display();
+        assert_eq!(
+            vec![
+                format!("{}/.mvn/wrapper/maven-wrapper.properties", root),
+                format!("{}/mvnw", root),
+                format!("{}/mvnw.cmd", root),
+            ],
+            generated_files
+        );
+        let properties = fs::read_to_string(&generated_files[0]).unwrap();
+        assert!(properties.contains("distributionType=only-script"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("mvnw"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(0o755, mode & 0o777);
+        }
+    }
+
+    #[test]
+    fn profile_config_migrated() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("roast-release.json");
+        fs::write(
+            &path,
+            r#"{
+                "root": "/roast",
+                "name": "hello",
+                "bin_source": "/roast/target/release",
+                "bin_target": "/roast/src/main/resources",
+                "java_source": "/roast/target/java",
+                "java_target": "/roast/src/main"
+            }"#,
+        )
+        .unwrap();
+
+        assert!(migrate_config(&path).unwrap());
+        let config = build::config_from_path(path.to_str().unwrap());
+        assert_eq!(migrations::SCHEMA_VERSION, config.schema_version());
+        assert_eq!("/roast/target/release", config.bin_source());
+        assert!(!migrate_config(&path).unwrap());
+    }
+}
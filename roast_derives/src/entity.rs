@@ -3,23 +3,264 @@ use inflector::Inflector;
 use itertools::Itertools;
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
 use syn::{parse_str, Expr, Ident};
 
+/// The unsupported type errors carry the file and line of the function, if
+/// it was found in the scanned sources, so the message points at the code.
 #[derive(Debug, Fail)]
+#[allow(clippy::enum_variant_names)]
 pub enum ConversionError {
-    #[fail(display = "Unsupported Return Type {} on function {}", rt, func)]
-    UnsupportedReturnType { func: String, rt: String },
+    UnsupportedReturnType {
+        func: String,
+        rt: String,
+        source_file: Option<String>,
+        line: Option<u32>,
+    },
+    UnsupportedArgType {
+        func: String,
+        ty: String,
+        source_file: Option<String>,
+        line: Option<u32>,
+    },
+    UnsupportedConstType {
+        name: String,
+        ty: String,
+    },
+    UnsupportedCallback {
+        func: String,
+        arg: String,
+        java_interface: String,
+        source_file: Option<String>,
+        line: Option<u32>,
+    },
+    UnsupportedFieldType {
+        variant: String,
+        field: String,
+        ty: String,
+    },
+    InvalidEntity {
+        entity: String,
+        errors: String,
+    },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = |source_file: &Option<String>, line: &Option<u32>| match (source_file, line)
+        {
+            (Some(file), Some(line)) => format!(" at {}:{}", file, line),
+            (Some(file), None) => format!(" at {}", file),
+            _ => String::new(),
+        };
+        match self {
+            ConversionError::UnsupportedReturnType {
+                func,
+                rt,
+                source_file,
+                line,
+            } => write!(
+                f,
+                "Unsupported return type '{}'{} in function '{}'",
+                rt,
+                location(source_file, line),
+                func
+            ),
+            ConversionError::UnsupportedArgType {
+                func,
+                ty,
+                source_file,
+                line,
+            } => write!(
+                f,
+                "Unsupported argument type '{}'{} in function '{}'",
+                ty,
+                location(source_file, line),
+                func
+            ),
+            ConversionError::UnsupportedCallback {
+                func,
+                arg,
+                java_interface,
+                source_file,
+                line,
+            } => write!(
+                f,
+                "Unsupported callback interface '{}' of argument '{}'{} in function '{}'",
+                java_interface,
+                arg,
+                location(source_file, line),
+                func
+            ),
+            ConversionError::UnsupportedConstType { name, ty } => {
+                write!(f, "Unsupported Constant Type {} on constant {}", ty, name)
+            }
+            ConversionError::UnsupportedFieldType { variant, field, ty } => write!(
+                f,
+                "Unsupported field type '{}' of field '{}' in variant '{}'",
+                ty, field, variant
+            ),
+            ConversionError::InvalidEntity { entity, errors } => {
+                write!(f, "Invalid java class {}: {}", entity, errors)
+            }
+        }
+    }
 }
 
 /// Describes a function/method associated with the derived struct.
-#[derive(Debug)]
+///
+/// It can be read from the `ROAST_METADATA_PATH` file instead of the source
+/// scan, see `MetadataFn`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct DerivedFn {
     name: String,
     return_type: Option<String>,
     args: Vec<DerivedFnArg>,
+    error_return: bool,
+    returns_named: Option<(String, String)>,
+    use_optional: bool,
+    java_collection: bool,
+    json: bool,
+    documented: bool,
+    cfg_java: Option<String>,
+    null_annotation: Option<NullAnnotation>,
+    collect: bool,
+    trait_object: Option<String>,
+    trait_path: Option<String>,
+    android_keep: bool,
+    int_enum: Option<String>,
+    visibility: JavaVisibility,
+    naming: NamingStrategy,
+    source_file: Option<String>,
+    line: Option<u32>,
+    must_use: bool,
+    deprecated: Option<String>,
+    #[serde(skip)]
+    duplicate_locations: Vec<String>,
 }
 
-#[derive(Debug)]
+/// A `DerivedFn` as read from the `ROAST_METADATA_PATH` file, where the name
+/// is required and every other field falls back to the same default as
+/// with `DerivedFn::new`.
+#[derive(Debug, Deserialize)]
+pub struct MetadataFn {
+    name: String,
+    #[serde(flatten)]
+    func: DerivedFn,
+}
+
+impl From<MetadataFn> for DerivedFn {
+    fn from(metadata: MetadataFn) -> Self {
+        DerivedFn {
+            name: metadata.name,
+            ..metadata.func
+        }
+    }
+}
+
+impl Default for DerivedFn {
+    fn default() -> Self {
+        DerivedFn {
+            name: String::new(),
+            return_type: None,
+            args: vec![],
+            error_return: false,
+            returns_named: None,
+            use_optional: false,
+            java_collection: false,
+            json: false,
+            documented: false,
+            cfg_java: None,
+            null_annotation: None,
+            collect: false,
+            trait_object: None,
+            trait_path: None,
+            android_keep: false,
+            int_enum: None,
+            visibility: JavaVisibility::Public,
+            naming: NamingStrategy::Camel,
+            source_file: None,
+            line: None,
+            must_use: false,
+            deprecated: None,
+            duplicate_locations: vec![],
+        }
+    }
+}
+
+/// The access modifier of a generated java method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JavaVisibility {
+    Public,
+    Protected,
+    /// package-private, which has no keyword
+    Package,
+}
+
+impl JavaVisibility {
+    fn keyword(self) -> &'static str {
+        match self {
+            JavaVisibility::Public => "public",
+            JavaVisibility::Protected => "protected",
+            JavaVisibility::Package => "",
+        }
+    }
+}
+
+/// How the snake case rust name of a function is turned into the name of
+/// the java method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum NamingStrategy {
+    /// `get_http_response` turns into `getHttpResponse`
+    Camel,
+    /// `get_http_response` turns into `getHTTPResponse`, see `ACRONYMS`
+    AcronymCamel,
+    /// keeps the rust name, for functions which are camel case already
+    Verbatim,
+}
+
+/// Words which `NamingStrategy::AcronymCamel` writes in upper case, unless
+/// they start the name.
+const ACRONYMS: &[&str] = &[
+    "api", "cpu", "csv", "dns", "gpu", "html", "http", "https", "id", "io", "ip", "jni", "json",
+    "jvm", "sql", "tcp", "udp", "uri", "url", "utf8", "uuid", "xml",
+];
+
+impl NamingStrategy {
+    fn apply(self, name: &str) -> String {
+        match self {
+            NamingStrategy::Camel => name.to_camel_case(),
+            NamingStrategy::AcronymCamel => name
+                .split('_')
+                .filter(|word| !word.is_empty())
+                .enumerate()
+                .map(|(i, word)| {
+                    let word = word.to_lowercase();
+                    if i == 0 {
+                        word
+                    } else if ACRONYMS.contains(&word.as_str()) {
+                        word.to_uppercase()
+                    } else {
+                        word.to_pascal_case()
+                    }
+                })
+                .collect(),
+            NamingStrategy::Verbatim => name.into(),
+        }
+    }
+}
+
+/// The nullness of a java return type, emitted as `@Nullable` or `@NonNull`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum NullAnnotation {
+    Nullable,
+    NonNull,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub enum DerivedFnArg {
     /// &self and &mut self
     SelfBorrow {
@@ -33,12 +274,41 @@ pub enum DerivedFnArg {
         name: String,
         ty: String,
     },
+    /// A java functional interface, called back from rust as a closure
+    Callback {
+        name: String,
+        java_interface: String,
+    },
+    /// A java string which is converted lossy, replacing invalid UTF-16
+    LossyString {
+        name: String,
+    },
+    /// A java double which is narrowed into an `f32`
+    LossyFloat {
+        name: String,
+    },
+    /// A java long holding a pointer to a struct allocated by rust, which is
+    /// passed as `&T` or `&mut T`
+    NativeHandle {
+        name: String,
+        mutable: bool,
+    },
+    /// A java int converted into a rust enum with `From<i32>`
+    IntEnum {
+        name: String,
+        ty: String,
+    },
 }
 
 impl DerivedFnArg {
     fn name(&self) -> Option<String> {
         match self {
             DerivedFnArg::Captured { name, .. } => Some(name.clone()),
+            DerivedFnArg::Callback { name, .. } => Some(name.clone()),
+            DerivedFnArg::LossyString { name } => Some(name.clone()),
+            DerivedFnArg::LossyFloat { name } => Some(name.clone()),
+            DerivedFnArg::NativeHandle { name, .. } => Some(name.clone()),
+            DerivedFnArg::IntEnum { name, .. } => Some(name.clone()),
             _ => None,
         }
     }
@@ -46,396 +316,5632 @@ impl DerivedFnArg {
     fn java_name(&self) -> Option<String> {
         match self {
             DerivedFnArg::Captured { name, .. } => Some(name.to_camel_case()),
+            DerivedFnArg::Callback { name, .. } => Some(name.to_camel_case()),
+            DerivedFnArg::LossyString { name } => Some(name.to_camel_case()),
+            DerivedFnArg::LossyFloat { name } => Some(name.to_camel_case()),
+            DerivedFnArg::NativeHandle { name, .. } => Some(name.to_camel_case()),
+            DerivedFnArg::IntEnum { name, .. } => Some(name.to_camel_case()),
             _ => None,
         }
     }
 }
 
 impl DerivedFn {
+    /// Creates a new function description.
+    ///
+    /// Panics if the name is not a valid java identifier.
     pub fn new(name: &str, return_type: Option<String>, args: Vec<DerivedFnArg>) -> Self {
+        validate_identifier("method", name);
         DerivedFn {
             name: name.into(),
             return_type,
             args,
+            ..DerivedFn::default()
         }
     }
 
-    /// If the argument list contains a reference to self this method is
-    /// non-static, otherwise it is.
-    pub fn is_static(&self) -> bool {
-        for a in &self.args {
-            match a {
-                DerivedFnArg::SelfBorrow { .. } => return false,
-                DerivedFnArg::SelfOwned { .. } => return false,
-                _ => (),
+    /// Records where the function is defined, which is added to the messages
+    /// of its conversion errors.
+    pub fn with_source_location(mut self, source_file: &str, line: Option<u32>) -> Self {
+        self.source_file = Some(source_file.into());
+        self.line = line;
+        self
+    }
+
+    /// Records another definition of a method with the same rust name,
+    /// which is dropped in favor of this one.
+    pub fn with_duplicate_location(mut self, location: &str) -> Self {
+        self.duplicate_locations.push(location.into());
+        self
+    }
+
+    /// Returns where the function is defined as `file:line`, or just the
+    /// file when the line is unknown.
+    pub fn source_location(&self) -> Option<String> {
+        match (&self.source_file, self.line) {
+            (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+            (Some(file), None) => Some(file.clone()),
+            _ => None,
+        }
+    }
+
+    fn unsupported_return_type(&self, rt: &str) -> ConversionError {
+        ConversionError::UnsupportedReturnType {
+            func: self.name.clone(),
+            rt: rt.into(),
+            source_file: self.source_file.clone(),
+            line: self.line,
+        }
+    }
+
+    fn unsupported_arg_type(&self, ty: &str) -> ConversionError {
+        ConversionError::UnsupportedArgType {
+            func: self.name.clone(),
+            ty: ty.into(),
+            source_file: self.source_file.clone(),
+            line: self.line,
+        }
+    }
+
+    /// Fails for a callback argument of a java interface which has no
+    /// conversion function in `roast::convert`.
+    fn check_callbacks(&self) -> Result<(), ConversionError> {
+        for arg in &self.args {
+            if let DerivedFnArg::Callback {
+                name,
+                java_interface,
+            } = arg
+            {
+                if callback_convert_fn(java_interface).is_none() {
+                    return Err(ConversionError::UnsupportedCallback {
+                        func: self.name.clone(),
+                        arg: name.clone(),
+                        java_interface: java_interface.clone(),
+                        source_file: self.source_file.clone(),
+                        line: self.line,
+                    });
+                }
             }
         }
-        true
+        Ok(())
     }
 
-    /// Returns the rust style function name turned into java style.
-    pub fn java_name(&self) -> String {
-        self.name.to_camel_case()
+    /// Marks the function as returning a `Result`, where the return type
+    /// is the `Ok` type and the `Err` variant is thrown as a java exception.
+    pub fn with_error_return(mut self, error_return: bool) -> Self {
+        self.error_return = error_return;
+        self
     }
 
-    /// Takes the return type but simply removes all invalid chars so it can
-    /// be used in rust code as part of the function signatures.
-    pub fn sanitized_return_type(&self) -> Option<String> {
-        self.return_type
-            .as_ref()
-            .map(|t| t.replace('<', "").replace('>', "").replace(' ', ""))
+    /// Names the two values of a tuple return type, which generates a named
+    /// java result class instead of the generic `Tuple2`.
+    ///
+    /// Panics if one of the names is not a valid java identifier.
+    pub fn with_returns_named(mut self, first: &str, second: &str) -> Self {
+        validate_identifier("field", first);
+        validate_identifier("field", second);
+        self.returns_named = Some((first.into(), second.into()));
+        self
     }
-}
 
-/// Describes the entity which is derived with methods and all.
-#[derive(Debug)]
-pub struct DerivedEntity {
-    name: String,
-    fns: Vec<DerivedFn>,
-}
+    /// Returns an `Option<T>` as a `java.util.Optional<T>` instead of a
+    /// nullable boxed type like `Integer`.
+    pub fn with_use_optional(mut self, use_optional: bool) -> Self {
+        self.use_optional = use_optional;
+        self
+    }
 
-impl DerivedEntity {
-    /// Creates a new `DerivedEntity`
-    pub fn new(name: &str, fns: Vec<DerivedFn>) -> Self {
-        DerivedEntity {
-            name: name.into(),
-            fns,
-        }
+    /// Maps collection types like `Vec<i32>` to a `java.util.List` instead
+    /// of a primitive array like `int[]`.
+    pub fn with_java_collection(mut self, java_collection: bool) -> Self {
+        self.java_collection = java_collection;
+        self
     }
 
-    /// Returns the name of this derived entity.
-    pub fn name(&self) -> &str {
-        &self.name
+    /// Serializes the return value into a JSON `String`, which allows to
+    /// return any type implementing `serde::Serialize`.
+    pub fn with_json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
     }
 
-    /// Generates the JNI FFI wrapper functions for all the struct method
-    /// implementations.
-    pub fn export_jni_ffi_tokens(&self) -> TokenStream {
-        let mut stream = quote! {};
-        for func in &self.fns {
-            let struct_name = Ident::new(&self.name, Span::call_site());
-            let fn_name = Ident::new(&func.name, Span::call_site());
-            let jni_name = Ident::new(
-                &format!("Java_{}_{}", struct_name, &func.java_name()),
-                Span::call_site(),
-            );
+    /// Marks the function as having a doc comment, which is required for
+    /// every exported function in strict mode.
+    pub fn with_documented(mut self, documented: bool) -> Self {
+        self.documented = documented;
+        self
+    }
 
-            let raw_ret_type =
-                rust_to_jni_return_type(func).expect("Could not convert JNI return type");
+    /// Marks the function as only available on the given platform, like
+    /// `android`, which is documented in the java class and generates a
+    /// separate class with the methods of that platform.
+    ///
+    /// Panics if the platform is not a valid java identifier.
+    pub fn with_cfg_java(mut self, platform: &str) -> Self {
+        validate_identifier("platform", platform);
+        self.cfg_java = Some(platform.into());
+        self
+    }
 
-            let mut args = vec![];
-            let mut inner_args = vec![];
+    /// Annotates the native method with the androidx `@Keep`, so R8 doesn't
+    /// strip it from the app if it looks unused on the java side.
+    pub fn with_android_keep(mut self, android_keep: bool) -> Self {
+        self.android_keep = android_keep;
+        self
+    }
 
-            // add custom args
-            for arg in &func.args {
-                if let DerivedFnArg::Captured { name: _name, ty } = arg {
-                    args.push(self.raw_arg_to_expr(
-                        &arg.name().expect("Could not read java name"),
-                        rust_to_jni_type(ty).expect("Could not convert rust to jni type"),
-                    ));
+    /// Marks the return value as `#[must_use]`, which annotates the java
+    /// method with `@CheckReturnValue` if the annotation is imported.
+    pub fn with_must_use(mut self, must_use: bool) -> Self {
+        self.must_use = must_use;
+        self
+    }
 
-                    let convert_fn = format!(
-                        "roast::convert::convert_arg_{}(&env, {})",
-                        rust_to_jni_type(ty)
-                            .expect("Could not convert rust to jni type")
-                            .replace("roast::", "")
-                            .to_lowercase(),
-                        &arg.name().expect("Could not read java name")
-                    );
-                    inner_args
-                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
-                }
-            }
+    /// Returns true if the return value must be used, see `with_must_use`.
+    pub fn must_use(&self) -> bool {
+        self.must_use
+    }
 
-            // add JNI env
-            if raw_ret_type.is_some() || !inner_args.is_empty() {
-                // for now we only need the env if we parse return values
-                args.insert(0, self.raw_arg_to_expr("env", "roast::JNIEnv"));
-            } else {
-                args.insert(0, self.raw_arg_to_expr("_env", "roast::JNIEnv"));
-            }
-            // add JCLass (static method?)
-            if func.is_static() {
-                args.insert(1, self.raw_arg_to_expr("_class", "roast::JClass"));
-            } else {
-                args.insert(1, self.raw_arg_to_expr("_obj", "roast::JObject"));
-            }
+    /// Marks the function as `#[deprecated]` with the given note, which is
+    /// empty if the attribute has none.
+    ///
+    /// The java method is annotated with `@Deprecated`, with the note as its
+    /// `@deprecated` javadoc tag, and the ffi wrapper is deprecated as well.
+    pub fn with_deprecated(mut self, note: Option<String>) -> Self {
+        self.deprecated = note;
+        self
+    }
 
-            // todo: switch some
-            let expanded = if let Some(t) = raw_ret_type {
-                let retval = parse_str::<Expr>(&t).unwrap();
-                let convert_fn = format!(
-                    "roast::convert::convert_retval_{}",
-                    func.sanitized_return_type()
-                        .as_ref()
-                        .unwrap()
-                        .to_lowercase()
-                );
-                let convert_ret_fn_name = parse_str::<Expr>(&convert_fn).unwrap();
-                // we got a return value, so add a conversion wrapper
-                quote! {
-                    #[no_mangle]
-                    pub extern "system" fn #jni_name(#(#args),*) -> #retval {
-                       #convert_ret_fn_name(&env, #struct_name::#fn_name(#(#inner_args),*))
-                    }
-                }
-            } else {
-                // no return argument, skip the ret conversion
-                quote! {
-                    #[no_mangle]
-                    pub extern "system" fn #jni_name(#(#args),*) {
-                       #struct_name::#fn_name(#(#inner_args),*)
-                    }
-                }
-            };
-            stream.extend(expanded.into_iter());
+    /// Annotates the java return type with `@Nullable` or `@NonNull`, which
+    /// helps IDEs with the null-safety analysis of the generated class.
+    pub fn with_null_annotation(mut self, null_annotation: Option<NullAnnotation>) -> Self {
+        self.null_annotation = null_annotation;
+        self
+    }
+
+    /// Collects a returned `impl Iterator<Item = T>` or `Box<dyn Iterator<Item = T>>`
+    /// into a `Vec<T>`, which is then returned like any other `Vec<T>`.
+    ///
+    /// Panics if the function does not return an iterator.
+    pub fn with_collect(mut self, collect: bool) -> Self {
+        if collect {
+            let item = self
+                .return_type
+                .as_deref()
+                .and_then(iterator_item_type)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "#[roast(collect)] on {} requires an impl Iterator<Item = T> return type",
+                        self.name
+                    )
+                });
+            self.return_type = Some(format!("Vec<{}>", item));
         }
-        stream
+        self.collect = collect;
+        self
     }
 
-    /// Converts an arg tuple of name and type into a expression tree that
-    /// can be pushed into the quote macro.
-    fn raw_arg_to_expr(&self, name: &str, ty: &str) -> Expr {
-        parse_str::<Expr>(&format!("{}: {}", name, ty)).unwrap()
+    /// Sets the access modifier of the java method, which doesn't affect the
+    /// JNI symbol the method binds to.
+    pub fn with_visibility(mut self, visibility: JavaVisibility) -> Self {
+        self.visibility = visibility;
+        self
     }
 
-    // Generates the equivalent full java class file for the derived entity.
-    pub fn export_java_syntax(&self, lib_name: &str) -> Result<String, ConversionError> {
-        let mut converted_methods = String::new();
-        converted_methods.push_str(&format!(
-            "\n\tstatic {{\n\t\tSystem.loadLibrary(\"{}\");\n\t}}\n",
-            lib_name,
-        ));
+    /// Picks how the rust name is turned into the java method name, which is
+    /// camel case by default.
+    pub fn with_naming(mut self, naming: NamingStrategy) -> Self {
+        self.naming = naming;
+        self
+    }
 
-        for func in &self.fns {
-            let return_type = rust_to_java_return_type(func)?;
-            let mut args = vec![];
-            for arg in &func.args {
-                if let DerivedFnArg::Captured { name: _name, ty } = arg {
-                    args.push(format!(
-                        "{} {}",
-                        rust_to_java_type(ty).unwrap(),
-                        arg.java_name().unwrap()
-                    ));
-                }
+    /// Returns an enum to java as an `int`, converted with `Into<i32>`, so the
+    /// enum doesn't need to be exported itself.
+    ///
+    /// Panics if the function has no return value.
+    pub fn with_enum_as_int(mut self, enum_as_int: bool) -> Self {
+        if enum_as_int {
+            let ty = self
+                .return_type
+                .replace(String::from("i32"))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "#[roast(enum_as_int)] on {} requires an enum return type",
+                        self.name
+                    )
+                });
+            self.int_enum = Some(ty);
+        }
+        self
+    }
+
+    /// Marks the function as a method of the trait at the given path, like
+    /// `ops::Computable`, which is called as `<Entity as ops::Computable>`.
+    ///
+    /// The struct has no java object to call the method on, so `&self` and
+    /// `&mut self` take the native handle of the struct instead.
+    pub fn with_trait_path(mut self, trait_path: &str) -> Self {
+        for arg in self.args.iter_mut() {
+            if let DerivedFnArg::SelfBorrow { mutable } = *arg {
+                *arg = DerivedFnArg::NativeHandle {
+                    name: "handle".into(),
+                    mutable,
+                };
             }
+        }
+        self.trait_path = Some(trait_path.into());
+        self
+    }
 
-            let static_qualifier = if func.is_static() { " static" } else { "" };
-            let result = format!(
-                "\n\tpublic{} native {} {}({});\n",
-                static_qualifier,
-                return_type,
-                func.java_name(),
-                args.iter().join(", ")
+    /// Returns the path the function is called through from the generated
+    /// code, which is qualified with the trait for trait methods.
+    fn call_path(&self, struct_name: &Ident) -> proc_macro2::TokenStream {
+        let fn_name = Ident::new(&self.name, Span::call_site());
+        match &self.trait_path {
+            Some(trait_path) => {
+                let trait_path =
+                    parse_str::<syn::Path>(trait_path).expect("Could not parse trait path");
+                quote! { <#struct_name as #trait_path>::#fn_name }
+            }
+            None => quote! { #struct_name::#fn_name },
+        }
+    }
+
+    /// Returns a `Box<dyn Trait>` to java as a native handle, which is wrapped
+    /// by the generated java class of the trait, like `com.example.Plugin`.
+    ///
+    /// Panics if the function does not return a boxed trait object or the
+    /// java class is not named after the trait.
+    pub fn with_trait_object(mut self, java_class: &str) -> Self {
+        let trait_name = self
+            .return_type
+            .as_deref()
+            .and_then(boxed_trait_name)
+            .unwrap_or_else(|| {
+                panic!(
+                    "#[roast(trait_object)] on {} requires a Box<dyn Trait> return type",
+                    self.name
+                )
+            });
+        java_class
+            .split('.')
+            .for_each(|segment| validate_identifier("trait object class", segment));
+        if java_class.rsplit('.').next() != Some(trait_name.as_str()) {
+            panic!(
+                "The java class {} of {} must be named after the trait {}",
+                java_class, self.name, trait_name
             );
-            converted_methods.push_str(&result);
         }
+        self.trait_object = Some(java_class.into());
+        self
+    }
 
-        let result = format!("public class {} {{\n{}\n}}\n", self.name, converted_methods);
+    /// Returns the rust trait and the java class of a function returning a
+    /// trait object, see `with_trait_object`.
+    pub fn trait_object(&self) -> Option<(String, &str)> {
+        let java_class = self.trait_object.as_deref()?;
+        let trait_name = boxed_trait_name(self.return_type.as_deref()?)?;
+        Some((trait_name, java_class))
+    }
 
-        Ok(result)
+    /// Returns the annotation set with `with_null_annotation`, if any.
+    pub fn null_annotation(&self) -> Option<NullAnnotation> {
+        self.null_annotation
     }
-}
 
-/// Takes a derived function and returns its return type as a java string.
-///
-/// If the return type cannot be converted properly, a `ConversionError` is raised.
-fn rust_to_java_return_type(func: &DerivedFn) -> Result<String, ConversionError> {
-    let ret = &func.return_type;
+    /// Returns the nullness of the java return type, where an `Option<T>`
+    /// which is not returned as `java.util.Optional` is `@Nullable` unless
+    /// annotated otherwise.
+    pub fn return_null_annotation(&self) -> Option<NullAnnotation> {
+        self.return_type.as_ref()?;
+        self.null_annotation.or_else(|| {
+            (self.option_return_type().is_some() && !self.use_optional)
+                .then_some(NullAnnotation::Nullable)
+        })
+    }
 
-    Ok(match ret {
-        None => "void".into(),
-        Some(t) => match rust_to_java_type(t) {
-            Some(v) => v,
-            None => {
-                return Err(ConversionError::UnsupportedReturnType {
-                    rt: t.clone(),
-                    func: func.name.clone(),
-                })
-            }
+    /// Looks up how an argument or return type of this function maps to
+    /// java, taking `#[roast(java_collection)]` into account.
+    fn type_mapping(&self, ty: &str) -> Option<TypeMapping> {
+        self.java_collection
+            .then(|| rust_to_collection_mapping(ty))
+            .flatten()
+            .or_else(|| rust_to_type_mapping(ty))
+    }
+
+    /// Looks up how the return type of this function maps to java, where
+    /// `#[roast(json)]` return values are always passed as a `String`.
+    ///
+    /// A `Result<String, String>` is returned as its `Ok` string, the error
+    /// is thrown as an exception instead. It is only supported as return
+    /// value, so it isn't part of `rust_to_type_mapping`.
+    fn return_type_mapping(&self, ty: &str) -> Option<TypeMapping> {
+        if self.trait_object.is_some() {
+            rust_to_type_mapping("i64")
+        } else if self.json || ty == "Result<String,String>" {
+            rust_to_type_mapping("String")
+        } else {
+            self.type_mapping(ty)
         }
-        .into(),
-    })
-}
+    }
 
-fn rust_to_jni_return_type(func: &DerivedFn) -> Result<Option<String>, ConversionError> {
-    let ret = &func.return_type;
+    /// Returns the `T` if the function returns an `Option<T>`.
+    ///
+    /// Returns `None` for `#[roast(json)]`, which serializes the option.
+    pub fn option_return_type(&self) -> Option<&str> {
+        if self.json {
+            return None;
+        }
+        self.return_type
+            .as_deref()?
+            .strip_prefix("Option<")?
+            .strip_suffix('>')
+    }
 
-    Ok(match ret {
-        None => None,
-        Some(t) => match rust_to_jni_type(t) {
-            Some(v) if v == "roast::JString" => Some(v.to_lowercase()),
-            Some(v) => Some(v.into()),
-            None => {
-                return Err(ConversionError::UnsupportedReturnType {
-                    rt: t.clone(),
-                    func: func.name.clone(),
-                })
+    /// Returns the element types if the function returns a tuple, like
+    /// `["i32", "String"]` for `(i32, String)`.
+    ///
+    /// Returns `None` for `#[roast(json)]`, which serializes the tuple.
+    pub fn tuple_return_types(&self) -> Option<Vec<&str>> {
+        if self.json {
+            return None;
+        }
+        let inner = self
+            .return_type
+            .as_deref()?
+            .strip_prefix('(')?
+            .strip_suffix(')')?;
+        let mut types = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in inner.char_indices() {
+            match c {
+                '<' | '(' | '[' => depth += 1,
+                '>' | ')' | ']' => depth -= 1,
+                ',' if depth == 0 => {
+                    types.push(inner[start..i].trim());
+                    start = i + 1;
+                }
+                _ => (),
             }
-        },
-    })
-}
+        }
+        types.push(inner[start..].trim());
+        types.retain(|t| !t.is_empty());
+        Some(types)
+    }
 
-/// Converts the string representation of a rust type into its java
-/// equivalent.
-///
-/// Note that for now this method only supports primitive types since
-/// more complex types are not implemented as of writing this.
-///
-/// If None is returned, it means that theo proper conversion could be
-/// made.
-fn rust_to_java_type(ty: &str) -> Option<&'static str> {
-    Some(match ty {
-        "i8" => "byte",
-        "u8" => "boolean",
-        "i16" => "short",
-        "u16" => "char",
-        "i32" => "int",
-        "i64" => "long",
-        "f32" => "float",
-        "f64" => "double",
-        "bool" => "boolean",
-        "String" => "String",
-        "Vec<u8>" => "byte[]",
-        _ => return None,
-    })
-}
+    /// Returns the name of the java native method.
+    ///
+    /// Functions returning a tuple are exposed through a java method which
+    /// unpacks the result, so their native method gets a `Native` suffix.
+    pub fn jni_method_name(&self) -> String {
+        if self.tuple_return_types().is_some() {
+            format!("{}Native", self.java_name())
+        } else {
+            self.java_name()
+        }
+    }
 
-/// Converts the rust type into its JNI FFI equivalent type.
-fn rust_to_jni_type(ty: &str) -> Option<&'static str> {
-    Some(match ty {
-        "i8" => "roast::jbyte",
-        "u8" => "roast::jboolean",
-        "i16" => "roast::jshort",
-        "u16" => "roast::jchar",
-        "i32" => "roast::jint",
-        "i64" => "roast::jlong",
-        "f32" => "roast::jfloat",
-        "f64" => "roast::jdouble",
-        "bool" => "roast::jboolean",
-        "String" => "roast::JString",
-        "Vec<u8>" => "roast::jbyteArray",
-        _ => return None,
+    /// Returns the native method name as it appears in the JNI symbol, where
+    /// `_` is escaped as `_1`.
+    fn jni_symbol_name(&self) -> String {
+        self.jni_method_name().replace('_', "_1")
+    }
+
+    /// If the argument list contains a reference to self this method is
+    /// non-static, otherwise it is.
+    pub fn is_static(&self) -> bool {
+        for a in &self.args {
+            match a {
+                DerivedFnArg::SelfBorrow { .. } => return false,
+                DerivedFnArg::SelfOwned { .. } => return false,
+                _ => (),
+            }
+        }
+        true
+    }
+
+    /// Returns the kotlin `external fun` declaration of the function, like
+    /// `external fun addInt(a: Int, b: Int): Int`.
+    ///
+    /// Static functions are annotated with `@JvmStatic`, since they need to
+    /// be declared in the `companion object` of a kotlin class to end up
+    /// with the same JNI symbols as their java counterparts.
+    pub fn kotlin_signature(&self) -> Result<String, ConversionError> {
+        let jvm_static = if self.is_static() { "@JvmStatic " } else { "" };
+        Ok(format!(
+            "{}external {}",
+            jvm_static,
+            kotlin_fn_signature(self)?
+        ))
+    }
+
+    /// Returns why exporting this function is unsafe, if it is.
+    ///
+    /// Java holds on to its objects independently of rust, so taking `self`
+    /// by value moves it out from under the java object, and `&mut self`
+    /// allows mutable aliasing since java can call into the same object
+    /// from multiple threads at once.
+    pub fn unsafe_reason(&self) -> Option<&'static str> {
+        self.args.iter().find_map(|a| match a {
+            DerivedFnArg::SelfOwned { mutable: true } => {
+                Some("takes `mut self`, which transfers ownership away from the java object")
+            }
+            DerivedFnArg::SelfOwned { mutable: false } => {
+                Some("takes `self`, which transfers ownership away from the java object")
+            }
+            DerivedFnArg::SelfBorrow { mutable: true } => {
+                Some("takes `&mut self`, which java can alias from multiple threads")
+            }
+            DerivedFnArg::NativeHandle { mutable: true, .. } => {
+                Some("takes a mutable native handle, which java can alias from multiple threads")
+            }
+            _ => None,
+        })
+    }
+
+    /// Returns the rust style function name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the rust style function name turned into java style, see
+    /// `with_naming`.
+    pub fn java_name(&self) -> String {
+        self.naming.apply(&self.name)
+    }
+
+    /// Assembles the JNI type descriptor of this function, like `(IZ)[B`.
+    ///
+    /// If any of the argument or return types has no descriptor, a
+    /// `ConversionError` is raised.
+    pub fn jni_descriptor(&self) -> Result<String, ConversionError> {
+        let mut descriptor = String::from("(");
+        for arg in &self.args {
+            match arg {
+                DerivedFnArg::Captured { ty, .. } => {
+                    match self.type_mapping(ty).map(|m| m.jni_descriptor) {
+                        Some(d) => descriptor.push_str(d),
+                        None => return Err(self.unsupported_arg_type(ty)),
+                    }
+                }
+                DerivedFnArg::Callback { java_interface, .. } => {
+                    self.check_callbacks()?;
+                    descriptor.push_str(&format!("L{};", java_interface.replace('.', "/")))
+                }
+                DerivedFnArg::LossyString { .. } => descriptor.push_str("Ljava/lang/String;"),
+                DerivedFnArg::LossyFloat { .. } => descriptor.push('D'),
+                DerivedFnArg::NativeHandle { .. } => descriptor.push('J'),
+                DerivedFnArg::IntEnum { .. } => descriptor.push('I'),
+                _ => (),
+            }
+        }
+        descriptor.push(')');
+        match &self.return_type {
+            None => descriptor.push('V'),
+            Some(_) if self.tuple_return_types().is_some() => {
+                descriptor.push_str("[Ljava/lang/Object;")
+            }
+            Some(_) if self.use_optional && self.option_return_type().is_some() => {
+                descriptor.push_str("Ljava/util/Optional;")
+            }
+            Some(t) if self.option_return_type().is_some() => {
+                match self.option_return_type().and_then(rust_to_type_mapping) {
+                    // primitives are boxed, arrays and strings are nullable already
+                    Some(m) if m.jni_descriptor.len() == 1 => descriptor
+                        .push_str(&format!("Ljava/lang/{};", java_boxed_type(m.java_type))),
+                    Some(m) => descriptor.push_str(m.jni_descriptor),
+                    None => return Err(self.unsupported_return_type(t)),
+                }
+            }
+            Some(t) => match self.return_type_mapping(t).map(|m| m.jni_descriptor) {
+                Some(d) => descriptor.push_str(d),
+                None => return Err(self.unsupported_return_type(t)),
+            },
+        }
+        Ok(descriptor)
+    }
+
+    /// Takes the return type but simply removes all invalid chars so it can
+    /// be used in rust code as part of the function signatures.
+    ///
+    /// Slices are prefixed with `slice` so `&[u8]` does not end up as `u8`,
+    /// tuples are named by their length like `tuple2`, options by their
+    /// java flavor like `option_optional` and java collections like
+    /// `list_i32`. Return values serialized with `#[roast(json)]` are named
+    /// `json`.
+    pub fn sanitized_return_type(&self) -> Option<String> {
+        if self.json && self.return_type.is_some() {
+            return Some("json".into());
+        }
+        if self.trait_object.is_some() {
+            return Some("trait_object".into());
+        }
+        if let Some(types) = self.tuple_return_types() {
+            return Some(format!("tuple{}", types.len()));
+        }
+        if self.return_type.as_deref() == Some("Result<String,String>") {
+            return Some("result_string_string".into());
+        }
+        if self.option_return_type().is_some() {
+            let flavor = if self.use_optional {
+                "option_optional"
+            } else {
+                "option"
+            };
+            return Some(flavor.into());
+        }
+        self.return_type.as_ref().map(|t| {
+            let t = match rust_to_collection_mapping(t) {
+                Some(_) if self.java_collection => t.replace("Vec<", "list_"),
+                _ => t.clone(),
+            };
+            t.replace("&[", "slice")
+                .replace(['<', '>', ']', '&', ' '], "")
+        })
+    }
+}
+
+/// Describes a rust enum with data variants, which is exported as a java 17
+/// sealed interface with a record for each variant.
+#[derive(Debug)]
+pub struct DerivedEnum {
+    name: String,
+    variants: Vec<DerivedVariant>,
+    package: Option<String>,
+}
+
+/// A variant of a `DerivedEnum` with its named fields, where a unit variant
+/// has none.
+#[derive(Debug)]
+pub struct DerivedVariant {
+    name: String,
+    fields: Vec<(String, String)>,
+    unit: bool,
+}
+
+impl DerivedVariant {
+    /// Creates a variant with named fields, given as pairs of the field
+    /// name and its rust type.
+    ///
+    /// Panics if a name is not a valid java identifier.
+    pub fn new(name: &str, fields: Vec<(String, String)>) -> Self {
+        validate_identifier("variant", name);
+        for (field, _) in &fields {
+            validate_identifier("field", field);
+        }
+        DerivedVariant {
+            name: name.into(),
+            fields,
+            unit: false,
+        }
+    }
+
+    /// Creates a variant without fields, which serde writes as a plain
+    /// string instead of an object.
+    pub fn unit(name: &str) -> Self {
+        DerivedVariant {
+            unit: true,
+            ..DerivedVariant::new(name, vec![])
+        }
+    }
+
+    /// Returns the java type and the expression which reads the field from
+    /// the parsed JSON object of the variant.
+    fn java_field(&self, field: &str, ty: &str) -> Result<(&'static str, String), ConversionError> {
+        let number =
+            |method: &str| format!("((BigDecimal) fields.get(\"{}\")).{}()", field, method);
+        Ok(match ty {
+            "i32" => ("int", number("intValueExact")),
+            "i64" => ("long", number("longValueExact")),
+            "f32" => ("float", number("floatValue")),
+            "f64" => ("double", number("doubleValue")),
+            "bool" => ("boolean", format!("(Boolean) fields.get(\"{}\")", field)),
+            "String" => ("String", format!("(String) fields.get(\"{}\")", field)),
+            _ => {
+                return Err(ConversionError::UnsupportedFieldType {
+                    variant: self.name.clone(),
+                    field: field.into(),
+                    ty: ty.into(),
+                })
+            }
+        })
+    }
+}
+
+/// Returns the path of the java source file of a class in the given package,
+/// relative to the source root, following the directory layout of packages.
+fn java_source_path(package: Option<&str>, class_name: &str) -> String {
+    match package {
+        Some(package) => format!("{}/{}.java", package.replace('.', "/"), class_name),
+        None => format!("{}.java", class_name),
+    }
+}
+
+/// Parses the JSON written by serde into maps, strings, booleans and
+/// `BigDecimal` numbers, which is all the fields of a `DerivedEnum` need.
+const JAVA_JSON_PARSER: &str = r#"	private static Object parseJson(String json, int[] pos) {
+		skipWhitespace(json, pos);
+		char c = json.charAt(pos[0]);
+		if (c == '{') {
+			Map<String, Object> object = new HashMap<>();
+			pos[0]++;
+			skipWhitespace(json, pos);
+			if (json.charAt(pos[0]) == '}') {
+				pos[0]++;
+				return object;
+			}
+			do {
+				String key = (String) parseJson(json, pos);
+				skipWhitespace(json, pos);
+				pos[0]++;
+				object.put(key, parseJson(json, pos));
+				skipWhitespace(json, pos);
+			} while (json.charAt(pos[0]++) == ',');
+			return object;
+		}
+		if (c == '"') {
+			StringBuilder string = new StringBuilder();
+			pos[0]++;
+			for (char d = json.charAt(pos[0]++); d != '"'; d = json.charAt(pos[0]++)) {
+				if (d == '\\') {
+					d = json.charAt(pos[0]++);
+					switch (d) {
+						case 'b': d = '\b'; break;
+						case 'f': d = '\f'; break;
+						case 'n': d = '\n'; break;
+						case 'r': d = '\r'; break;
+						case 't': d = '\t'; break;
+						case 'u':
+							d = (char) Integer.parseInt(json.substring(pos[0], pos[0] + 4), 16);
+							pos[0] += 4;
+							break;
+						default:
+							break;
+					}
+				}
+				string.append(d);
+			}
+			return string.toString();
+		}
+		for (String literal : new String[] {"true", "false", "null"}) {
+			if (json.startsWith(literal, pos[0])) {
+				pos[0] += literal.length();
+				return literal.equals("null") ? null : Boolean.valueOf(literal);
+			}
+		}
+		int start = pos[0];
+		while (pos[0] < json.length() && "+-.0123456789eE".indexOf(json.charAt(pos[0])) >= 0) {
+			pos[0]++;
+		}
+		return new BigDecimal(json.substring(start, pos[0]));
+	}
+
+	private static void skipWhitespace(String json, int[] pos) {
+		while (pos[0] < json.length() && Character.isWhitespace(json.charAt(pos[0]))) {
+			pos[0]++;
+		}
+	}
+"#;
+
+impl DerivedEnum {
+    /// Creates a new enum description.
+    ///
+    /// Panics if the name is not a valid java identifier.
+    pub fn new(name: &str, variants: Vec<DerivedVariant>) -> Self {
+        validate_identifier("enum", name);
+        DerivedEnum {
+            name: name.into(),
+            variants,
+            package: None,
+        }
+    }
+
+    /// Puts the sealed interface into the given package, like `com.example`.
+    ///
+    /// Panics if a part of the package is not a valid java identifier.
+    pub fn with_package(mut self, package: &str) -> Self {
+        for part in package.split('.') {
+            validate_identifier("package", part);
+        }
+        self.package = Some(package.into());
+        self
+    }
+
+    /// Returns the path of the java source file of the sealed interface,
+    /// relative to the source root, like `com/example/Shape.java`.
+    pub fn java_source_path(&self) -> String {
+        java_source_path(self.package.as_deref(), &self.name)
+    }
+
+    /// Generates the sealed interface with a record per variant, whose
+    /// `fromJson` factory turns the JSON of the serialized rust enum, as
+    /// returned by a `#[roast(json)]` method, into the matching record.
+    ///
+    /// The fields are named in camel case and support `i32`, `i64`, `f32`,
+    /// `f64`, `bool` and `String`.
+    pub fn export_java_syntax(&self) -> Result<String, ConversionError> {
+        let mut records = String::new();
+        let mut unit_cases = String::new();
+        let mut struct_cases = String::new();
+        for variant in &self.variants {
+            let mut components = vec![];
+            let mut values = vec![];
+            for (field, ty) in &variant.fields {
+                let (java_type, value) = variant.java_field(field, ty)?;
+                components.push(format!("{} {}", java_type, field.to_camel_case()));
+                values.push(value);
+            }
+            records.push_str(&format!(
+                "\trecord {}({}) implements {} {{}}\n\n",
+                variant.name,
+                components.join(", "),
+                self.name
+            ));
+            let case = format!(
+                "\t\t\t\tcase \"{}\": return new {}({});\n",
+                variant.name,
+                variant.name,
+                values.join(", ")
+            );
+            if variant.unit {
+                unit_cases.push_str(&case);
+            } else {
+                struct_cases.push_str(&case);
+            }
+        }
+
+        let mut from_json = String::new();
+        if !unit_cases.is_empty() {
+            from_json.push_str(&format!(
+                "\t\tif (value instanceof String variant) {{\n\t\t\tswitch (variant) {{\n{}\
+                 \t\t\t}}\n\t\t}}\n",
+                unit_cases
+            ));
+        }
+        if !struct_cases.is_empty() {
+            from_json.push_str(&format!(
+                "\t\tif (value instanceof Map<?, ?> object && object.size() == 1) {{\n\
+                 \t\t\tMap.Entry<?, ?> entry = object.entrySet().iterator().next();\n\
+                 \t\t\tMap<?, ?> fields = (Map<?, ?>) entry.getValue();\n\
+                 \t\t\tswitch ((String) entry.getKey()) {{\n{}\t\t\t}}\n\t\t}}\n",
+                struct_cases
+            ));
+        }
+
+        Ok(format!(
+            "// Generated by roast from the rust enum {name}, requires java 17\n\
+             {package}\
+             import java.math.BigDecimal;\n\
+             import java.util.HashMap;\n\
+             import java.util.Map;\n\n\
+             public sealed interface {name} permits {permits} {{\n\n\
+             {records}\
+             \t/**\n\
+             \t * Creates the variant from the JSON of the rust enum, like the return\n\
+             \t * value of a method marked with #[roast(json)].\n\
+             \t */\n\
+             \tstatic {name} fromJson(String json) {{\n\
+             \t\tObject value = parseJson(json, new int[] {{0}});\n\
+             {from_json}\
+             \t\tthrow new IllegalArgumentException(\"Not a variant of {name}: \" + json);\n\
+             \t}}\n\n\
+             {parser}\
+             }}\n",
+            name = self.name,
+            package = self
+                .package
+                .as_ref()
+                .map(|package| format!("package {};\n\n", package))
+                .unwrap_or_default(),
+            permits = self
+                .variants
+                .iter()
+                .map(|v| format!("{}.{}", self.name, v.name))
+                .join(", "),
+            records = records,
+            from_json = from_json,
+            parser = JAVA_JSON_PARSER,
+        ))
+    }
+}
+
+/// Describes an associated constant of the derived struct.
+#[derive(Debug)]
+pub struct DerivedConst {
+    name: String,
+    ty: String,
+    value: String,
+}
+
+impl DerivedConst {
+    /// Creates a new constant description, where the value is the plain
+    /// literal like `-42` or the unquoted content of a string.
+    ///
+    /// Panics if the name is not a valid java identifier.
+    pub fn new(name: &str, ty: &str, value: &str) -> Self {
+        validate_identifier("constant", name);
+        DerivedConst {
+            name: name.into(),
+            ty: ty.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Returns the name of the constant.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the java type of the constant, or None if the type is not
+    /// supported for constants.
+    pub fn java_type(&self) -> Option<&'static str> {
+        match self.ty.as_str() {
+            "i32" => Some("int"),
+            "i64" => Some("long"),
+            "f64" => Some("double"),
+            "bool" => Some("boolean"),
+            "&str" => Some("String"),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a java literal, like `42L` for an `i64`.
+    fn java_literal(&self) -> String {
+        match self.ty.as_str() {
+            "i64" => format!("{}L", self.value),
+            "&str" => {
+                let mut literal = String::from("\"");
+                for c in self.value.chars() {
+                    match c {
+                        '"' => literal.push_str("\\\""),
+                        '\\' => literal.push_str("\\\\"),
+                        '\n' => literal.push_str("\\n"),
+                        '\r' => literal.push_str("\\r"),
+                        '\t' => literal.push_str("\\t"),
+                        c => literal.push(c),
+                    }
+                }
+                literal.push('"');
+                literal
+            }
+            _ => self.value.clone(),
+        }
+    }
+}
+
+/// A JNI function exported by `DerivedEntity::export_jni_ffi_tokens`, which
+/// `roast build` lists in `roast-symbols.json`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct JniSymbol {
+    pub symbol: String,
+    pub java_class: String,
+    pub java_method: String,
+    /// `None` if a type has no JNI descriptor
+    pub descriptor: Option<String>,
+    pub rust_function: String,
+}
+
+/// A `get_x`/`set_x` method pair which exposes the field `x`.
+#[derive(Debug)]
+pub struct AccessorPair<'a> {
+    pub field: String,
+    pub ty: String,
+    pub getter: &'a DerivedFn,
+}
+
+/// How the generated java class loads the native library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LibLoader {
+    /// `System.loadLibrary`, which searches the `java.library.path`
+    System,
+    /// nothing, the application loads the library before using the class
+    Manual,
+    /// `NativeLibLoader.loadFromJar`, which extracts the library from the
+    /// resources of the JAR
+    Resource,
+}
+
+/// The java class which loads a native library packaged in the JAR, see
+/// `LibLoader::Resource`.
+const JAVA_NATIVE_LIB_LOADER: &str = r#"import java.io.IOException;
+import java.io.InputStream;
+import java.nio.file.Files;
+import java.nio.file.Path;
+import java.nio.file.StandardCopyOption;
+import java.util.HashSet;
+import java.util.Set;
+
+/**
+ * Loads native libraries which are packaged as resources of the JAR, for
+ * deployments where System.loadLibrary can't find them.
+ */
+public final class NativeLibLoader {
+
+	private static final Set<String> LOADED = new HashSet<>();
+
+	private NativeLibLoader() {
+	}
+
+	/**
+	 * Extracts the library, like libfoo.so for "foo" on linux, from the root
+	 * of the resources into a temporary directory and loads it from there.
+	 */
+	public static synchronized void loadFromJar(String name) {
+		if (LOADED.contains(name)) {
+			return;
+		}
+		String fileName = System.mapLibraryName(name);
+		try (InputStream in = NativeLibLoader.class.getResourceAsStream("/" + fileName)) {
+			if (in == null) {
+				throw new UnsatisfiedLinkError("Native library " + fileName + " is not a resource");
+			}
+			Path dir = Files.createTempDirectory("roast");
+			dir.toFile().deleteOnExit();
+			Path lib = dir.resolve(fileName);
+			Files.copy(in, lib, StandardCopyOption.REPLACE_EXISTING);
+			lib.toFile().deleteOnExit();
+			System.load(lib.toAbsolutePath().toString());
+			LOADED.add(name);
+		} catch (IOException e) {
+			UnsatisfiedLinkError error = new UnsatisfiedLinkError("Could not extract " + fileName);
+			error.initCause(e);
+			throw error;
+		}
+	}
+}
+"#;
+
+/// Describes the entity which is derived with methods and all.
+#[derive(Debug)]
+pub struct DerivedEntity {
+    name: String,
+    fns: Vec<DerivedFn>,
+    constants: Vec<DerivedConst>,
+    utility_class: bool,
+    unsafe_allowed: bool,
+    thread_safe: bool,
+    strict_mode: bool,
+    annotation_imports: BTreeSet<String>,
+    lib_name: Option<String>,
+    lib_loader: LibLoader,
+    trait_object: Option<String>,
+    android_keep: bool,
+    extra_java_bodies: Vec<String>,
+    inner_class_of: Option<String>,
+    inner_classes: Vec<DerivedEntity>,
+    naming: NamingStrategy,
+    package: Option<String>,
+}
+
+impl DerivedEntity {
+    /// Creates a new `DerivedEntity`
+    ///
+    /// Panics if the name is not a valid java identifier.
+    pub fn new(name: &str, fns: Vec<DerivedFn>) -> Self {
+        validate_identifier("class", name);
+        DerivedEntity {
+            name: name.into(),
+            fns,
+            constants: vec![],
+            utility_class: false,
+            unsafe_allowed: false,
+            thread_safe: false,
+            strict_mode: false,
+            annotation_imports: BTreeSet::new(),
+            lib_name: None,
+            lib_loader: LibLoader::System,
+            trait_object: None,
+            android_keep: false,
+            extra_java_bodies: vec![],
+            inner_class_of: None,
+            inner_classes: vec![],
+            naming: NamingStrategy::Camel,
+            package: None,
+        }
+    }
+
+    /// Puts the java class into the given package, like `com.example`.
+    ///
+    /// Panics if a part of the package is not a valid java identifier.
+    pub fn with_package(mut self, package: &str) -> Self {
+        for part in package.split('.') {
+            validate_identifier("package", part);
+        }
+        self.package = Some(package.into());
+        self
+    }
+
+    /// Returns the java package of the class, which is the one set with
+    /// `with_package` or the one of the trait object class.
+    pub fn java_package(&self) -> Option<&str> {
+        match &self.trait_object {
+            Some(java_class) => java_class.rsplit_once('.').map(|(package, _)| package),
+            None => self.package.as_deref(),
+        }
+    }
+
+    /// Returns the path of the java source file of a class in the package of
+    /// the entity, relative to the source root, like `com/example/Foo.java`.
+    pub fn java_source_path(&self, class_name: &str) -> String {
+        java_source_path(self.java_package(), class_name)
+    }
+
+    /// Returns the name of the java class without its package, which is the
+    /// name of the entity unless it is a trait object class.
+    pub fn java_class_name(&self) -> &str {
+        match &self.trait_object {
+            Some(java_class) => java_class.rsplit('.').next().unwrap_or(java_class),
+            None => &self.name,
+        }
+    }
+
+    /// Returns the `package` declaration which starts the java files of the
+    /// entity, or nothing for the unnamed package.
+    fn java_package_declaration(&self) -> String {
+        self.java_package()
+            .map(|package| format!("package {};\n\n", package))
+            .unwrap_or_default()
+    }
+
+    /// Returns the `package` declaration which starts the kotlin files of the
+    /// entity, or nothing for the unnamed package.
+    fn kotlin_package_declaration(&self) -> String {
+        self.java_package()
+            .map(|package| format!("package {}\n\n", package))
+            .unwrap_or_default()
+    }
+
+    /// Adds associated constants, which are inlined into the java class
+    /// since they need no JNI wrapper.
+    pub fn with_constants(mut self, constants: Vec<DerivedConst>) -> Self {
+        self.constants = constants;
+        self
+    }
+
+    /// Allows exporting functions which are unsafe to call from java, see
+    /// `DerivedFn::unsafe_reason` for details.
+    pub fn with_unsafe_allowed(mut self, unsafe_allowed: bool) -> Self {
+        self.unsafe_allowed = unsafe_allowed;
+        self
+    }
+
+    /// Marks all methods as safe to call from multiple java threads, which
+    /// makes them `synchronized` and requires the struct to be `Send + Sync`.
+    pub fn with_thread_safe(mut self, thread_safe: bool) -> Self {
+        self.thread_safe = thread_safe;
+        self
+    }
+
+    /// Turns the situations which would otherwise generate suboptimal code
+    /// into compile errors, see `DerivedEntity::strict_violations`.
+    pub fn with_strict_mode(mut self, strict_mode: bool) -> Self {
+        self.strict_mode = strict_mode;
+        self
+    }
+
+    /// Generates the java class as a final utility class with a private
+    /// constructor, which makes sense if all methods are static.
+    pub fn with_utility_class(mut self, utility_class: bool) -> Self {
+        self.utility_class = utility_class;
+        self
+    }
+
+    /// Adds the fully qualified null annotations, like
+    /// `androidx.annotation.Nullable`, which are imported into the java class.
+    ///
+    /// Return types are only annotated if the matching annotation is imported.
+    pub fn with_annotation_imports(mut self, annotation_imports: BTreeSet<String>) -> Self {
+        self.annotation_imports = annotation_imports;
+        self
+    }
+
+    /// Annotates the whole java class with the androidx `@Keep`, so R8
+    /// keeps it and all of its native methods in minified android builds.
+    pub fn with_android_keep(mut self, android_keep: bool) -> Self {
+        self.android_keep = android_keep;
+        self
+    }
+
+    /// Appends hand-written java code, like a `toString()` which calls the
+    /// native methods, verbatim to the body of the java class.
+    ///
+    /// Can be called more than once, the code is added in the same order.
+    pub fn with_extra_java_body(mut self, code: String) -> Self {
+        self.extra_java_bodies.push(code);
+        self
+    }
+
+    /// Turns the entity into a `public static class` nested in the java class
+    /// of the given outer entity, which binds the native methods to the
+    /// `Outer$Inner` class.
+    ///
+    /// Panics if the outer name is not a valid java identifier.
+    pub fn with_inner_class_of(mut self, outer: &str) -> Self {
+        validate_identifier("class", outer);
+        self.inner_class_of = Some(outer.into());
+        self
+    }
+
+    /// Nests the java class of the given entity, see `with_inner_class_of`,
+    /// into the java class of this entity.
+    pub fn with_inner_class(mut self, inner: DerivedEntity) -> Self {
+        self.inner_classes.push(inner);
+        self
+    }
+
+    /// Applies the naming strategy to all methods, see `DerivedFn::with_naming`.
+    pub fn with_naming(mut self, naming: NamingStrategy) -> Self {
+        self.naming = naming;
+        self.fns = self
+            .fns
+            .into_iter()
+            .map(|f| f.with_naming(naming))
+            .collect();
+        self
+    }
+
+    /// Returns the outer class set with `with_inner_class_of`.
+    pub fn inner_class_of(&self) -> Option<&str> {
+        self.inner_class_of.as_deref()
+    }
+
+    /// Sets the name of the native library loaded by the java class, like
+    /// `foo_jni` for `libfoo_jni.so`, if it differs from the package name.
+    pub fn with_lib_name(mut self, lib_name: &str) -> Self {
+        self.lib_name = Some(lib_name.into());
+        self
+    }
+
+    /// Sets how the java class loads the native library, which is
+    /// `System.loadLibrary` by default.
+    pub fn with_lib_loader(mut self, lib_loader: LibLoader) -> Self {
+        self.lib_loader = lib_loader;
+        self
+    }
+
+    /// Returns the loader set with `with_lib_loader`.
+    pub fn lib_loader(&self) -> LibLoader {
+        self.lib_loader
+    }
+
+    /// Returns the call which loads the native library, without the
+    /// trailing semicolon, or None if the application loads it itself.
+    fn load_library_call(&self, lib_name: &str) -> Option<String> {
+        match self.lib_loader {
+            LibLoader::System => Some(format!("System.loadLibrary(\"{}\")", lib_name)),
+            LibLoader::Manual => None,
+            LibLoader::Resource => Some(format!("NativeLibLoader.loadFromJar(\"{}\")", lib_name)),
+        }
+    }
+
+    /// Turns the entity into the java class wrapping a trait object, like
+    /// `com.example.Plugin`, where the entity is named after the trait.
+    ///
+    /// The `&self` methods of the trait take the native handle of the boxed
+    /// trait object, which is freed again by `destroy()`.
+    pub fn with_trait_object(mut self, java_class: &str) -> Self {
+        self.trait_object = Some(java_class.into());
+        self
+    }
+
+    /// Returns the rust trait and the java class of every trait object which
+    /// is returned by the methods, see `DerivedFn::with_trait_object`.
+    pub fn trait_objects(&self) -> Vec<(String, &str)> {
+        self.fns
+            .iter()
+            .filter_map(DerivedFn::trait_object)
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    /// Returns the binary name of the java class, which includes the java
+    /// package and the outer class of an inner class, like
+    /// `com.example.Outer$Inner`.
+    fn java_binary_name(&self) -> String {
+        let name = match (&self.trait_object, &self.inner_class_of) {
+            (Some(java_class), _) => return java_class.clone(),
+            (None, Some(outer)) => format!("{}${}", outer, self.name),
+            (None, None) => self.name.clone(),
+        };
+        match &self.package {
+            Some(package) => format!("{}.{}", package, name),
+            None => name,
+        }
+    }
+
+    /// Returns the class name used in the JNI symbols, see `java_binary_name`.
+    fn jni_class_name(&self) -> String {
+        self.java_binary_name()
+            .replace('_', "_1")
+            .replace('.', "_")
+            .replace('$', "_00024")
+    }
+
+    /// Returns the name of the native library set with `with_lib_name`.
+    pub fn lib_name(&self) -> Option<&str> {
+        self.lib_name.as_deref()
+    }
+
+    /// Returns the imported annotation for the nullness of the return type of
+    /// the given function, like `@Nullable `.
+    ///
+    /// Primitive return types are never null, so they are not annotated.
+    fn java_null_annotation(&self, func: &DerivedFn, java_type: &str) -> String {
+        let names: &[&str] = match func.return_null_annotation() {
+            _ if is_java_primitive(java_type) => return String::new(),
+            Some(NullAnnotation::Nullable) => &["Nullable"],
+            Some(NullAnnotation::NonNull) => &["NonNull", "NotNull"],
+            None => return String::new(),
+        };
+        self.annotation_imports
+            .iter()
+            .filter_map(|import| import.rsplit('.').next())
+            .find(|name| names.contains(name))
+            .map(|name| format!("@{} ", name))
+            .unwrap_or_default()
+    }
+
+    /// Returns the `@CheckReturnValue` line of a `#[must_use]` function, if
+    /// one of the annotations is imported.
+    fn java_check_return_value_annotation(&self, func: &DerivedFn) -> &'static str {
+        let imported = self
+            .annotation_imports
+            .iter()
+            .any(|import| import.ends_with(".CheckReturnValue"));
+        if func.must_use && imported {
+            "\t@CheckReturnValue\n"
+        } else {
+            ""
+        }
+    }
+
+    /// Returns true if there are methods and none of them take `self`.
+    pub fn is_static_only(&self) -> bool {
+        !self.fns.is_empty() && self.fns.iter().all(DerivedFn::is_static)
+    }
+
+    /// Returns the name of this derived entity.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the number of methods exported for this entity.
+    pub fn methods_count(&self) -> usize {
+        self.fns.len()
+    }
+
+    /// Returns true if there are no methods exported for this entity.
+    pub fn is_empty(&self) -> bool {
+        self.methods_count() == 0
+    }
+
+    /// Lists the methods which are defined more than once for this entity
+    /// and its inner classes, for example in impl blocks in different files.
+    /// Only the first definition is exported.
+    pub fn duplicate_warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .fns
+            .iter()
+            .filter(|func| !func.duplicate_locations.is_empty())
+            .map(|func| {
+                format!(
+                    "{}::{} is defined more than once, at {} and {}, only the first is exported",
+                    self.name,
+                    func.name,
+                    func.source_location().unwrap_or_else(|| String::from("?")),
+                    func.duplicate_locations.join(" and ")
+                )
+            })
+            .collect();
+        for inner in &self.inner_classes {
+            warnings.extend(inner.duplicate_warnings());
+        }
+        warnings
+    }
+
+    /// Lists the methods of this entity and its inner classes which take both
+    /// `f32` and `f64` arguments.
+    ///
+    /// The `f32` arguments are java floats, so callers passing a double need
+    /// a cast. Mixing both is often a typo in the rust signature, which would
+    /// silently change the java signature.
+    pub fn float_precision_warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self
+            .fns
+            .iter()
+            .filter(|func| {
+                let has_arg = |float: &str| {
+                    func.args
+                        .iter()
+                        .any(|arg| matches!(arg, DerivedFnArg::Captured { ty, .. } if ty == float))
+                };
+                has_arg("f32") && has_arg("f64")
+            })
+            .map(|func| {
+                format!(
+                    "{}::{} mixes f32 and f64 arguments, the f32 ones are java floats, mark \
+                     them with #[roast(lossy)] to accept a double, or silence this with \
+                     #[roast(allow_mixed_floats)] on the struct",
+                    self.name, func.name
+                )
+            })
+            .collect();
+        for inner in &self.inner_classes {
+            warnings.extend(inner.float_precision_warnings());
+        }
+        warnings
+    }
+
+    /// Returns the methods of this entity and its inner classes by name, in
+    /// the format of the `ROAST_METADATA_PATH` file.
+    pub fn export_metadata(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert(
+            self.name.clone(),
+            serde_json::to_value(&self.fns).expect("Could not serialize methods"),
+        );
+        for inner in &self.inner_classes {
+            metadata.extend(inner.export_metadata());
+        }
+        metadata
+    }
+
+    /// Lists everything that is rejected in strict mode, which is an entity
+    /// without methods, methods without doc comments and method names which
+    /// are not snake case, since they do not turn into proper java names.
+    /// Names are not checked with `NamingStrategy::Verbatim`.
+    pub fn strict_violations(&self) -> Vec<String> {
+        let mut violations = vec![];
+        if self.is_empty() {
+            violations.push(format!(
+                "{} derives RoastExport but has no public methods to export",
+                self.name
+            ));
+        }
+        for func in &self.fns {
+            if !func.documented {
+                violations.push(format!("{}::{} has no doc comment", self.name, func.name));
+            }
+            if self.naming != NamingStrategy::Verbatim && func.name != func.name.to_snake_case() {
+                violations.push(format!("{}::{} is not snake case", self.name, func.name));
+            }
+        }
+        violations
+    }
+
+    /// Checks that the methods can be turned into a valid java class.
+    ///
+    /// Methods must not share the same java name, like `get_x` and `getX`,
+    /// since java can't tell them apart if their arguments are identical and
+    /// the JNI function names of overloads collide otherwise. Method and
+    /// argument names must not be java keywords either.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = vec![];
+        for (i, func) in self.fns.iter().enumerate() {
+            let java_name = func.java_name();
+            if let Some(other) = self.fns[..i].iter().find(|f| f.java_name() == java_name) {
+                if java_arg_types(other) == java_arg_types(func) {
+                    errors.push(format!(
+                        "{}::{} and {}::{} both map to the java method {}",
+                        self.name, other.name, self.name, func.name, java_name
+                    ));
+                } else {
+                    errors.push(format!(
+                        "{}::{} and {}::{} overload the java method {}, which is not supported \
+                         by JNI",
+                        self.name, other.name, self.name, func.name, java_name
+                    ));
+                }
+            }
+            if is_java_keyword(&java_name) {
+                errors.push(format!(
+                    "{}::{} maps to the java keyword {}",
+                    self.name, func.name, java_name
+                ));
+            }
+            if self.trait_object.is_some() && java_name == "destroy" {
+                errors.push(format!(
+                    "{}::{} clashes with the destroy method of the trait object class",
+                    self.name, func.name
+                ));
+            }
+            for arg in &func.args {
+                if let Some(name) = arg.java_name().filter(|n| is_java_keyword(n)) {
+                    errors.push(format!(
+                        "The argument {} of {}::{} is a java keyword",
+                        name, self.name, func.name
+                    ));
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Finds all getter and setter pairs by their naming convention.
+    ///
+    /// A getter `get_x` takes no arguments apart from `self` or a
+    /// `#[roast(handle)]` of the struct and returns the field type, while
+    /// the matching setter `set_x` takes a single argument of the same type
+    /// and returns nothing. Pairs are returned in the order of their getters.
+    pub fn detect_accessor_pairs(&self) -> Vec<AccessorPair<'_>> {
+        let captured = |func: &DerivedFn| -> Vec<String> {
+            func.args
+                .iter()
+                .filter_map(|a| match a {
+                    DerivedFnArg::Captured { ty, .. } => Some(ty.clone()),
+                    DerivedFnArg::SelfBorrow { .. }
+                    | DerivedFnArg::SelfOwned { .. }
+                    | DerivedFnArg::NativeHandle { .. } => None,
+                    _ => Some(String::new()),
+                })
+                .collect()
+        };
+
+        let mut pairs = vec![];
+        for getter in &self.fns {
+            let field = match getter.name.strip_prefix("get_") {
+                Some(f) if !f.is_empty() => f,
+                _ => continue,
+            };
+            let ty = match &getter.return_type {
+                Some(ty) if captured(getter).is_empty() && !getter.error_return => ty,
+                _ => continue,
+            };
+            let setter_name = format!("set_{}", field);
+            let has_setter = self.fns.iter().any(|f| {
+                f.name == setter_name && f.return_type.is_none() && captured(f) == [ty.clone()]
+            });
+            if has_setter {
+                pairs.push(AccessorPair {
+                    field: field.into(),
+                    ty: ty.clone(),
+                    getter,
+                });
+            }
+        }
+        pairs
+    }
+
+    /// Generates the kotlin data class which holds the values of all
+    /// accessor pairs, see `detect_accessor_pairs`.
+    ///
+    /// It is named after the entity with a `Data` suffix so it doesn't clash
+    /// with the `expect object`, and goes into the `commonMain` source set.
+    /// The `fromNative` factory passes its handle on to every getter which
+    /// takes a `#[roast(handle)]`. Returns `None` if there are no accessor
+    /// pairs.
+    pub fn export_kotlin_data_class_syntax(&self) -> Result<Option<String>, ConversionError> {
+        let pairs = self.detect_accessor_pairs();
+        if pairs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut fields = vec![];
+        let mut reads = vec![];
+        for pair in &pairs {
+            let ty = match rust_to_type_mapping(&pair.ty).map(|m| m.kotlin_type) {
+                Some(t) => t,
+                None => return Err(pair.getter.unsupported_return_type(&pair.ty)),
+            };
+            fields.push(format!("val {}: {}", pair.field.to_camel_case(), ty));
+            let handle = pair
+                .getter
+                .args
+                .iter()
+                .any(|a| matches!(a, DerivedFnArg::NativeHandle { .. }));
+            reads.push(format!(
+                "{}.{}({})",
+                self.name,
+                pair.getter.java_name(),
+                if handle { "handle" } else { "" }
+            ));
+        }
+
+        Ok(Some(format!(
+            "{}data class {name}Data({}) {{\n\n\tcompanion object {{\n\n\t\t\
+             fun fromNative(handle: Long): {name}Data = {name}Data({})\n\n\t}}\n\n}}\n",
+            self.kotlin_package_declaration(),
+            fields.iter().join(", "),
+            reads.iter().join(", "),
+            name = self.name
+        )))
+    }
+
+    /// Generates the JNI FFI wrapper functions for all the struct method
+    /// implementations.
+    ///
+    /// Functions which are unsafe to export generate a `compile_error!`
+    /// instead, unless they have been allowed explicitly. In strict mode,
+    /// every strict violation generates a `compile_error!` as well.
+    pub fn export_jni_ffi_tokens(&self) -> TokenStream {
+        let mut stream = quote! {};
+        if self.strict_mode {
+            for violation in self.strict_violations() {
+                let message = format!("{} (#[roast(strict)])", violation);
+                stream.extend(quote! { compile_error!(#message); });
+            }
+        }
+        for func in &self.fns {
+            if let Some(reason) = func.unsafe_reason() {
+                if !self.unsafe_allowed {
+                    let message = format!(
+                        "{}::{} {}. Add #[roast(unsafe_allowed)] to {} if this is intended.",
+                        self.name, func.name, reason, self.name
+                    );
+                    stream.extend(quote! { compile_error!(#message); });
+                    continue;
+                }
+            }
+            if let Err(e) = func.check_callbacks() {
+                let message = e.to_string();
+                stream.extend(quote! { compile_error!(#message); });
+                continue;
+            }
+            let struct_name = Ident::new(&self.name, Span::call_site());
+            let jni_name = Ident::new(
+                &format!("Java_{}_{}", self.jni_class_name(), &func.jni_symbol_name()),
+                Span::call_site(),
+            );
+
+            let raw_ret_type =
+                rust_to_jni_return_type(func).expect("Could not convert JNI return type");
+
+            let mut args = vec![];
+            let mut inner_args = vec![];
+            // mutable slices are converted before and copied back after the call
+            let mut converted_args = vec![];
+            let mut write_backs = vec![];
+
+            // add custom args
+            for arg in &func.args {
+                if let (DerivedFnArg::SelfBorrow { mutable }, Some(_)) = (arg, &self.trait_object) {
+                    args.push(self.raw_arg_to_expr("native_handle", "roast::jlong"));
+
+                    let (borrow, convert) = if *mutable {
+                        ("&mut **", "mut_")
+                    } else {
+                        ("&**", "")
+                    };
+                    let convert_fn = format!(
+                        "{}unsafe {{ roast::convert::convert_handle_to_{}ref::<Box<dyn {}>>(\
+                         &env, native_handle) }}",
+                        borrow, convert, self.name
+                    );
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+                if let DerivedFnArg::Captured { name: _name, ty } = arg {
+                    let mapping = func
+                        .type_mapping(ty)
+                        .expect("Could not convert rust to jni type");
+                    let name = arg.name().expect("Could not read java name");
+                    args.push(self.raw_arg_to_expr(&name, mapping.jni_type));
+
+                    if let Some(write_back_fn) = write_back_arg_fn(ty) {
+                        let converted = format!("{}_mut", name);
+                        let convert_fn = parse_str::<Expr>(&format!(
+                            "roast::convert::{}(&env, {})",
+                            mapping.convert_arg_fn, name
+                        ))
+                        .expect("Could not parse expression");
+                        let write_back = parse_str::<Expr>(&format!(
+                            "roast::convert::{}(&env, {}, &{})",
+                            write_back_fn, name, converted
+                        ))
+                        .expect("Could not parse expression");
+                        inner_args.push(
+                            parse_str::<Expr>(&format!("&mut {}", converted))
+                                .expect("Could not parse expression"),
+                        );
+                        let converted = Ident::new(&converted, Span::call_site());
+                        converted_args.push(quote! { let mut #converted = #convert_fn; });
+                        write_backs.push(quote! { #write_back; });
+                        continue;
+                    }
+
+                    let convert_fn = format!(
+                        "roast::convert::{}(&env, {}){}",
+                        mapping.convert_arg_fn,
+                        &arg.name().expect("Could not read java name"),
+                        borrow_converted_arg(ty)
+                    );
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+                if let DerivedFnArg::Callback {
+                    name,
+                    java_interface,
+                } = arg
+                {
+                    args.push(self.raw_arg_to_expr(name, "roast::JObject"));
+
+                    let convert_fn = format!(
+                        "roast::convert::{}(&env, {})",
+                        callback_convert_fn(java_interface).expect("Unsupported callback"),
+                        name
+                    );
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+                if let DerivedFnArg::LossyString { name } = arg {
+                    args.push(self.raw_arg_to_expr(name, "roast::JString"));
+
+                    let convert_fn =
+                        format!("roast::convert::convert_arg_jstring_lossy(&env, {})", name);
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+                if let DerivedFnArg::LossyFloat { name } = arg {
+                    args.push(self.raw_arg_to_expr(name, "roast::jdouble"));
+
+                    let convert_fn = format!(
+                        "roast::convert::convert_arg_jdouble_to_f32_lossy(&env, {})",
+                        name
+                    );
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+                if let DerivedFnArg::NativeHandle { name, mutable } = arg {
+                    args.push(self.raw_arg_to_expr(name, "roast::jlong"));
+
+                    let convert_fn = format!(
+                        "unsafe {{ roast::convert::convert_handle_to_{}ref::<{}>(&env, {}) }}",
+                        if *mutable { "mut_" } else { "" },
+                        self.name,
+                        name
+                    );
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+                if let DerivedFnArg::IntEnum { name, ty } = arg {
+                    args.push(self.raw_arg_to_expr(name, "roast::jint"));
+
+                    let convert_fn = format!("{}::from({})", ty, name);
+                    inner_args
+                        .push(parse_str::<Expr>(&convert_fn).expect("Could not parse expression"));
+                }
+            }
+
+            // add JNI env
+            if raw_ret_type.is_some() || !inner_args.is_empty() {
+                // for now we only need the env if we parse return values
+                args.insert(0, self.raw_arg_to_expr("env", "roast::JNIEnv"));
+            } else {
+                args.insert(0, self.raw_arg_to_expr("_env", "roast::JNIEnv"));
+            }
+            // add JCLass (static method?)
+            // _class: JClass is required by JNI static calling convention,
+            // even though the generated wrappers never use it.
+            if func.is_static() || self.trait_object.is_some() {
+                args.insert(1, self.raw_arg_to_expr("_class", "roast::JClass"));
+            } else {
+                args.insert(1, self.raw_arg_to_expr("_obj", "roast::JObject"));
+            }
+
+            let call_path = func.call_path(&struct_name);
+            let mut call = quote! { #call_path(#(#inner_args),*) };
+            if func.collect {
+                let collected = parse_str::<syn::Type>(func.return_type.as_deref().unwrap())
+                    .expect("Could not parse collected type");
+                call = if func.error_return {
+                    quote! { #call.map(|values| values.collect::<#collected>()) }
+                } else {
+                    quote! { #call.collect::<#collected>() }
+                };
+            }
+            if func.int_enum.is_some() {
+                call = if func.error_return {
+                    quote! { #call.map(Into::<i32>::into) }
+                } else {
+                    quote! { Into::<i32>::into(#call) }
+                };
+            }
+            if !write_backs.is_empty() {
+                call = quote! {{
+                    #(#converted_args)*
+                    let result = #call;
+                    #(#write_backs)*
+                    result
+                }};
+            }
+
+            // the wrapper calls the deprecated function on purpose, so only
+            // its own callers are warned
+            let deprecated = match func.deprecated.as_deref() {
+                Some("") => quote! { #[deprecated] #[allow(deprecated)] },
+                Some(note) => quote! { #[deprecated(note = #note)] #[allow(deprecated)] },
+                None => quote! {},
+            };
+
+            // todo: switch some
+            let expanded = if let Some(t) = raw_ret_type {
+                let retval = parse_str::<Expr>(&t).unwrap();
+                let convert_fn = format!(
+                    "roast::convert::convert_retval_{}",
+                    func.sanitized_return_type()
+                        .as_ref()
+                        .unwrap()
+                        .to_lowercase()
+                );
+                let convert_ret_fn_name = parse_str::<Expr>(&convert_fn).unwrap();
+                if func.error_return {
+                    // the err variant is thrown, the ok variant converted as usual
+                    quote! {
+                        #deprecated
+                        #[no_mangle]
+                        pub extern "system" fn #jni_name(#(#args),*) -> #retval {
+                           roast::convert::convert_retval_result_box_dyn_error(
+                               &env,
+                               #call,
+                               #convert_ret_fn_name
+                           )
+                        }
+                    }
+                } else {
+                    // we got a return value, so add a conversion wrapper
+                    quote! {
+                        #deprecated
+                        #[no_mangle]
+                        pub extern "system" fn #jni_name(#(#args),*) -> #retval {
+                           #convert_ret_fn_name(&env, #call)
+                        }
+                    }
+                }
+            } else {
+                // no return argument, skip the ret conversion
+                quote! {
+                    #deprecated
+                        #[no_mangle]
+                    pub extern "system" fn #jni_name(#(#args),*) {
+                       #call
+                    }
+                }
+            };
+            stream.extend(expanded.into_iter());
+        }
+        if self.trait_object.is_some() {
+            let trait_name = Ident::new(&self.name, Span::call_site());
+            let jni_name = Ident::new(
+                &format!("Java_{}_destroy", self.jni_class_name()),
+                Span::call_site(),
+            );
+            stream.extend(quote! {
+                #[no_mangle]
+                pub extern "system" fn #jni_name(
+                    env: roast::JNIEnv,
+                    _class: roast::JClass,
+                    native_handle: roast::jlong
+                ) {
+                    unsafe { roast::convert::drop_handle::<Box<dyn #trait_name>>(&env, native_handle) }
+                }
+            });
+        }
+        if !self.constants.is_empty() {
+            // constants are inlined into java, so rust would consider them unused
+            let struct_name = Ident::new(&self.name, Span::call_site());
+            let names = self
+                .constants
+                .iter()
+                .map(|c| Ident::new(&c.name, Span::call_site()));
+            stream.extend(quote! {
+                const _: () = {
+                    #(let _ = #struct_name::#names;)*
+                };
+            });
+        }
+        stream
+    }
+
+    /// Lists the JNI functions generated by `export_jni_ffi_tokens`, including
+    /// the `destroy` function of a trait object class.
+    pub fn jni_symbols(&self) -> Vec<JniSymbol> {
+        let java_class = self.java_binary_name();
+        let mut symbols: Vec<JniSymbol> = self
+            .fns
+            .iter()
+            .map(|func| {
+                let descriptor = func.jni_descriptor().ok().map(|d| match self.trait_object {
+                    // the native handle is passed in front of the arguments
+                    Some(_) => d.replacen('(', "(J", 1),
+                    None => d,
+                });
+                JniSymbol {
+                    symbol: format!("Java_{}_{}", self.jni_class_name(), func.jni_symbol_name()),
+                    java_class: java_class.clone(),
+                    java_method: func.jni_method_name(),
+                    descriptor,
+                    rust_function: format!("{}::{}", self.name, func.name),
+                }
+            })
+            .collect();
+        if self.trait_object.is_some() {
+            symbols.push(JniSymbol {
+                symbol: format!("Java_{}_destroy", self.jni_class_name()),
+                java_class,
+                java_method: String::from("destroy"),
+                descriptor: Some(String::from("(J)V")),
+                rust_function: String::from("roast::convert::drop_handle"),
+            });
+        }
+        symbols
+    }
+
+    /// Generates a `register_natives` function on the struct which registers
+    /// all JNI FFI wrapper functions through `RegisterNatives`.
+    ///
+    /// This is faster than the symbol lookup the JVM performs otherwise and
+    /// is preferred on android. It is usually called from `JNI_OnLoad`.
+    pub fn generate_jni_registration_table(&self) -> TokenStream {
+        let struct_name = Ident::new(&self.name, Span::call_site());
+        let mut methods = vec![];
+        for func in &self.fns {
+            let java_name = func.jni_method_name();
+            let jni_name = Ident::new(
+                &format!("Java_{}_{}", self.jni_class_name(), &func.jni_symbol_name()),
+                Span::call_site(),
+            );
+            let descriptor = match func.jni_descriptor() {
+                Ok(d) => d,
+                Err(e) => panic!("{}", e),
+            };
+            methods.push(quote! {
+                roast::NativeMethod {
+                    name: #java_name.into(),
+                    sig: #descriptor.into(),
+                    fn_ptr: #jni_name as *mut std::ffi::c_void
+                }
+            });
+        }
+        let count = methods.len();
+        quote! {
+            impl #struct_name {
+                pub fn register_natives(env: &roast::JNIEnv, class: roast::JClass) {
+                    let methods: [roast::NativeMethod; #count] = [#(#methods),*];
+                    env.register_native_methods(class, &methods)
+                        .expect("Could not register native methods!");
+                }
+            }
+        }
+    }
+
+    /// Generates a compile time check that the struct is `Send + Sync`, which
+    /// fails the build with a trait bound error otherwise.
+    pub fn generate_thread_safety_assertion(&self) -> TokenStream {
+        let struct_name = Ident::new(&self.name, Span::call_site());
+        quote! {
+            const _: () = {
+                fn assert_send_sync<T: Send + Sync>() {}
+                fn assert_all() {
+                    assert_send_sync::<#struct_name>();
+                }
+            };
+        }
+    }
+
+    /// Converts an arg tuple of name and type into a expression tree that
+    /// can be pushed into the quote macro.
+    fn raw_arg_to_expr(&self, name: &str, ty: &str) -> Expr {
+        parse_str::<Expr>(&format!("{}: {}", name, ty)).unwrap()
+    }
+
+    // Generates the equivalent full java class file for the derived entity.
+    pub fn export_java_syntax(&self, lib_name: &str) -> Result<String, ConversionError> {
+        self.validate()
+            .map_err(|errors| ConversionError::InvalidEntity {
+                entity: self.name.clone(),
+                errors: errors.join(", "),
+            })?;
+        if self.trait_object.is_some() {
+            return self.export_java_trait_object_syntax(lib_name);
+        }
+        let mut converted_methods = self.java_class_body(Some(lib_name))?;
+        for inner in &self.inner_classes {
+            converted_methods.push_str(&inner.export_java_inner_class_syntax()?);
+        }
+
+        let (header, class_qualifier) = if self.utility_class {
+            ("", "public final class")
+        } else if self.is_static_only() {
+            (
+                "// _class is unused; consider using a plain Java utility class\n",
+                "public class",
+            )
+        } else {
+            ("", "public class")
+        };
+        let nested = || self.inner_classes.iter();
+        let mut annotations = self.annotation_imports.clone();
+        annotations.extend(nested().flat_map(|inner| inner.annotation_imports.clone()));
+        if nested()
+            .chain(Some(self))
+            .any(DerivedEntity::uses_android_keep)
+        {
+            annotations.insert(String::from("androidx.annotation.Keep"));
+        }
+        let fns = self.fns.iter().chain(nested().flat_map(|inner| &inner.fns));
+        let result = format!(
+            "{}{}{}{}{} {} {{\n{}\n}}\n",
+            self.java_package_declaration(),
+            java_imports(fns, &annotations),
+            header,
+            if self.android_keep { "@Keep\n" } else { "" },
+            class_qualifier,
+            self.name,
+            converted_methods
+        );
+
+        Ok(result)
+    }
+
+    /// Generates the java class of an inner entity, indented to be nested
+    /// into the class body of the outer entity.
+    ///
+    /// The native library is loaded by the outer class.
+    fn export_java_inner_class_syntax(&self) -> Result<String, ConversionError> {
+        self.validate()
+            .map_err(|errors| ConversionError::InvalidEntity {
+                entity: self.name.clone(),
+                errors: errors.join(", "),
+            })?;
+        let body = self
+            .java_class_body(None)?
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("\t{}", line)
+                }
+            })
+            .join("\n");
+        Ok(format!(
+            "\n{}\tpublic static {}class {} {{\n{}\n\n\t}}\n",
+            if self.android_keep { "\t@Keep\n" } else { "" },
+            if self.utility_class { "final " } else { "" },
+            self.name,
+            body
+        ))
+    }
+
+    /// Returns whether the class or any of its methods are annotated with
+    /// the androidx `@Keep`.
+    fn uses_android_keep(&self) -> bool {
+        self.android_keep || self.fns.iter().any(|func| func.android_keep)
+    }
+
+    /// Generates the members of the java class, which loads the native
+    /// library in a static block if a library name is given.
+    fn java_class_body(&self, lib_name: Option<&str>) -> Result<String, ConversionError> {
+        let mut converted_methods = String::new();
+        if !self.constants.is_empty() {
+            converted_methods.push('\n');
+        }
+        for constant in &self.constants {
+            let java_type =
+                constant
+                    .java_type()
+                    .ok_or_else(|| ConversionError::UnsupportedConstType {
+                        name: constant.name.clone(),
+                        ty: constant.ty.clone(),
+                    })?;
+            converted_methods.push_str(&format!(
+                "\tpublic static final {} {} = {};\n",
+                java_type,
+                constant.name,
+                constant.java_literal()
+            ));
+        }
+        if let Some(call) = lib_name.and_then(|lib_name| self.load_library_call(lib_name)) {
+            converted_methods.push_str(&format!("\n\tstatic {{\n\t\t{};\n\t}}\n", call));
+        }
+        if self.utility_class {
+            converted_methods.push_str(&format!("\n\tprivate {}() {{\n\t}}\n", self.name));
+        }
+
+        for func in &self.fns {
+            func.check_callbacks()?;
+            let (args, arg_names) = java_args(func);
+
+            let static_qualifier = if func.is_static() { " static" } else { "" };
+            let synchronized_qualifier = if self.thread_safe {
+                " synchronized"
+            } else {
+                ""
+            };
+            let keep_annotation = if func.android_keep && !self.android_keep {
+                "\t@Keep\n"
+            } else {
+                ""
+            };
+            if func.tuple_return_types().is_some() {
+                converted_methods.push_str(&java_tuple_methods(
+                    func,
+                    static_qualifier,
+                    synchronized_qualifier,
+                    keep_annotation,
+                    self.java_check_return_value_annotation(func),
+                    &args,
+                    &arg_names,
+                )?);
+                continue;
+            }
+            let java_type = rust_to_java_return_type(func)?;
+            let return_type = format!(
+                "{}{}{}",
+                self.java_null_annotation(func, &java_type),
+                java_return_type_comment(func),
+                java_type
+            );
+            let modifiers = format!(
+                "{}{}{} native",
+                func.visibility.keyword(),
+                static_qualifier,
+                synchronized_qualifier
+            );
+            let result = format!(
+                "\n{}{}{}{}{}\t{} {} {}({});\n",
+                java_doc(func),
+                java_availability_comment(func),
+                keep_annotation,
+                self.java_check_return_value_annotation(func),
+                java_deprecated_annotation(func),
+                modifiers.trim_start(),
+                return_type,
+                func.java_name(),
+                args.iter().join(", ")
+            );
+            converted_methods.push_str(&result);
+        }
+        for body in &self.extra_java_bodies {
+            converted_methods.push('\n');
+            converted_methods.push_str(body.trim_end_matches('\n'));
+            converted_methods.push('\n');
+        }
+        Ok(converted_methods)
+    }
+
+    /// Generates the java class which wraps the native handle of a trait
+    /// object, with a method delegating to the native function for each
+    /// method of the trait.
+    ///
+    /// `destroy()` frees the trait object, after which the object must not
+    /// be used anymore.
+    fn export_java_trait_object_syntax(&self, lib_name: &str) -> Result<String, ConversionError> {
+        let package = self.java_package_declaration();
+        let mut delegates = String::new();
+        let mut natives = String::new();
+        for func in &self.fns {
+            func.check_callbacks()?;
+            let (args, arg_names) = java_args(func);
+            let return_type = format!(
+                "{}{}",
+                java_return_type_comment(func),
+                rust_to_java_return_type(func)?
+            );
+            let call = format!(
+                "{}({})",
+                func.java_name(),
+                std::iter::once(String::from("nativeHandle"))
+                    .chain(arg_names)
+                    .join(", ")
+            );
+            let body = if func.return_type.is_some() {
+                format!("return {}", call)
+            } else {
+                call
+            };
+            delegates.push_str(&format!(
+                "\n\tpublic {} {}({}) {{\n\t\t{};\n\t}}\n",
+                return_type,
+                func.java_name(),
+                args.join(", "),
+                body
+            ));
+            natives.push_str(&format!(
+                "\n\tprivate static native {} {}({});\n",
+                return_type,
+                func.java_name(),
+                std::iter::once(String::from("long nativeHandle"))
+                    .chain(args)
+                    .join(", ")
+            ));
+        }
+        let load_library = match self.load_library_call(lib_name) {
+            Some(call) => format!("\tstatic {{\n\t\t{};\n\t}}\n\n", call),
+            None => String::new(),
+        };
+        Ok(format!(
+            "{}public class {name} {{\n\n{}\
+             \tprivate long nativeHandle;\n\n\tpublic {name}(long nativeHandle) {{\n\
+             \t\tthis.nativeHandle = nativeHandle;\n\t}}\n{}\n\tpublic void destroy() {{\n\
+             \t\tif (nativeHandle != 0) {{\n\t\t\tdestroy(nativeHandle);\n\
+             \t\t\tnativeHandle = 0;\n\t\t}}\n\t}}\n{}\n\
+             \tprivate static native void destroy(long nativeHandle);\n\n}}\n",
+            package,
+            load_library,
+            delegates,
+            natives,
+            name = self.java_class_name()
+        ))
+    }
+
+    /// Generates a class for each platform of `#[roast(cfg_java = "...")]`,
+    /// like `FooAndroid`, which only contains the methods available there.
+    ///
+    /// The methods delegate to the full class, where instance methods take
+    /// the instance as their first argument. `isAvailable()` checks if the
+    /// JVM actually runs on the platform. Returns the class names together
+    /// with their source.
+    pub fn export_java_platform_syntax(&self) -> Result<Vec<(String, String)>, ConversionError> {
+        let platforms = self
+            .fns
+            .iter()
+            .filter_map(|f| f.cfg_java.as_deref())
+            .sorted()
+            .dedup();
+
+        let mut classes = vec![];
+        for platform in platforms {
+            let class_name = format!("{}{}", self.name, platform.to_pascal_case());
+            let fns = self
+                .fns
+                .iter()
+                .filter(|f| f.cfg_java.as_deref() == Some(platform));
+            let mut converted_methods = format!(
+                "\n\tprivate {}() {{\n\t}}\n\n\tpublic static boolean isAvailable() {{\n\
+                 \t\treturn {};\n\t}}\n",
+                class_name,
+                java_platform_check(platform)
+            );
+            for func in fns.clone() {
+                let (mut args, arg_names) = java_args(func);
+                let return_type = match &func.return_type {
+                    Some(_) if func.tuple_return_types().is_some() => match func.returns_named {
+                        Some(_) => format!("{}.{}", self.name, java_tuple_class(func)?),
+                        None => java_tuple_class(func)?,
+                    },
+                    _ => format!(
+                        "{}{}",
+                        java_return_type_comment(func),
+                        rust_to_java_return_type(func)?
+                    ),
+                };
+                let target = if func.is_static() {
+                    self.name.clone()
+                } else {
+                    let instance = self.name.to_camel_case();
+                    args.insert(0, format!("{} {}", self.name, instance));
+                    instance
+                };
+                let call = format!("{}.{}({})", target, func.java_name(), arg_names.join(", "));
+                let body = if func.return_type.is_some() {
+                    format!("return {}", call)
+                } else {
+                    call
+                };
+                converted_methods.push_str(&format!(
+                    "\n\tpublic static {} {}({}) {{\n\t\t{};\n\t}}\n",
+                    return_type,
+                    func.java_name(),
+                    args.join(", "),
+                    body
+                ));
+            }
+            classes.push((
+                class_name.clone(),
+                format!(
+                    "{}{}public final class {} {{\n{}\n}}\n",
+                    self.java_package_declaration(),
+                    java_imports(fns, &BTreeSet::new()),
+                    class_name,
+                    converted_methods
+                ),
+            ));
+        }
+        Ok(classes)
+    }
+
+    /// Generates the `NativeLibLoader` java class used by
+    /// `LibLoader::Resource`, in the package of the entity so the static
+    /// initializer finds it without an import.
+    ///
+    /// If the entity loads the library differently, None is returned.
+    pub fn export_java_native_lib_loader_syntax(&self) -> Option<String> {
+        (self.lib_loader == LibLoader::Resource).then(|| {
+            format!(
+                "{}{}",
+                self.java_package_declaration(),
+                JAVA_NATIVE_LIB_LOADER
+            )
+        })
+    }
+
+    /// Generates the generic `Tuple2` java class, which holds the values of
+    /// functions returning a tuple without `returns_named`.
+    ///
+    /// If no function needs it, None is returned.
+    pub fn export_java_tuple_syntax(&self) -> Option<String> {
+        self.fns
+            .iter()
+            .any(|f| f.tuple_return_types().is_some() && f.returns_named.is_none())
+            .then(|| {
+                format!(
+                    "{}public final class Tuple2<A, B> {{\n\n\tpublic final A first;\n\
+                     \tpublic final B second;\n\n\tpublic Tuple2(A first, B second) {{\n\
+                     \t\tthis.first = first;\n\t\tthis.second = second;\n\t}}\n}}\n",
+                    self.java_package_declaration()
+                )
+            })
+    }
+
+    /// Generates a JUnit 5 test class with a smoke test per method, which
+    /// calls the method with default arguments like `0` or `""`.
+    ///
+    /// Return values are checked with `assertNotNull`, except for nullable
+    /// ones. The tests are only meant as a starting point for a test suite,
+    /// since default arguments may well be invalid input for a method.
+    pub fn export_java_test_syntax(&self) -> Result<String, ConversionError> {
+        let mut tests = String::new();
+        for func in &self.fns {
+            let mut args = vec![];
+            for arg in &func.args {
+                match arg {
+                    DerivedFnArg::Captured { ty, .. } => {
+                        let mapping = func
+                            .type_mapping(ty)
+                            .ok_or_else(|| func.unsupported_arg_type(ty))?;
+                        args.push(java_default_value(mapping.java_type));
+                    }
+                    DerivedFnArg::Callback { .. } => args.push("null"),
+                    DerivedFnArg::LossyString { .. } => args.push("\"\""),
+                    DerivedFnArg::LossyFloat { .. } => args.push("0.0"),
+                    DerivedFnArg::NativeHandle { .. } => args.push("0L"),
+                    DerivedFnArg::IntEnum { .. } => args.push("0"),
+                    _ => (),
+                }
+            }
+            let receiver = if func.is_static() {
+                self.name.clone()
+            } else {
+                format!("new {}()", self.name)
+            };
+            let call = format!("{}.{}({})", receiver, func.java_name(), args.join(", "));
+            let nullable = func.option_return_type().is_some() && !func.use_optional;
+            let statement = if func.return_type.is_none() || nullable {
+                format!("{};", call)
+            } else {
+                format!("assertNotNull({});", call)
+            };
+            tests.push_str(&format!(
+                "\n\t@Test\n\tpublic void {}() {{\n\t\t{}\n\t}}\n",
+                func.java_name(),
+                statement
+            ));
+        }
+        Ok(format!(
+            "{}import org.junit.jupiter.api.Test;\n\n\
+             import static org.junit.jupiter.api.Assertions.assertNotNull;\n\n\
+             public class {}Test {{\n{}\n}}\n",
+            self.java_package_declaration(),
+            self.name,
+            tests
+        ))
+    }
+
+    /// Generates a C header which declares the JNI functions of the derived
+    /// entity, so they can be called from C and C++ as well.
+    pub fn export_header_syntax(&self) -> String {
+        let guard = format!("_Included_{}", self.jni_class_name());
+        let mut declarations = String::new();
+        for func in &self.fns {
+            let return_type = match rust_to_jni_return_type(func) {
+                Ok(Some(t)) => jni_type_to_c(&t),
+                Ok(None) => String::from("void"),
+                Err(e) => panic!("{}", e),
+            };
+            let mut args = vec![String::from("JNIEnv *env")];
+            if func.is_static() {
+                args.push(String::from("jclass clazz"));
+            } else {
+                args.push(String::from("jobject obj"));
+            }
+            for arg in &func.args {
+                let ty = match arg {
+                    DerivedFnArg::Captured { ty, .. } => jni_type_to_c(
+                        func.type_mapping(ty)
+                            .map(|m| m.jni_type)
+                            .expect("Could not convert rust to jni type"),
+                    ),
+                    DerivedFnArg::Callback { .. } => String::from("jobject"),
+                    DerivedFnArg::LossyString { .. } => String::from("jstring"),
+                    DerivedFnArg::LossyFloat { .. } => String::from("jdouble"),
+                    DerivedFnArg::NativeHandle { .. } => String::from("jlong"),
+                    DerivedFnArg::IntEnum { .. } => String::from("jint"),
+                    _ => continue,
+                };
+                args.push(format!("{} {}", ty, arg.name().unwrap()));
+            }
+            declarations.push_str(&format!(
+                "\nJNIEXPORT {} JNICALL Java_{}_{}({});\n",
+                return_type,
+                self.jni_class_name(),
+                func.jni_symbol_name(),
+                args.join(", ")
+            ));
+        }
+
+        format!(
+            "/* Generated by roast, do not edit */\n#ifndef {guard}\n#define {guard}\n\
+             #include <jni.h>\n\n#ifdef __cplusplus\nextern \"C\" {{\n#endif\n{}\n\
+             #ifdef __cplusplus\n}}\n#endif\n#endif\n",
+            declarations,
+            guard = guard
+        )
+    }
+
+    /// Generates the kotlin multiplatform `expect` declaration of the derived
+    /// entity, which goes into the `commonMain` source set.
+    pub fn export_kotlin_expect_syntax(&self) -> Result<String, ConversionError> {
+        let mut converted_methods = String::new();
+        for func in &self.fns {
+            converted_methods.push_str(&format!("\n\t{}\n", kotlin_fn_signature(func)?));
+        }
+
+        Ok(format!(
+            "{}expect object {} {{\n{}\n}}\n",
+            self.kotlin_package_declaration(),
+            self.name,
+            converted_methods
+        ))
+    }
+
+    /// Generates the kotlin multiplatform `actual` implementation of the
+    /// derived entity, which goes into the `jvmMain` source set.
+    ///
+    /// Static functions are annotated with `@JvmStatic` so they end up with
+    /// the same JNI symbols as their java counterparts.
+    pub fn export_kotlin_actual_syntax(&self, lib_name: &str) -> Result<String, ConversionError> {
+        let mut converted_methods = String::new();
+        if let Some(call) = self.load_library_call(lib_name) {
+            converted_methods.push_str(&format!("\n\tinit {{\n\t\t{}\n\t}}\n", call));
+        }
+
+        for func in &self.fns {
+            // `actual` is a modifier, so it goes behind any `@JvmStatic` annotation.
+            let signature = func
+                .kotlin_signature()?
+                .replacen("external ", "actual external ", 1);
+            converted_methods.push_str(&format!("\n\t{}\n", signature));
+        }
+
+        Ok(format!(
+            "{}actual object {} {{\n{}\n}}\n",
+            self.kotlin_package_declaration(),
+            self.name,
+            converted_methods
+        ))
+    }
+
+    /// Returns the static functions which can be called from swift, which
+    /// are the ones with only `i32`, `i64`, `bool` and `String` values.
+    fn swift_fns(&self) -> impl Iterator<Item = &DerivedFn> {
+        self.fns.iter().filter(|func| {
+            let args_supported = func.args.iter().all(|arg| match arg {
+                DerivedFnArg::Captured { ty, .. } => rust_to_swift_type(ty).is_some(),
+                _ => false,
+            });
+            let return_supported = match &func.return_type {
+                Some(t) => rust_to_swift_type(t).is_some(),
+                None => true,
+            };
+            // these change the value returned by the rust function
+            let converted = func.error_return
+                || func.collect
+                || func.json
+                || func.int_enum.is_some()
+                || func.trait_object.is_some();
+            func.is_static() && args_supported && return_supported && !converted
+        })
+    }
+
+    /// Generates a swift `extension` of the derived entity, where each static
+    /// function delegates to its rust counterpart through `@_silgen_name`.
+    ///
+    /// This is experimental. Only functions with `i32`, `i64`, `bool` and
+    /// `String` values are exported, everything else is skipped. Strings
+    /// cross the boundary as C strings, where the ones returned from rust
+    /// are freed through `roast_swift_<Name>_free_string`.
+    pub fn export_swift_syntax(&self) -> String {
+        let mut declarations = String::new();
+        let mut methods = String::new();
+        for func in self.swift_fns() {
+            let symbol = format!("roast_swift_{}_{}", self.name, func.name);
+            let args = func
+                .args
+                .iter()
+                .filter_map(|arg| match arg {
+                    DerivedFnArg::Captured { name, ty } => {
+                        Some((name.to_camel_case(), rust_to_swift_type(ty).unwrap()))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let return_type = func
+                .return_type
+                .as_deref()
+                .map(|t| rust_to_swift_type(t).unwrap());
+
+            declarations.push_str(&format!(
+                "\n@_silgen_name(\"{}\")\nprivate func {}({}){}\n",
+                symbol,
+                symbol,
+                args.iter()
+                    .map(|(name, (_, c_type))| format!("_ {}: {}", name, c_type))
+                    .join(", "),
+                return_type
+                    .map(|(_, c_type)| format!(
+                        " -> {}",
+                        c_type.replace("UnsafePointer", "UnsafeMutablePointer")
+                    ))
+                    .unwrap_or_default()
+            ));
+
+            // string arguments are only valid within `withCString`
+            let mut call = format!(
+                "{}({})",
+                symbol,
+                args.iter().map(|(name, _)| name).join(", ")
+            );
+            for (name, _) in args.iter().rev().filter(|(_, (ty, _))| *ty == "String") {
+                call = format!(
+                    "{name}.withCString {{ {name} in\n{}\n}}",
+                    call.lines().map(|l| format!("    {}", l)).join("\n"),
+                    name = name
+                );
+            }
+            let body = match return_type {
+                Some(("String", _)) => format!(
+                    "let result = {}\ndefer {{ roast_swift_{}_free_string(result) }}\n\
+                     return String(cString: result)",
+                    call, self.name
+                ),
+                Some(_) => format!("return {}", call),
+                None => call,
+            };
+            methods.push_str(&format!(
+                "\n    public static func {}({}){} {{\n{}\n    }}\n",
+                func.java_name(),
+                args.iter()
+                    .map(|(name, (ty, _))| format!("{}: {}", name, ty))
+                    .join(", "),
+                return_type
+                    .map(|(ty, _)| format!(" -> {}", ty))
+                    .unwrap_or_default(),
+                body.lines().map(|l| format!("        {}", l)).join("\n")
+            ));
+        }
+        if self.swift_returns_string() {
+            declarations.push_str(&format!(
+                "\n// Returned strings are allocated by rust and have to be freed by\n\
+                 // roast::swift::free_cstring, which this function calls.\n\
+                 @_silgen_name(\"roast_swift_{name}_free_string\")\n\
+                 private func roast_swift_{name}_free_string(\
+                 _ string: UnsafeMutablePointer<CChar>)\n",
+                name = self.name
+            ));
+        }
+
+        format!(
+            "// Generated by roast, do not edit. Swift support is experimental.\n{}\n\
+             public enum {name} {{}}\n\nextension {name} {{\n{}\n}}\n",
+            declarations,
+            methods,
+            name = self.name
+        )
+    }
+
+    /// Returns true if any function exported to swift returns a `String`.
+    fn swift_returns_string(&self) -> bool {
+        self.swift_fns()
+            .any(|func| func.return_type.as_deref() == Some("String"))
+    }
+
+    /// Generates the C functions the swift `extension` of
+    /// `export_swift_syntax` calls into, which are only compiled for apple
+    /// targets.
+    pub fn export_swift_ffi_tokens(&self) -> TokenStream {
+        let struct_name = Ident::new(&self.name, Span::call_site());
+        let mut stream = TokenStream::new();
+        for func in self.swift_fns() {
+            let symbol = Ident::new(
+                &format!("roast_swift_{}_{}", self.name, func.name),
+                Span::call_site(),
+            );
+            let mut args = vec![];
+            let mut inner_args = vec![];
+            for arg in &func.args {
+                if let DerivedFnArg::Captured { name, ty } = arg {
+                    let name = Ident::new(name, Span::call_site());
+                    if ty == "String" {
+                        args.push(quote! { #name: *const std::os::raw::c_char });
+                        inner_args
+                            .push(quote! { unsafe { roast::swift::convert_arg_cstring(#name) } });
+                    } else {
+                        let ty = parse_str::<syn::Type>(ty).expect("Could not parse swift type");
+                        args.push(quote! { #name: #ty });
+                        inner_args.push(quote! { #name });
+                    }
+                }
+            }
+            let call_path = func.call_path(&struct_name);
+            let call = quote! { #call_path(#(#inner_args),*) };
+            // the C string arguments are dereferenced, which a safe caller can't vouch for
+            let unsafety = func
+                .args
+                .iter()
+                .any(|arg| matches!(arg, DerivedFnArg::Captured { ty, .. } if ty == "String"))
+                .then(|| quote! { unsafe });
+            let allow_deprecated = func
+                .deprecated
+                .as_ref()
+                .map(|_| quote! { #[allow(deprecated)] });
+            let attrs = quote! {
+                #[cfg(target_vendor = "apple")]
+                #[no_mangle]
+                #allow_deprecated
+            };
+            stream.extend(match func.return_type.as_deref() {
+                Some("String") => quote! {
+                    #attrs
+                    #unsafety extern "C" fn #symbol(#(#args),*) -> *mut std::os::raw::c_char {
+                        roast::swift::convert_retval_cstring(#call)
+                    }
+                },
+                Some(t) => {
+                    let ret = parse_str::<syn::Type>(t).expect("Could not parse swift type");
+                    quote! {
+                        #attrs
+                        #unsafety extern "C" fn #symbol(#(#args),*) -> #ret {
+                            #call
+                        }
+                    }
+                }
+                None => quote! {
+                    #attrs
+                    #unsafety extern "C" fn #symbol(#(#args),*) {
+                        #call
+                    }
+                },
+            });
+        }
+        if self.swift_returns_string() {
+            let free = Ident::new(
+                &format!("roast_swift_{}_free_string", self.name),
+                Span::call_site(),
+            );
+            stream.extend(quote! {
+                #[cfg(target_vendor = "apple")]
+                #[no_mangle]
+                unsafe extern "C" fn #free(string: *mut std::os::raw::c_char) {
+                    unsafe { roast::swift::free_cstring(string) }
+                }
+            });
+        }
+        stream
+    }
+}
+
+/// Maps a rust type to the swift type of the `export_swift_syntax` methods,
+/// together with the type it crosses the C boundary as.
+fn rust_to_swift_type(ty: &str) -> Option<(&'static str, &'static str)> {
+    let (rust_type, swift_type, c_type) = match rust_to_type_mapping(ty)?.kotlin_type {
+        "Int" => ("i32", "Int32", "Int32"),
+        "Long" => ("i64", "Int64", "Int64"),
+        "Boolean" => ("bool", "Bool", "Bool"),
+        "String" => ("String", "String", "UnsafePointer<CChar>"),
+        _ => return None,
+    };
+    // types like u64 share the kotlin type, but the swift wrappers pass the
+    // values on without the conversion they need
+    (ty == rust_type).then_some((swift_type, c_type))
+}
+
+/// Takes a derived function and returns its return type as a java string.
+///
+/// If the return type cannot be converted properly, a `ConversionError` is raised.
+fn rust_to_java_return_type(func: &DerivedFn) -> Result<String, ConversionError> {
+    let ret = &func.return_type;
+
+    Ok(match ret {
+        None => "void".into(),
+        Some(t) if func.option_return_type().is_some() => {
+            match func.option_return_type().and_then(rust_to_type_mapping) {
+                Some(m) if func.use_optional => {
+                    format!("Optional<{}>", java_boxed_type(m.java_type))
+                }
+                Some(m) => java_boxed_type(m.java_type).into(),
+                None => return Err(func.unsupported_return_type(t)),
+            }
+        }
+        Some(t) => match func.return_type_mapping(t).map(|m| m.java_type) {
+            Some(v) => v,
+            None => return Err(func.unsupported_return_type(t)),
+        }
+        .into(),
+    })
+}
+
+/// Generates the java methods of a function returning a tuple.
+///
+/// The native method returns an `Object[]` and is wrapped by a public method
+/// which unpacks it into a `Tuple2`, or with `returns_named` into a nested
+/// result class like `DivResult`. Only pairs are supported for now.
+///
+/// `@Keep` goes on the native method, `@CheckReturnValue` on the wrapper.
+fn java_tuple_methods(
+    func: &DerivedFn,
+    static_qualifier: &str,
+    synchronized_qualifier: &str,
+    keep_annotation: &str,
+    check_annotation: &str,
+    args: &[String],
+    arg_names: &[String],
+) -> Result<String, ConversionError> {
+    let java_types = java_tuple_types(func)?;
+    let boxed = java_types
+        .iter()
+        .map(|t| java_boxed_type(t))
+        .collect::<Vec<_>>();
+
+    let class = java_tuple_class(func)?;
+    let (constructor, nested) = match &func.returns_named {
+        Some((first, second)) => {
+            let nested = format!(
+                "\n\tpublic static final class {class} {{\n\t\tpublic final {} {first};\n\
+                 \t\tpublic final {} {second};\n\n\t\t{class}({} {first}, {} {second}) {{\n\
+                 \t\t\tthis.{first} = {first};\n\t\t\tthis.{second} = {second};\n\t\t}}\n\t}}\n",
+                java_types[0],
+                java_types[1],
+                java_types[0],
+                java_types[1],
+                class = class,
+                first = first,
+                second = second
+            );
+            (class.clone(), nested)
+        }
+        None => (String::from("Tuple2<>"), String::new()),
+    };
+
+    Ok(format!(
+        "\n{doc}{available}{check}{deprecated}\t{modifiers} {class} {name}({args}) {{\n\
+         \t\tObject[] result = {native}({arg_names});\n\
+         \t\treturn new {constructor}(({}) result[0], ({}) result[1]);\n\t}}\n\n\
+         {keep}\tprivate{static_qualifier}{synchronized_qualifier} native Object[] {native}({args});\n{nested}",
+        boxed[0],
+        boxed[1],
+        doc = java_doc(func),
+        available = java_availability_comment(func),
+        deprecated = java_deprecated_annotation(func),
+        modifiers = format!("{}{}", func.visibility.keyword(), static_qualifier).trim_start(),
+        static_qualifier = static_qualifier,
+        synchronized_qualifier = synchronized_qualifier,
+        keep = keep_annotation,
+        check = check_annotation,
+        class = class,
+        name = func.java_name(),
+        args = args.join(", "),
+        native = func.jni_method_name(),
+        arg_names = arg_names.join(", "),
+        constructor = constructor,
+        nested = nested
+    ))
+}
+
+/// Returns the java types of the values of a tuple return type.
+///
+/// Only pairs of supported types can be converted, otherwise a
+/// `ConversionError` is raised.
+fn java_tuple_types(func: &DerivedFn) -> Result<Vec<&'static str>, ConversionError> {
+    let unsupported =
+        || func.unsupported_return_type(func.return_type.as_deref().unwrap_or_default());
+    let types = func.tuple_return_types().unwrap_or_default();
+    if types.len() != 2 {
+        return Err(unsupported());
+    }
+    let mut java_types = vec![];
+    for ty in &types {
+        java_types.push(rust_to_type_mapping(ty).ok_or_else(unsupported)?.java_type);
+    }
+    Ok(java_types)
+}
+
+/// Returns the java class a tuple return value is unpacked into, like
+/// `DivResult` with `returns_named` or `Tuple2<Integer, String>` otherwise.
+fn java_tuple_class(func: &DerivedFn) -> Result<String, ConversionError> {
+    let java_types = java_tuple_types(func)?;
+    Ok(match &func.returns_named {
+        Some(_) => format!("{}Result", func.name.to_pascal_case()),
+        None => format!(
+            "Tuple2<{}, {}>",
+            java_boxed_type(java_types[0]),
+            java_boxed_type(java_types[1])
+        ),
+    })
+}
+
+/// Builds the java arguments of a function, like `int a`, together with
+/// their names to pass them on.
+fn java_args(func: &DerivedFn) -> (Vec<String>, Vec<String>) {
+    let mut args = vec![];
+    let mut arg_names = vec![];
+    for arg in &func.args {
+        if let DerivedFnArg::Captured { name: _name, ty } = arg {
+            args.push(format!(
+                "{}{} {}",
+                java_type_comment(ty),
+                func.type_mapping(ty).map(|m| m.java_type).unwrap(),
+                arg.java_name().unwrap()
+            ));
+        }
+        if let DerivedFnArg::Callback { java_interface, .. } = arg {
+            args.push(format!("{} {}", java_interface, arg.java_name().unwrap()));
+        }
+        if let DerivedFnArg::LossyString { .. } = arg {
+            args.push(format!("String {}", arg.java_name().unwrap()));
+        }
+        if let DerivedFnArg::LossyFloat { .. } = arg {
+            args.push(format!("double {}", arg.java_name().unwrap()));
+        }
+        if let DerivedFnArg::NativeHandle { .. } = arg {
+            args.push(format!("long {}", arg.java_name().unwrap()));
+        }
+        if let DerivedFnArg::IntEnum { ty, .. } = arg {
+            args.push(format!("/* {} */ int {}", ty, arg.java_name().unwrap()));
+        }
+        arg_names.extend(arg.java_name());
+    }
+    (args, arg_names)
+}
+
+/// Returns the java types of the arguments of a function, where types
+/// without a java mapping are kept as they are.
+fn java_arg_types(func: &DerivedFn) -> Vec<String> {
+    func.args
+        .iter()
+        .filter_map(|arg| match arg {
+            DerivedFnArg::Captured { ty, .. } => Some(
+                func.type_mapping(ty)
+                    .map(|m| m.java_type.to_string())
+                    .unwrap_or_else(|| ty.clone()),
+            ),
+            DerivedFnArg::Callback { java_interface, .. } => Some(java_interface.clone()),
+            DerivedFnArg::LossyString { .. } => Some(String::from("String")),
+            DerivedFnArg::LossyFloat { .. } => Some(String::from("double")),
+            DerivedFnArg::NativeHandle { .. } => Some(String::from("long")),
+            DerivedFnArg::IntEnum { .. } => Some(String::from("int")),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the `T` of an iterator return type like `impl Iterator<Item = T>`
+/// or `Box<dyn Iterator<Item = T>>`, ignoring any whitespace.
+fn iterator_item_type(return_type: &str) -> Option<String> {
+    let ty: String = return_type.chars().filter(|c| !c.is_whitespace()).collect();
+    let item = match ty.strip_prefix("implIterator<Item=") {
+        Some(rest) => rest.strip_suffix('>')?,
+        None => ty
+            .strip_prefix("Box<dynIterator<Item=")?
+            .strip_suffix(">>")?,
+    };
+    Some(item.into())
+}
+
+/// Checks if the name is reserved in java and can't be used as identifier.
+fn is_java_keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "abstract"
+            | "assert"
+            | "boolean"
+            | "break"
+            | "byte"
+            | "case"
+            | "catch"
+            | "char"
+            | "class"
+            | "const"
+            | "continue"
+            | "default"
+            | "do"
+            | "double"
+            | "else"
+            | "enum"
+            | "extends"
+            | "false"
+            | "final"
+            | "finally"
+            | "float"
+            | "for"
+            | "goto"
+            | "if"
+            | "implements"
+            | "import"
+            | "instanceof"
+            | "int"
+            | "interface"
+            | "long"
+            | "native"
+            | "new"
+            | "null"
+            | "package"
+            | "private"
+            | "protected"
+            | "public"
+            | "return"
+            | "short"
+            | "static"
+            | "strictfp"
+            | "super"
+            | "switch"
+            | "synchronized"
+            | "this"
+            | "throw"
+            | "throws"
+            | "transient"
+            | "true"
+            | "try"
+            | "void"
+            | "volatile"
+            | "while"
+    )
+}
+
+/// Returns true if the java type is a primitive or `void`, which can't be null.
+fn is_java_primitive(java_type: &str) -> bool {
+    matches!(
+        java_type,
+        "boolean" | "byte" | "char" | "short" | "int" | "long" | "float" | "double" | "void"
+    )
+}
+
+/// Returns the javadoc of a function, or an empty string.
+///
+/// Functions with unsigned `u64` arguments or return value, which java only
+/// knows as signed `long`, explain how to work with them. Deprecated
+/// functions with a note get a `@deprecated` tag.
+fn java_doc(func: &DerivedFn) -> String {
+    let unsigned = func
+        .args
+        .iter()
+        .any(|arg| matches!(arg, DerivedFnArg::Captured { ty, .. } if ty == "u64"))
+        || (!func.json && func.return_type.as_deref() == Some("u64"));
+    let mut lines = vec![];
+    if unsigned {
+        lines.extend([
+            "The long values marked as unsigned hold the bits of a u64, so values",
+            "above Long.MAX_VALUE appear negative. Use Long.toUnsignedString and",
+            "Long.parseUnsignedLong to work with them.",
+        ]);
+    }
+    let note = func.deprecated.as_deref().unwrap_or_default();
+    let tag = format!("@deprecated {}", note.split_whitespace().join(" "));
+    if !note.trim().is_empty() {
+        if !lines.is_empty() {
+            lines.push("");
+        }
+        lines.push(&tag);
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    let body = lines
+        .iter()
+        .map(|line| format!("\t *{}{}\n", if line.is_empty() { "" } else { " " }, line))
+        .join("");
+    format!("\t/**\n{}\t */\n", body.replace("*/", "*&#47;"))
+}
+
+/// Returns the `@Deprecated` line of a deprecated function, or an empty
+/// string.
+fn java_deprecated_annotation(func: &DerivedFn) -> &'static str {
+    if func.deprecated.is_some() {
+        "\t@Deprecated\n"
+    } else {
+        ""
+    }
+}
+
+/// Returns the `// @available android` comment line of a function which is
+/// only available on some platform, or an empty string.
+fn java_availability_comment(func: &DerivedFn) -> String {
+    func.cfg_java
+        .as_ref()
+        .map(|p| format!("\t// @available {}\n", p))
+        .unwrap_or_default()
+}
+
+/// Returns the java imports needed by the given functions, together with the
+/// given annotations.
+fn java_imports<'a>(
+    fns: impl Iterator<Item = &'a DerivedFn>,
+    annotations: &BTreeSet<String>,
+) -> String {
+    let mut imports: Vec<&str> = annotations.iter().map(String::as_str).collect();
+    for func in fns {
+        if func.use_optional && func.option_return_type().is_some() {
+            imports.push("java.util.Optional");
+        }
+        let mut types = func
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                DerivedFnArg::Captured { ty, .. } => Some(ty.as_str()),
+                _ => None,
+            })
+            .chain(func.return_type.as_deref());
+        if func.java_collection && types.any(|ty| rust_to_collection_mapping(ty).is_some()) {
+            imports.push("java.util.List");
+        }
+    }
+    if imports.is_empty() {
+        return String::new();
+    }
+    let mut result: String = imports
+        .into_iter()
+        .sorted()
+        .dedup()
+        .map(|i| format!("import {};\n", i))
+        .collect();
+    result.push('\n');
+    result
+}
+
+/// Returns the java expression which checks at runtime if the JVM runs on
+/// the given platform.
+fn java_platform_check(platform: &str) -> String {
+    match platform {
+        "android" => {
+            String::from("System.getProperty(\"java.vendor\", \"\").contains(\"Android\")")
+        }
+        p => format!(
+            "System.getProperty(\"os.name\", \"\").toLowerCase().contains(\"{}\")",
+            if p == "macos" { "mac" } else { p }
+        ),
+    }
+}
+
+/// Returns a java expression of the given type which generated tests pass
+/// as argument, like `0` for `int` or `new byte[0]` for `byte[]`.
+fn java_default_value(java_type: &str) -> &'static str {
+    match java_type {
+        "boolean" => "false",
+        "byte" => "(byte) 0",
+        "short" => "(short) 0",
+        "char" => "(char) 0",
+        "int" => "0",
+        "long" => "0L",
+        "float" => "0f",
+        "double" => "0d",
+        "String" => "\"\"",
+        "byte[]" => "new byte[0]",
+        "float[]" => "new float[0]",
+        "boolean[]" => "new boolean[0]",
+        "int[]" => "new int[0]",
+        "List<Integer>" => "new java.util.ArrayList<>()",
+        _ => "null",
+    }
+}
+
+/// Returns the wrapper class of a java primitive type, like `Integer` for
+/// `int`. Other types are returned as they are.
+fn java_boxed_type(java_type: &str) -> &str {
+    match java_type {
+        "byte" => "Byte",
+        "boolean" => "Boolean",
+        "short" => "Short",
+        "char" => "Character",
+        "int" => "Integer",
+        "long" => "Long",
+        "float" => "Float",
+        "double" => "Double",
+        t => t,
+    }
+}
+
+fn rust_to_jni_return_type(func: &DerivedFn) -> Result<Option<String>, ConversionError> {
+    let ret = &func.return_type;
+
+    Ok(match ret {
+        None => None,
+        Some(_) if func.tuple_return_types().is_some() => Some("roast::jobjectArray".into()),
+        Some(_) if func.option_return_type().is_some() => Some("roast::jobject".into()),
+        Some(t) => match func.return_type_mapping(t).map(|m| m.jni_type) {
+            Some(v) if v == "roast::JString" || v == "roast::JObject" => Some(v.to_lowercase()),
+            Some(v) => Some(v.into()),
+            None => return Err(func.unsupported_return_type(t)),
+        },
+    })
+}
+
+/// Returns a comment which documents the meaning of a java type, for rust
+/// types which map to a more general java type like `Timestamp` to `long`.
+fn java_type_comment(ty: &str) -> &'static str {
+    match ty {
+        "Timestamp" => "/* milliseconds since epoch */ ",
+        "u64" => "/* unsigned */ ",
+        _ => "",
+    }
+}
+
+/// Returns the comment which documents the meaning of the java return type
+/// of a function, see `java_type_comment`.
+fn java_return_type_comment(func: &DerivedFn) -> String {
+    match (&func.trait_object, &func.int_enum) {
+        (Some(java_class), _) => format!("/* {} handle */ ", java_class),
+        (None, Some(ty)) => format!("/* {} */ ", ty),
+        (None, None) => java_type_comment(func.return_type.as_deref().unwrap_or_default()).into(),
+    }
+}
+
+/// Returns the `Trait` of a `Box<dyn Trait>` type, ignoring any whitespace.
+fn boxed_trait_name(ty: &str) -> Option<String> {
+    let ty: String = ty.chars().filter(|c| !c.is_whitespace()).collect();
+    Some(ty.strip_prefix("Box<dyn")?.strip_suffix('>')?.into())
+}
+
+/// Returns the method call which borrows a converted argument, for rust
+/// types like `&[u8]` which are converted into their owned counterpart.
+fn borrow_converted_arg(ty: &str) -> &'static str {
+    match ty {
+        "&[u8]" => ".as_slice()",
+        "&str" => ".as_str()",
+        _ => "",
+    }
+}
+
+/// Returns the function in `roast::convert` which copies a mutably borrowed
+/// argument back into its java array after the call, like for `&mut [u8]`.
+fn write_back_arg_fn(ty: &str) -> Option<&'static str> {
+    match ty {
+        "&mut [u8]" => Some("convert_arg_jbytearray_write_back"),
+        _ => None,
+    }
+}
+
+/// Turns the JNI FFI type into its `<jni.h>` equivalent.
+fn jni_type_to_c(ty: &str) -> String {
+    ty.replace("roast::", "")
+        .replace("JString", "jstring")
+        .replace("JObject", "jobject")
+}
+
+/// Builds the kotlin signature of a function, like `fun foo(a: Int): Long`.
+fn kotlin_fn_signature(func: &DerivedFn) -> Result<String, ConversionError> {
+    let mut args = vec![];
+    for arg in &func.args {
+        match arg {
+            DerivedFnArg::Captured { ty, .. } => {
+                match func.type_mapping(ty).map(|m| m.kotlin_type) {
+                    Some(t) => args.push(format!("{}: {}", arg.java_name().unwrap(), t)),
+                    None => return Err(func.unsupported_arg_type(ty)),
+                }
+            }
+            DerivedFnArg::Callback { java_interface, .. } => {
+                return Err(func.unsupported_arg_type(java_interface))
+            }
+            DerivedFnArg::LossyString { .. } => {
+                args.push(format!("{}: String", arg.java_name().unwrap()))
+            }
+            DerivedFnArg::LossyFloat { .. } => {
+                args.push(format!("{}: Double", arg.java_name().unwrap()))
+            }
+            DerivedFnArg::NativeHandle { .. } => {
+                args.push(format!("{}: Long", arg.java_name().unwrap()))
+            }
+            DerivedFnArg::IntEnum { .. } => args.push(format!("{}: Int", arg.java_name().unwrap())),
+            _ => (),
+        }
+    }
+
+    let return_type = match &func.return_type {
+        None => String::new(),
+        Some(t) if func.option_return_type().is_some() => {
+            match func.option_return_type().and_then(rust_to_type_mapping) {
+                Some(m) if !func.use_optional => format!(": {}?", m.kotlin_type),
+                _ => return Err(func.unsupported_return_type(t)),
+            }
+        }
+        Some(t) => match func.return_type_mapping(t).map(|m| m.kotlin_type) {
+            Some(v) => format!(": {}", v),
+            None => return Err(func.unsupported_return_type(t)),
+        },
+    };
+
+    Ok(format!(
+        "fun {}({}){}",
+        func.java_name(),
+        args.iter().join(", "),
+        return_type
+    ))
+}
+
+/// Panics with a descriptive message if the name cannot be used as a java
+/// identifier, since it would otherwise produce broken java and JNI code.
+fn validate_identifier(kind: &str, name: &str) {
+    let first = match name.chars().next() {
+        Some(c) => c,
+        None => panic!("The {} name must not be empty", kind),
+    };
+    if first.is_ascii_digit() {
+        panic!("The {} name \"{}\" must not start with a digit", kind, name);
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == '_' || *c == '$'))
+    {
+        panic!(
+            "The {} name \"{}\" contains the invalid character {:?}",
+            kind, name, c
+        );
+    }
+}
+
+/// Returns the function in `roast::convert` which turns an argument of the
+/// fully qualified java interface into a rust closure, if there is one.
+fn callback_convert_fn(java_interface: &str) -> Option<&'static str> {
+    match java_interface {
+        "java.util.function.IntSupplier" => Some("convert_arg_jcallback_int_supplier"),
+        _ => None,
+    }
+}
+
+/// Describes how a rust type is represented on the java side and how it
+/// crosses the JNI boundary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeMapping {
+    /// The java type, like `int`.
+    pub java_type: &'static str,
+    /// The kotlin type, like `Int`.
+    pub kotlin_type: &'static str,
+    /// The JNI FFI type used in the wrapper functions, like `roast::jint`.
+    pub jni_type: &'static str,
+    /// The JNI type descriptor, like `I`.
+    pub jni_descriptor: &'static str,
+    /// The function in `roast::convert` which turns the JNI type into the
+    /// rust type, like `convert_arg_jint`.
+    pub convert_arg_fn: &'static str,
+}
+
+/// Looks up how the string representation of a rust type maps to java.
+///
+/// If None is returned, the type is not supported (yet).
+fn rust_to_type_mapping(ty: &str) -> Option<TypeMapping> {
+    let (java_type, kotlin_type, jni_type, jni_descriptor, convert_arg_fn) = match ty {
+        "i8" => ("byte", "Byte", "roast::jbyte", "B", "convert_arg_jbyte"),
+        "u8" => (
+            "boolean",
+            "Boolean",
+            "roast::jboolean",
+            "Z",
+            "convert_arg_jboolean",
+        ),
+        "i16" => ("short", "Short", "roast::jshort", "S", "convert_arg_jshort"),
+        "u16" => ("char", "Char", "roast::jchar", "C", "convert_arg_jchar"),
+        "i32" => ("int", "Int", "roast::jint", "I", "convert_arg_jint"),
+        "i64" => ("long", "Long", "roast::jlong", "J", "convert_arg_jlong"),
+        "Timestamp" => ("long", "Long", "roast::jlong", "J", "convert_arg_jlong"),
+        "u64" => (
+            "long",
+            "Long",
+            "roast::jlong",
+            "J",
+            "convert_arg_jlong_to_u64",
+        ),
+        "f32" => ("float", "Float", "roast::jfloat", "F", "convert_arg_jfloat"),
+        "f64" => (
+            "double",
+            "Double",
+            "roast::jdouble",
+            "D",
+            "convert_arg_jdouble",
+        ),
+        "bool" => (
+            "boolean",
+            "Boolean",
+            "roast::jboolean",
+            "Z",
+            "convert_arg_jboolean",
+        ),
+        "String" => (
+            "String",
+            "String",
+            "roast::JString",
+            "Ljava/lang/String;",
+            "convert_arg_jstring",
+        ),
+        "&str" => (
+            "String",
+            "String",
+            "roast::JString",
+            "Ljava/lang/String;",
+            "convert_arg_jstring",
+        ),
+        "PathBuf" => (
+            "String",
+            "String",
+            "roast::JString",
+            "Ljava/lang/String;",
+            "convert_arg_jpathstring",
+        ),
+        "Option<i32>" => (
+            "Integer",
+            "Int?",
+            "roast::JObject",
+            "Ljava/lang/Integer;",
+            "convert_arg_jint_nullable",
+        ),
+        "Option<i64>" => (
+            "Long",
+            "Long?",
+            "roast::JObject",
+            "Ljava/lang/Long;",
+            "convert_arg_jlong_nullable",
+        ),
+        "Option<f64>" => (
+            "Double",
+            "Double?",
+            "roast::JObject",
+            "Ljava/lang/Double;",
+            "convert_arg_jdouble_nullable",
+        ),
+        "Option<bool>" => (
+            "Boolean",
+            "Boolean?",
+            "roast::JObject",
+            "Ljava/lang/Boolean;",
+            "convert_arg_jboolean_nullable",
+        ),
+        "Option<String>" => (
+            "String",
+            "String?",
+            "roast::JString",
+            "Ljava/lang/String;",
+            "convert_arg_jstring_nullable",
+        ),
+        "Vec<u8>" => (
+            "byte[]",
+            "ByteArray",
+            "roast::jbyteArray",
+            "[B",
+            "convert_arg_jbytearray",
+        ),
+        "&[u8]" => (
+            "byte[]",
+            "ByteArray",
+            "roast::jbyteArray",
+            "[B",
+            "convert_arg_jbytearray",
+        ),
+        "&mut [u8]" => (
+            "byte[]",
+            "ByteArray",
+            "roast::jbyteArray",
+            "[B",
+            "convert_arg_jbytearray",
+        ),
+        "Vec<f32>" => (
+            "float[]",
+            "FloatArray",
+            "roast::jfloatArray",
+            "[F",
+            "convert_arg_jfloatarray",
+        ),
+        "Vec<bool>" => (
+            "boolean[]",
+            "BooleanArray",
+            "roast::jbooleanArray",
+            "[Z",
+            "convert_arg_jbooleanarray",
+        ),
+        "Vec<u32>" => (
+            "int[]",
+            "IntArray",
+            "roast::jintArray",
+            "[I",
+            "convert_arg_juintarray",
+        ),
+        "Vec<i32>" => (
+            "int[]",
+            "IntArray",
+            "roast::jintArray",
+            "[I",
+            "convert_arg_jintarray",
+        ),
+        _ => return None,
+    };
+    Some(TypeMapping {
+        java_type,
+        kotlin_type,
+        jni_type,
+        jni_descriptor,
+        convert_arg_fn,
+    })
+}
+
+/// Looks up how a rust collection type maps to a `java.util` collection
+/// with `#[roast(java_collection)]`.
+///
+/// If None is returned, the type has no collection variant and is mapped
+/// like any other type.
+fn rust_to_collection_mapping(ty: &str) -> Option<TypeMapping> {
+    let (java_type, kotlin_type, convert_arg_fn) = match ty {
+        "Vec<i32>" => ("List<Integer>", "List<Int>", "convert_arg_jlist_i32"),
+        _ => return None,
+    };
+    Some(TypeMapping {
+        java_type,
+        kotlin_type,
+        jni_type: "roast::JObject",
+        jni_descriptor: "Ljava/util/List;",
+        convert_arg_fn,
     })
 }
 
-#[cfg(test)]
-mod tests {
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn rust_to_java_type(ty: &str) -> Option<&'static str> {
+        rust_to_type_mapping(ty).map(|m| m.java_type)
+    }
+
+    fn rust_to_kotlin_type(ty: &str) -> Option<&'static str> {
+        rust_to_type_mapping(ty).map(|m| m.kotlin_type)
+    }
+
+    fn rust_to_jni_type(ty: &str) -> Option<&'static str> {
+        rust_to_type_mapping(ty).map(|m| m.jni_type)
+    }
+
+    fn rust_to_jni_descriptor(ty: &str) -> Option<&'static str> {
+        rust_to_type_mapping(ty).map(|m| m.jni_descriptor)
+    }
+
+    #[test]
+    fn rust_type_to_java_type() {
+        assert_eq!(Some("byte"), rust_to_java_type("i8"));
+        assert_eq!(Some("boolean"), rust_to_java_type("u8"));
+        assert_eq!(Some("short"), rust_to_java_type("i16"));
+        assert_eq!(Some("char"), rust_to_java_type("u16"));
+        assert_eq!(Some("int"), rust_to_java_type("i32"));
+        assert_eq!(Some("long"), rust_to_java_type("i64"));
+        assert_eq!(Some("long"), rust_to_java_type("Timestamp"));
+        assert_eq!(Some("long"), rust_to_java_type("u64"));
+        assert_eq!(Some("float"), rust_to_java_type("f32"));
+        assert_eq!(Some("double"), rust_to_java_type("f64"));
+        assert_eq!(Some("boolean"), rust_to_java_type("bool"));
+        assert_eq!(Some("String"), rust_to_java_type("String"));
+        assert_eq!(Some("String"), rust_to_java_type("&str"));
+        assert_eq!(Some("String"), rust_to_java_type("PathBuf"));
+        assert_eq!(Some("byte[]"), rust_to_java_type("Vec<u8>"));
+        assert_eq!(Some("byte[]"), rust_to_java_type("&[u8]"));
+        assert_eq!(Some("float[]"), rust_to_java_type("Vec<f32>"));
+        assert_eq!(Some("boolean[]"), rust_to_java_type("Vec<bool>"));
+        assert_eq!(Some("int[]"), rust_to_java_type("Vec<u32>"));
+        assert_eq!(Some("int[]"), rust_to_java_type("Vec<i32>"));
+    }
+
+    #[test]
+    fn rust_type_to_jni_type() {
+        assert_eq!(Some("roast::jbyte"), rust_to_jni_type("i8"));
+        assert_eq!(Some("roast::jboolean"), rust_to_jni_type("u8"));
+        assert_eq!(Some("roast::jshort"), rust_to_jni_type("i16"));
+        assert_eq!(Some("roast::jchar"), rust_to_jni_type("u16"));
+        assert_eq!(Some("roast::jint"), rust_to_jni_type("i32"));
+        assert_eq!(Some("roast::jlong"), rust_to_jni_type("i64"));
+        assert_eq!(Some("roast::jlong"), rust_to_jni_type("Timestamp"));
+        assert_eq!(Some("roast::jlong"), rust_to_jni_type("u64"));
+        assert_eq!(Some("roast::jfloat"), rust_to_jni_type("f32"));
+        assert_eq!(Some("roast::jdouble"), rust_to_jni_type("f64"));
+        assert_eq!(Some("roast::jboolean"), rust_to_jni_type("bool"));
+        assert_eq!(Some("roast::JString"), rust_to_jni_type("String"));
+        assert_eq!(Some("roast::JString"), rust_to_jni_type("&str"));
+        assert_eq!(Some("roast::JString"), rust_to_jni_type("PathBuf"));
+        assert_eq!(Some("roast::jbyteArray"), rust_to_jni_type("Vec<u8>"));
+        assert_eq!(Some("roast::jbyteArray"), rust_to_jni_type("&[u8]"));
+        assert_eq!(Some("roast::jfloatArray"), rust_to_jni_type("Vec<f32>"));
+        assert_eq!(Some("roast::jbooleanArray"), rust_to_jni_type("Vec<bool>"));
+        assert_eq!(Some("roast::jintArray"), rust_to_jni_type("Vec<u32>"));
+        assert_eq!(Some("roast::jintArray"), rust_to_jni_type("Vec<i32>"));
+    }
+
+    #[test]
+    fn rust_type_to_kotlin_type() {
+        assert_eq!(Some("Byte"), rust_to_kotlin_type("i8"));
+        assert_eq!(Some("Boolean"), rust_to_kotlin_type("u8"));
+        assert_eq!(Some("Short"), rust_to_kotlin_type("i16"));
+        assert_eq!(Some("Char"), rust_to_kotlin_type("u16"));
+        assert_eq!(Some("Int"), rust_to_kotlin_type("i32"));
+        assert_eq!(Some("Long"), rust_to_kotlin_type("i64"));
+        assert_eq!(Some("Long"), rust_to_kotlin_type("Timestamp"));
+        assert_eq!(Some("Long"), rust_to_kotlin_type("u64"));
+        assert_eq!(Some("Float"), rust_to_kotlin_type("f32"));
+        assert_eq!(Some("Double"), rust_to_kotlin_type("f64"));
+        assert_eq!(Some("Boolean"), rust_to_kotlin_type("bool"));
+        assert_eq!(Some("String"), rust_to_kotlin_type("String"));
+        assert_eq!(Some("String"), rust_to_kotlin_type("&str"));
+        assert_eq!(Some("String"), rust_to_kotlin_type("PathBuf"));
+        assert_eq!(Some("ByteArray"), rust_to_kotlin_type("Vec<u8>"));
+        assert_eq!(Some("ByteArray"), rust_to_kotlin_type("&[u8]"));
+        assert_eq!(Some("FloatArray"), rust_to_kotlin_type("Vec<f32>"));
+        assert_eq!(Some("BooleanArray"), rust_to_kotlin_type("Vec<bool>"));
+        assert_eq!(Some("IntArray"), rust_to_kotlin_type("Vec<u32>"));
+        assert_eq!(Some("IntArray"), rust_to_kotlin_type("Vec<i32>"));
+    }
+
+    #[test]
+    fn rust_type_to_jni_descriptor() {
+        assert_eq!(Some("B"), rust_to_jni_descriptor("i8"));
+        assert_eq!(Some("Z"), rust_to_jni_descriptor("u8"));
+        assert_eq!(Some("S"), rust_to_jni_descriptor("i16"));
+        assert_eq!(Some("C"), rust_to_jni_descriptor("u16"));
+        assert_eq!(Some("I"), rust_to_jni_descriptor("i32"));
+        assert_eq!(Some("J"), rust_to_jni_descriptor("i64"));
+        assert_eq!(Some("J"), rust_to_jni_descriptor("Timestamp"));
+        assert_eq!(Some("J"), rust_to_jni_descriptor("u64"));
+        assert_eq!(Some("F"), rust_to_jni_descriptor("f32"));
+        assert_eq!(Some("D"), rust_to_jni_descriptor("f64"));
+        assert_eq!(Some("Z"), rust_to_jni_descriptor("bool"));
+        assert_eq!(Some("Ljava/lang/String;"), rust_to_jni_descriptor("String"));
+        assert_eq!(Some("Ljava/lang/String;"), rust_to_jni_descriptor("&str"));
+        assert_eq!(
+            Some("Ljava/lang/String;"),
+            rust_to_jni_descriptor("PathBuf")
+        );
+        assert_eq!(Some("[B"), rust_to_jni_descriptor("Vec<u8>"));
+        assert_eq!(Some("[B"), rust_to_jni_descriptor("&[u8]"));
+        assert_eq!(Some("[F"), rust_to_jni_descriptor("Vec<f32>"));
+        assert_eq!(Some("[Z"), rust_to_jni_descriptor("Vec<bool>"));
+        assert_eq!(Some("[I"), rust_to_jni_descriptor("Vec<u32>"));
+        assert_eq!(Some("[I"), rust_to_jni_descriptor("Vec<i32>"));
+        assert_eq!(None, rust_to_jni_descriptor("usize"));
+    }
+
+    #[test]
+    fn rust_type_to_convert_arg_fn() {
+        let convert_arg_fn = |ty| rust_to_type_mapping(ty).map(|m| m.convert_arg_fn);
+        assert_eq!(Some("convert_arg_jint"), convert_arg_fn("i32"));
+        assert_eq!(Some("convert_arg_jlong"), convert_arg_fn("Timestamp"));
+        assert_eq!(Some("convert_arg_jlong_to_u64"), convert_arg_fn("u64"));
+        assert_eq!(Some("convert_arg_jstring"), convert_arg_fn("String"));
+        assert_eq!(Some("convert_arg_jpathstring"), convert_arg_fn("PathBuf"));
+        assert_eq!(Some("convert_arg_jbytearray"), convert_arg_fn("Vec<u8>"));
+        assert_eq!(Some("convert_arg_juintarray"), convert_arg_fn("Vec<u32>"));
+        assert_eq!(Some("convert_arg_jintarray"), convert_arg_fn("Vec<i32>"));
+        assert_eq!(None, convert_arg_fn("usize"));
+    }
+
+    #[test]
+    fn func_jni_descriptor() {
+        assert_eq!(
+            "()V",
+            DerivedFn::new("foo", None, vec![])
+                .jni_descriptor()
+                .unwrap()
+        );
+
+        let args = vec![
+            DerivedFnArg::SelfBorrow { mutable: false },
+            DerivedFnArg::Captured {
+                name: "a".into(),
+                ty: "i32".into(),
+            },
+            DerivedFnArg::Captured {
+                name: "b".into(),
+                ty: "String".into(),
+            },
+        ];
+        let func = DerivedFn::new("foo", Some("Vec<u8>".into()), args);
+        assert_eq!("(ILjava/lang/String;)[B", func.jni_descriptor().unwrap());
+
+        let args = vec![DerivedFnArg::Callback {
+            name: "supplier".into(),
+            java_interface: "java.util.function.IntSupplier".into(),
+        }];
+        let func = DerivedFn::new("foo", Some("i64".into()), args);
+        assert_eq!(
+            "(Ljava/util/function/IntSupplier;)J",
+            func.jni_descriptor().unwrap()
+        );
+    }
+
+    #[test]
+    fn func_jni_descriptor_unsupported() {
+        let args = vec![DerivedFnArg::Captured {
+            name: "a".into(),
+            ty: "usize".into(),
+        }];
+        assert!(DerivedFn::new("foo", None, args).jni_descriptor().is_err());
+        assert!(DerivedFn::new("foo", Some("usize".into()), vec![])
+            .jni_descriptor()
+            .is_err());
+    }
+
+    #[test]
+    fn func_name_to_java_style() {
+        assert_eq!(
+            String::from("func"),
+            DerivedFn::new("func", None, vec![]).java_name()
+        );
+        assert_eq!(
+            String::from("myFuncName"),
+            DerivedFn::new("my_func_name", None, vec![]).java_name()
+        );
+    }
+
+    #[test]
+    fn jni_symbols_listed() {
+        let derived = DerivedEntity::new(
+            "Stats",
+            vec![
+                DerivedFn::new(
+                    "max",
+                    Some("i32".into()),
+                    vec![DerivedFnArg::Captured {
+                        name: "values".into(),
+                        ty: "Vec<i32>".into(),
+                    }],
+                ),
+                DerivedFn::new("split", Some("(i32, i32)".into()), vec![]),
+            ],
+        )
+        .with_inner_class_of("Arrays");
+        assert_eq!(
+            vec![
+                JniSymbol {
+                    symbol: "Java_Arrays_00024Stats_max".into(),
+                    java_class: "Arrays$Stats".into(),
+                    java_method: "max".into(),
+                    descriptor: Some("([I)I".into()),
+                    rust_function: "Stats::max".into(),
+                },
+                JniSymbol {
+                    symbol: "Java_Arrays_00024Stats_splitNative".into(),
+                    java_class: "Arrays$Stats".into(),
+                    java_method: "splitNative".into(),
+                    descriptor: Some("()[Ljava/lang/Object;".into()),
+                    rust_function: "Stats::split".into(),
+                },
+            ],
+            derived.jni_symbols()
+        );
+
+        let this = DerivedFnArg::SelfBorrow { mutable: false };
+        let shape = DerivedEntity::new(
+            "Shape",
+            vec![DerivedFn::new("area", Some("f64".into()), vec![this])],
+        )
+        .with_trait_object("com.example.Shape");
+        let symbols = shape.jni_symbols();
+        assert_eq!(2, symbols.len());
+        assert_eq!("Java_com_example_Shape_area", symbols[0].symbol);
+        assert_eq!(Some("(J)D"), symbols[0].descriptor.as_deref());
+        assert_eq!("Java_com_example_Shape_destroy", symbols[1].symbol);
+        let exported = format!("{}", shape.export_jni_ffi_tokens());
+        for symbol in &symbols {
+            assert!(exported.contains(&format!("fn {} ", symbol.symbol)));
+        }
+    }
+
+    #[test]
+    fn func_name_naming_strategies() {
+        let java_name = |naming| {
+            DerivedFn::new("get_http_response", None, vec![])
+                .with_naming(naming)
+                .java_name()
+        };
+        assert_eq!("getHttpResponse", java_name(NamingStrategy::Camel));
+        assert_eq!("getHTTPResponse", java_name(NamingStrategy::AcronymCamel));
+        assert_eq!("get_http_response", java_name(NamingStrategy::Verbatim));
+        assert_eq!(
+            "urlForID",
+            DerivedFn::new("url_for_id", None, vec![])
+                .with_naming(NamingStrategy::AcronymCamel)
+                .java_name()
+        );
+    }
+
+    #[test]
+    fn ffi_convert_verbatim_naming() {
+        let derived = DerivedEntity::new(
+            "Entity",
+            vec![
+                DerivedFn::new("get_http_response", None, vec![]),
+                DerivedFn::new("parseURL", None, vec![]),
+            ],
+        )
+        .with_naming(NamingStrategy::Verbatim);
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.contains("	public static native void get_http_response();"));
+        assert!(java.contains("	public static native void parseURL();"));
+
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        assert!(exported.contains("fn Java_Entity_get_1http_1response "));
+        assert!(exported.contains("fn Java_Entity_parseURL "));
+        let registered = format!("{}", derived.generate_jni_registration_table());
+        assert!(registered.contains("\"get_http_response\""));
+        assert!(registered.contains("Java_Entity_get_1http_1response"));
+        assert!(derived
+            .strict_violations()
+            .iter()
+            .all(|v| !v.contains("snake case")));
+    }
+
+    #[test]
+    #[should_panic(expected = "The class name must not be empty")]
+    fn entity_name_empty() {
+        DerivedEntity::new("", vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The class name \"My Entity\" contains the invalid character ' '")]
+    fn entity_name_with_space() {
+        DerivedEntity::new("My Entity", vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The class name \"1Entity\" must not start with a digit")]
+    fn entity_name_starts_with_digit() {
+        DerivedEntity::new("1Entity", vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The class name \"Entity-1\" contains the invalid character '-'")]
+    fn entity_name_with_invalid_char() {
+        DerivedEntity::new("Entity-1", vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The method name must not be empty")]
+    fn fn_name_empty() {
+        DerivedFn::new("", None, vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The method name \"my func\" contains the invalid character ' '")]
+    fn fn_name_with_space() {
+        DerivedFn::new("my func", None, vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The method name \"2func\" must not start with a digit")]
+    fn fn_name_starts_with_digit() {
+        DerivedFn::new("2func", None, vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The method name \"func!\" contains the invalid character '!'")]
+    fn fn_name_with_invalid_char() {
+        DerivedFn::new("func!", None, vec![]);
+    }
+
+    #[test]
+    fn valid_names() {
+        DerivedEntity::new("_Entity$1", vec![DerivedFn::new("my_func_2", None, vec![])]);
+    }
+
+    #[test]
+    fn methods_count_and_empty() {
+        let derived = DerivedEntity::new("Entity", vec![]);
+        assert_eq!(0, derived.methods_count());
+        assert!(derived.is_empty());
+
+        let derived = DerivedEntity::new("Entity", vec![DerivedFn::new("foo", None, vec![])]);
+        assert_eq!(1, derived.methods_count());
+        assert!(!derived.is_empty());
+    }
+
+    #[test]
+    fn java_convert_no_methods() {
+        let derived = DerivedEntity::new("Entity", vec![]);
+        let expected = r#"public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn java_convert_package() {
+        let fns = vec![DerivedFn::new("foo", Some("i32".into()), vec![])];
+        let derived = DerivedEntity::new("Entity", fns).with_package("com.my_app");
+        let expected = r#"package com.my_app;
+
+// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native int foo();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        assert_eq!("com/my_app/Entity.java", derived.java_source_path("Entity"));
+        assert_eq!(
+            "Java_com_my_1app_Entity_foo",
+            derived.jni_symbols()[0].symbol
+        );
+        assert!(derived
+            .export_kotlin_actual_syntax("mylib")
+            .unwrap()
+            .starts_with("package com.my_app\n\nactual object Entity {"));
+
+        let inner = DerivedEntity::new("Inner", vec![DerivedFn::new("foo", None, vec![])])
+            .with_package("com.my_app")
+            .with_inner_class_of("Entity");
+        assert_eq!(
+            "Java_com_my_1app_Entity_00024Inner_foo",
+            inner.jni_symbols()[0].symbol
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The package name \"my-app\" contains the invalid character '-'")]
+    fn invalid_package() {
+        DerivedEntity::new("Entity", vec![]).with_package("com.my-app");
+    }
+
+    #[test]
+    fn java_convert_manual_lib_loader() {
+        let derived = DerivedEntity::new("Entity", vec![]).with_lib_loader(LibLoader::Manual);
+        assert_eq!(
+            "public class Entity {\n\n}\n",
+            derived.export_java_syntax("mylib").unwrap()
+        );
+        assert!(derived.export_java_native_lib_loader_syntax().is_none());
+    }
+
+    #[test]
+    fn java_convert_resource_lib_loader() {
+        let derived = DerivedEntity::new("Entity", vec![]).with_lib_loader(LibLoader::Resource);
+        let expected = r#"public class Entity {
+
+	static {
+		NativeLibLoader.loadFromJar("mylib");
+	}
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        let loader = derived.export_java_native_lib_loader_syntax().unwrap();
+        assert!(loader.starts_with("import java.io.IOException;"));
+        assert!(loader.contains("public final class NativeLibLoader {"));
+        assert!(loader.contains("public static synchronized void loadFromJar(String name) {"));
+
+        let derived = derived.with_package("com.example");
+        let loader = derived.export_java_native_lib_loader_syntax().unwrap();
+        assert!(loader.starts_with("package com.example;\n\nimport java.io.IOException;"));
+        assert_eq!(
+            "com/example/NativeLibLoader.java",
+            derived.java_source_path("NativeLibLoader")
+        );
+    }
+
+    #[test]
+    fn ffi_convert_no_methods() {
+        let derived = DerivedEntity::new("Entity", vec![]);
+        let tokens = derived.export_jni_ffi_tokens();
+        let exported = format!("{}", tokens);
+        assert!(exported.is_empty());
+    }
+
+    #[test]
+    fn header_convert_no_methods() {
+        let derived = DerivedEntity::new("Entity", vec![]);
+        let expected = r#"/* Generated by roast, do not edit */
+#ifndef _Included_Entity
+#define _Included_Entity
+#include <jni.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#ifdef __cplusplus
+}
+#endif
+#endif
+"#;
+        assert_eq!(expected, derived.export_header_syntax());
+    }
+
+    #[test]
+    fn header_convert_methods() {
+        let fns = vec![
+            DerivedFn::new(
+                "add_long",
+                Some("i64".into()),
+                vec![
+                    DerivedFnArg::Captured {
+                        name: "a".into(),
+                        ty: "i64".into(),
+                    },
+                    DerivedFnArg::Captured {
+                        name: "b_value".into(),
+                        ty: "i32".into(),
+                    },
+                ],
+            ),
+            DerivedFn::new(
+                "is_empty",
+                Some("bool".into()),
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            ),
+            DerivedFn::new(
+                "reverse",
+                Some("String".into()),
+                vec![DerivedFnArg::Captured {
+                    name: "input".into(),
+                    ty: "String".into(),
+                }],
+            ),
+        ];
+        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"/* Generated by roast, do not edit */
+#ifndef _Included_Entity
+#define _Included_Entity
+#include <jni.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+JNIEXPORT jlong JNICALL Java_Entity_addLong(JNIEnv *env, jclass clazz, jlong a, jint b_value);
+
+JNIEXPORT jboolean JNICALL Java_Entity_isEmpty(JNIEnv *env, jobject obj);
+
+JNIEXPORT jstring JNICALL Java_Entity_reverse(JNIEnv *env, jclass clazz, jstring input);
+
+#ifdef __cplusplus
+}
+#endif
+#endif
+"#;
+        assert_eq!(expected, derived.export_header_syntax());
+    }
+
+    fn kotlin_test_fns() -> Vec<DerivedFn> {
+        vec![
+            DerivedFn::new(
+                "add_int",
+                Some("i32".into()),
+                vec![
+                    DerivedFnArg::Captured {
+                        name: "a".into(),
+                        ty: "i32".into(),
+                    },
+                    DerivedFnArg::Captured {
+                        name: "b_value".into(),
+                        ty: "i32".into(),
+                    },
+                ],
+            ),
+            DerivedFn::new(
+                "reset",
+                None,
+                vec![DerivedFnArg::SelfBorrow { mutable: true }],
+            ),
+        ]
+    }
+
+    #[test]
+    fn kotlin_convert_expect() {
+        let derived = DerivedEntity::new("Entity", kotlin_test_fns());
+        let expected = r#"expect object Entity {
+
+	fun addInt(a: Int, bValue: Int): Int
+
+	fun reset()
+
+}
+"#;
+        assert_eq!(expected, derived.export_kotlin_expect_syntax().unwrap());
+    }
+
+    #[test]
+    fn kotlin_convert_actual() {
+        let derived = DerivedEntity::new("Entity", kotlin_test_fns());
+        let expected = r#"actual object Entity {
+
+	init {
+		System.loadLibrary("mylib")
+	}
+
+	@JvmStatic actual external fun addInt(a: Int, bValue: Int): Int
+
+	actual external fun reset()
+
+}
+"#;
+        assert_eq!(
+            expected,
+            derived.export_kotlin_actual_syntax("mylib").unwrap()
+        );
+    }
+
+    fn accessor_test_fns() -> Vec<DerivedFn> {
+        let setter = |name: &str, ty: &str| {
+            DerivedFn::new(
+                name,
+                None,
+                vec![
+                    DerivedFnArg::SelfBorrow { mutable: true },
+                    DerivedFnArg::Captured {
+                        name: "value".into(),
+                        ty: ty.into(),
+                    },
+                ],
+            )
+        };
+        let getter = |name: &str, ty: &str| {
+            DerivedFn::new(
+                name,
+                Some(ty.into()),
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            )
+        };
+        let handle_getter = |name: &str, ty: &str| {
+            DerivedFn::new(
+                name,
+                Some(ty.into()),
+                vec![DerivedFnArg::NativeHandle {
+                    name: "point".into(),
+                    mutable: false,
+                }],
+            )
+        };
+        vec![
+            handle_getter("get_label", "String"),
+            getter("get_only", "i32"),
+            getter("get_pos_x", "i32"),
+            getter("get_y", "i32"),
+            setter("set_label", "String"),
+            setter("set_pos_x", "i32"),
+            setter("set_y", "i64"),
+        ]
+    }
+
+    #[test]
+    fn detect_accessor_pairs() {
+        let derived = DerivedEntity::new("Point", accessor_test_fns());
+        let pairs = derived.detect_accessor_pairs();
+        assert_eq!(2, pairs.len());
+        assert_eq!("label", pairs[0].field);
+        assert_eq!("String", pairs[0].ty);
+        assert_eq!("get_label", pairs[0].getter.name());
+        assert_eq!("pos_x", pairs[1].field);
+        assert_eq!("i32", pairs[1].ty);
+    }
+
+    #[test]
+    fn kotlin_convert_data_class() {
+        let derived = DerivedEntity::new("Point", accessor_test_fns());
+        let expected = r#"data class PointData(val label: String, val posX: Int) {
+
+	companion object {
+
+		fun fromNative(handle: Long): PointData = PointData(Point.getLabel(handle), Point.getPosX())
+
+	}
+
+}
+"#;
+        assert_eq!(
+            Some(expected.into()),
+            derived.export_kotlin_data_class_syntax().unwrap()
+        );
+    }
+
+    #[test]
+    fn kotlin_convert_data_class_without_accessors() {
+        let derived = DerivedEntity::new("Entity", kotlin_test_fns());
+        assert_eq!(None, derived.export_kotlin_data_class_syntax().unwrap());
+    }
+
+    #[test]
+    fn kotlin_convert_callback_unsupported() {
+        let fns = vec![DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Callback {
+                name: "supplier".into(),
+                java_interface: "java.util.function.IntSupplier".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        assert!(derived.export_kotlin_expect_syntax().is_err());
+        assert!(derived.export_kotlin_actual_syntax("mylib").is_err());
+    }
+
+    #[test]
+    fn java_convert_utility_class() {
+        let fns = vec![DerivedFn::new("foobar", None, vec![])];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
+        let expected = r#"public final class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	private Entity() {
+	}
+
+	public static native void foobar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn static_only() {
+        assert!(!DerivedEntity::new("Entity", vec![]).is_static_only());
+
+        let fns = vec![DerivedFn::new("foo", None, vec![])];
+        assert!(DerivedEntity::new("Entity", fns).is_static_only());
+
+        let fns = vec![
+            DerivedFn::new("foo", None, vec![]),
+            DerivedFn::new(
+                "bar",
+                None,
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            ),
+        ];
+        assert!(!DerivedEntity::new("Entity", fns).is_static_only());
+    }
+
+    #[test]
+    fn java_convert_static_no_arg_no_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("foobar", None, vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native void foobar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_static_no_arg_no_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("foobar", None, vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected =
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_foobar (_env : roast :: \
+             JNIEnv , _class : roast :: JClass) { Entity :: foobar () }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_no_arg_no_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foobar",
+            None,
+            vec![DerivedFnArg::SelfBorrow { mutable: false }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public native void foobar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_no_arg_no_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foobar",
+            None,
+            vec![DerivedFnArg::SelfBorrow { mutable: false }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected =
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_foobar (_env : roast :: \
+             JNIEnv , _obj : roast :: JObject) { Entity :: foobar () }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_static_no_arg_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("foobar", Some("i32".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native int foobar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_static_no_arg_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("foobar", Some("i32".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_foobar (env : roast :: \
+             JNIEnv , _class : roast :: JClass) -> roast :: jint { roast :: convert :: \
+             convert_retval_i32 (& env , Entity :: foobar ()) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_static_arg_no_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foobar",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "a".into(),
+                ty: "i64".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native void foobar(long a);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_static_arg_no_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foobar",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "a".into(),
+                ty: "i64".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_foobar (env : roast :: \
+             JNIEnv , _class : roast :: JClass , a : roast :: jlong) { Entity :: foobar \
+             (roast :: convert :: convert_arg_jlong (& env , a)) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_static_arg_and_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foobar",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "i16".into(),
+                },
+            ],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native boolean foobar(int a, short b);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn java_convert_thread_safe() {
+        let fns = vec![
+            DerivedFn::new("foo", Some("i32".into()), vec![]),
+            DerivedFn::new(
+                "bar",
+                None,
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            ),
+        ];
+        let derived = DerivedEntity::new("Entity", fns).with_thread_safe(true);
+
+        let expected = r#"public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static synchronized native int foo();
+
+	public synchronized native void bar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn thread_safety_assertion() {
+        let derived = DerivedEntity::new("Entity", vec![]).with_thread_safe(true);
+        let expected = "const _ : () = { fn assert_send_sync < T : Send + Sync > () { } fn \
+             assert_all () { assert_send_sync :: < Entity > () ; } } ;";
+        assert_eq!(
+            expected,
+            format!("{}", derived.generate_thread_safety_assertion())
+        );
+    }
+
+    #[test]
+    fn java_convert_timestamp() {
+        let fns = vec![DerivedFn::new(
+            "later",
+            Some("Timestamp".into()),
+            vec![DerivedFnArg::Captured {
+                name: "since".into(),
+                ty: "Timestamp".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
+
+        let expected = r#"public final class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	private Entity() {
+	}
+
+	public static native /* milliseconds since epoch */ long later(/* milliseconds since epoch */ long since);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_timestamp() {
+        let fns = vec![DerivedFn::new(
+            "later",
+            Some("Timestamp".into()),
+            vec![DerivedFnArg::Captured {
+                name: "since".into(),
+                ty: "Timestamp".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_later (env : roast :: \
+             JNIEnv , _class : roast :: JClass , since : roast :: jlong) -> roast :: jlong { \
+             roast :: convert :: convert_retval_timestamp (& env , Entity :: later (roast :: \
+             convert :: convert_arg_jlong (& env , since))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_u64() {
+        let fns = vec![DerivedFn::new(
+            "next_id",
+            Some("u64".into()),
+            vec![DerivedFnArg::Captured {
+                name: "id".into(),
+                ty: "u64".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
+
+        let expected = r#"public final class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	private Entity() {
+	}
+
+	/**
+	 * The long values marked as unsigned hold the bits of a u64, so values
+	 * above Long.MAX_VALUE appear negative. Use Long.toUnsignedString and
+	 * Long.parseUnsignedLong to work with them.
+	 */
+	public static native /* unsigned */ long nextId(/* unsigned */ long id);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_u64() {
+        let fns = vec![DerivedFn::new(
+            "next_id",
+            Some("u64".into()),
+            vec![DerivedFnArg::Captured {
+                name: "id".into(),
+                ty: "u64".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_nextId (env : roast :: \
+             JNIEnv , _class : roast :: JClass , id : roast :: jlong) -> roast :: jlong { \
+             roast :: convert :: convert_retval_u64 (& env , Entity :: next_id (roast :: \
+             convert :: convert_arg_jlong_to_u64 (& env , id))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_u32_array_arg_and_ret() {
+        let fns = vec![DerivedFn::new(
+            "increment",
+            Some("Vec<u32>".into()),
+            vec![DerivedFnArg::Captured {
+                name: "values".into(),
+                ty: "Vec<u32>".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_increment (env : roast \
+             :: JNIEnv , _class : roast :: JClass , values : roast :: jintArray) -> roast :: \
+             jintArray { roast :: convert :: convert_retval_vecu32 (& env , Entity :: increment \
+             (roast :: convert :: convert_arg_juintarray (& env , values))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_path_arg_and_ret() {
+        let fns = vec![DerivedFn::new(
+            "resolve",
+            Some("PathBuf".into()),
+            vec![DerivedFnArg::Captured {
+                name: "base".into(),
+                ty: "PathBuf".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected =
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_resolve (env : roast :: \
+             JNIEnv , _class : roast :: JClass , base : roast :: JString) -> roast :: jstring { \
+             roast :: convert :: convert_retval_pathbuf (& env , Entity :: resolve (roast :: \
+             convert :: convert_arg_jpathstring (& env , base))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_static_arg_and_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foobar",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "i16".into(),
+                },
+            ],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_foobar (env : roast :: \
+             JNIEnv , _class : roast :: JClass , a : roast :: jint , b : roast :: jshort) \
+             -> roast :: jboolean { roast :: convert :: convert_retval_bool (& env , Entity \
+             :: foobar (roast :: convert :: convert_arg_jint (& env , a) , roast :: convert \
+             :: convert_arg_jshort (& env , b))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_static_two_methods() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foo",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "i16".into(),
+                },
+            ],
+        ));
+        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native boolean foo(int a, short b);
+
+	public static native int bar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_static_two_methods() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foo",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "i16".into(),
+                },
+            ],
+        ));
+        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected =
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_foo (env : roast :: JNIEnv \
+             , _class : roast :: JClass , a : roast :: jint , b : roast :: jshort) -> roast \
+             :: jboolean { roast :: convert :: convert_retval_bool (& env , Entity :: foo \
+             (roast :: convert :: convert_arg_jint (& env , a) , roast :: convert :: \
+             convert_arg_jshort (& env , b))) } # [no_mangle] pub extern \"system\" fn \
+             Java_Entity_bar (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: \
+             jint { roast :: convert :: convert_retval_i32 (& env , Entity :: bar ()) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_mixed_static_nonstatic_two_methods() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foo",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "i16".into(),
+                },
+                DerivedFnArg::SelfOwned { mutable: true },
+            ],
+        ));
+        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public native boolean foo(int a, short b);
+
+	public static native int bar();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_mixed_static_nonstatic_two_methods() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "get_foo_bar",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "i16".into(),
+                },
+                DerivedFnArg::SelfOwned { mutable: true },
+            ],
+        ));
+        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+
+        let derived = DerivedEntity::new("Entity", fns).with_unsafe_allowed(true);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected =
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_getFooBar (env : roast :: \
+             JNIEnv , _obj : roast :: JObject , a : roast :: jint , b : roast :: jshort) -> \
+             roast :: jboolean { roast :: convert :: convert_retval_bool (& env , Entity :: \
+             get_foo_bar (roast :: convert :: convert_arg_jint (& env , a) , roast :: \
+             convert :: convert_arg_jshort (& env , b))) } # [no_mangle] pub extern \
+             \"system\" fn Java_Entity_bar (env : roast :: JNIEnv , _class : roast :: \
+             JClass) -> roast :: jint { roast :: convert :: convert_retval_i32 (& env , \
+             Entity :: bar ()) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn unsafe_reasons() {
+        let reason = |arg| DerivedFn::new("foo", None, vec![arg]).unsafe_reason();
+        assert!(reason(DerivedFnArg::SelfOwned { mutable: true }).is_some());
+        assert!(reason(DerivedFnArg::SelfOwned { mutable: false }).is_some());
+        assert!(reason(DerivedFnArg::SelfBorrow { mutable: true }).is_some());
+        assert!(reason(DerivedFnArg::SelfBorrow { mutable: false }).is_none());
+        assert!(DerivedFn::new("foo", None, vec![])
+            .unsafe_reason()
+            .is_none());
+    }
+
+    #[test]
+    fn ffi_convert_unsafe_not_allowed() {
+        let fns = vec![DerivedFn::new(
+            "reset",
+            None,
+            vec![DerivedFnArg::SelfBorrow { mutable: true }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "compile_error ! (\"Entity::reset takes `&mut self`, which java can alias \
+             from multiple threads. Add #[roast(unsafe_allowed)] to Entity if this is \
+             intended.\") ;";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_unsafe_allowed() {
+        let fns = vec![DerivedFn::new(
+            "reset",
+            None,
+            vec![DerivedFnArg::SelfBorrow { mutable: true }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns).with_unsafe_allowed(true);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_reset (_env : roast :: \
+             JNIEnv , _obj : roast :: JObject) { Entity :: reset () }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_string_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("String".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myfunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass) -> roast :: jstring { roast :: convert :: \
+             convert_retval_string (& env , Entity :: myfunc ()) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_multiple_string_args() {
+        let string_arg = |name: &str| DerivedFnArg::Captured {
+            name: name.into(),
+            ty: "String".into(),
+        };
+        let fns = vec![DerivedFn::new(
+            "format",
+            Some("String".into()),
+            vec![string_arg("template"), string_arg("value")],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_format (env : roast :: \
+             JNIEnv , _class : roast :: JClass , template : roast :: JString , value : roast :: \
+             JString) -> roast :: jstring { roast :: convert :: convert_retval_string (& env , \
+             Entity :: format (roast :: convert :: convert_arg_jstring (& env , template) , \
+             roast :: convert :: convert_arg_jstring (& env , value))) }";
+        assert_eq!(expected, exported);
+        assert_eq!(
+            "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+            derived.fns[0].jni_descriptor().unwrap()
+        );
+    }
+
+    #[test]
+    fn ffi_convert_result_string_string_return_value() {
+        let fns = vec![DerivedFn::new(
+            "parse",
+            Some("Result<String,String>".into()),
+            vec![],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_parse (env : roast :: \
+             JNIEnv , _class : roast :: JClass) -> roast :: jstring { roast :: convert :: \
+             convert_retval_result_string_string (& env , Entity :: parse ()) }";
+        assert_eq!(expected, exported);
+        assert_eq!(
+            Some("result_string_string".into()),
+            derived.fns[0].sanitized_return_type()
+        );
+        assert_eq!(None, rust_to_jni_type("Result<String,String>"));
+        assert!(derived
+            .export_java_syntax("mylib")
+            .unwrap()
+            .contains("public static native String parse();"));
+    }
+
+    #[test]
+    fn java_convert_string_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("String".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native String myfunc();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_string_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "my_var".into(),
+                ty: "String".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myFunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass , my_var : roast :: JString) { Entity :: \
+             my_func (roast :: convert :: convert_arg_jstring (& env , my_var)) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_string_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "my_var".into(),
+                ty: "String".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native void myFunc(String myVar);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_bytearray_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "my_var".into(),
+                ty: "Vec<u8>".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myFunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass , my_var : roast :: jbyteArray) { Entity :: \
+             my_func (roast :: convert :: convert_arg_jbytearray (& env , my_var)) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_byte_slice_arg_value() {
+        let fns = vec![DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "my_var".into(),
+                ty: "&[u8]".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myFunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass , my_var : roast :: jbyteArray) { Entity :: \
+             my_func (roast :: convert :: convert_arg_jbytearray (& env , my_var) . as_slice ()) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_mut_byte_slice_arg_value() {
+        let fns = vec![DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "my_var".into(),
+                ty: "&mut [u8]".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myFunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass , my_var : roast :: jbyteArray) { { let mut \
+             my_var_mut = roast :: convert :: convert_arg_jbytearray (& env , my_var) ; let \
+             result = Entity :: my_func (& mut my_var_mut) ; roast :: convert :: \
+             convert_arg_jbytearray_write_back (& env , my_var , & my_var_mut) ; result } }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn float_precision_warning() {
+        let float = |name: &str, ty: &str| DerivedFnArg::Captured {
+            name: name.into(),
+            ty: ty.into(),
+        };
+        let derived = DerivedEntity::new(
+            "Entity",
+            vec![
+                DerivedFn::new("mixed", None, vec![float("a", "f32"), float("b", "f64")]),
+                DerivedFn::new("floats", None, vec![float("a", "f32"), float("b", "f32")]),
+                DerivedFn::new(
+                    "lossy",
+                    None,
+                    vec![
+                        DerivedFnArg::LossyFloat { name: "a".into() },
+                        float("b", "f64"),
+                    ],
+                ),
+            ],
+        );
+        assert_eq!(
+            vec![String::from(
+                "Entity::mixed mixes f32 and f64 arguments, the f32 ones are java floats, mark \
+                 them with #[roast(lossy)] to accept a double, or silence this with \
+                 #[roast(allow_mixed_floats)] on the struct"
+            )],
+            derived.float_precision_warnings()
+        );
+    }
+
+    #[test]
+    fn java_sealed_enum() {
+        let derived = DerivedEnum::new(
+            "Shape",
+            vec![
+                DerivedVariant::new("Circle", vec![("radius".into(), "f64".into())]),
+                DerivedVariant::new(
+                    "Label",
+                    vec![
+                        ("text".into(), "String".into()),
+                        ("max_width".into(), "i32".into()),
+                    ],
+                ),
+                DerivedVariant::unit("Empty"),
+            ],
+        );
+        let expected = r#"// Generated by roast from the rust enum Shape, requires java 17
+import java.math.BigDecimal;
+import java.util.HashMap;
+import java.util.Map;
+
+public sealed interface Shape permits Shape.Circle, Shape.Label, Shape.Empty {
+
+	record Circle(double radius) implements Shape {}
+
+	record Label(String text, int maxWidth) implements Shape {}
+
+	record Empty() implements Shape {}
+
+	/**
+	 * Creates the variant from the JSON of the rust enum, like the return
+	 * value of a method marked with #[roast(json)].
+	 */
+	static Shape fromJson(String json) {
+		Object value = parseJson(json, new int[] {0});
+		if (value instanceof String variant) {
+			switch (variant) {
+				case "Empty": return new Empty();
+			}
+		}
+		if (value instanceof Map<?, ?> object && object.size() == 1) {
+			Map.Entry<?, ?> entry = object.entrySet().iterator().next();
+			Map<?, ?> fields = (Map<?, ?>) entry.getValue();
+			switch ((String) entry.getKey()) {
+				case "Circle": return new Circle(((BigDecimal) fields.get("radius")).doubleValue());
+				case "Label": return new Label((String) fields.get("text"), ((BigDecimal) fields.get("max_width")).intValueExact());
+			}
+		}
+		throw new IllegalArgumentException("Not a variant of Shape: " + json);
+	}
+
+"#;
+        let exported = derived.export_java_syntax().unwrap();
+        assert_eq!(format!("{}{}}}\n", expected, JAVA_JSON_PARSER), exported);
+    }
+
+    #[test]
+    fn java_sealed_enum_in_package() {
+        let derived = DerivedEnum::new("Shape", vec![DerivedVariant::unit("Empty")])
+            .with_package("com.example");
+        assert_eq!("com/example/Shape.java", derived.java_source_path());
+        assert!(derived.export_java_syntax().unwrap().starts_with(
+            "// Generated by roast from the rust enum Shape, requires java 17\n\
+             package com.example;\n\nimport java.math.BigDecimal;"
+        ));
+        assert_eq!(
+            "Shape.java",
+            DerivedEnum::new("Shape", vec![]).java_source_path()
+        );
+    }
+
+    #[test]
+    fn java_sealed_enum_unsupported_field() {
+        let derived = DerivedEnum::new(
+            "Shape",
+            vec![DerivedVariant::new(
+                "Circle",
+                vec![("radius".into(), "u8".into())],
+            )],
+        );
+        assert_eq!(
+            "Unsupported field type 'u8' of field 'radius' in variant 'Circle'",
+            derived.export_java_syntax().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn java_convert_bytearray_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            None,
+            vec![DerivedFnArg::Captured {
+                name: "my_var".into(),
+                ty: "Vec<u8>".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native void myFunc(byte[] myVar);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_bytearray_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("Vec<u8>".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myfunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass) -> roast :: jbyteArray { roast :: convert \
+             :: convert_retval_vecu8 (& env , Entity :: myfunc ()) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_bytearray_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("Vec<u8>".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native byte[] myfunc();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_str_ref_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("&str".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myfunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass) -> roast :: jstring { roast :: convert \
+             :: convert_retval_str (& env , Entity :: myfunc ()) }";
+        assert_eq!(expected, exported);
+    }
 
-    use super::*;
+    #[test]
+    fn java_convert_str_ref_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("&str".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native String myfunc();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
 
     #[test]
-    fn rust_type_to_java_type() {
-        assert_eq!(Some("byte"), rust_to_java_type("i8"));
-        assert_eq!(Some("boolean"), rust_to_java_type("u8"));
-        assert_eq!(Some("short"), rust_to_java_type("i16"));
-        assert_eq!(Some("char"), rust_to_java_type("u16"));
-        assert_eq!(Some("int"), rust_to_java_type("i32"));
-        assert_eq!(Some("long"), rust_to_java_type("i64"));
-        assert_eq!(Some("float"), rust_to_java_type("f32"));
-        assert_eq!(Some("double"), rust_to_java_type("f64"));
-        assert_eq!(Some("boolean"), rust_to_java_type("bool"));
-        assert_eq!(Some("String"), rust_to_java_type("String"));
-        assert_eq!(Some("byte[]"), rust_to_java_type("Vec<u8>"));
+    fn ffi_convert_byte_slice_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("&[u8]".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myfunc (env : roast :: \
+             JNIEnv , _class : roast :: JClass) -> roast :: jbyteArray { roast :: convert \
+             :: convert_retval_sliceu8 (& env , Entity :: myfunc ()) }";
+        assert_eq!(expected, exported);
     }
 
     #[test]
-    fn rust_type_to_jni_type() {
-        assert_eq!(Some("roast::jbyte"), rust_to_jni_type("i8"));
-        assert_eq!(Some("roast::jboolean"), rust_to_jni_type("u8"));
-        assert_eq!(Some("roast::jshort"), rust_to_jni_type("i16"));
-        assert_eq!(Some("roast::jchar"), rust_to_jni_type("u16"));
-        assert_eq!(Some("roast::jint"), rust_to_jni_type("i32"));
-        assert_eq!(Some("roast::jlong"), rust_to_jni_type("i64"));
-        assert_eq!(Some("roast::jfloat"), rust_to_jni_type("f32"));
-        assert_eq!(Some("roast::jdouble"), rust_to_jni_type("f64"));
-        assert_eq!(Some("roast::jboolean"), rust_to_jni_type("bool"));
-        assert_eq!(Some("roast::JString"), rust_to_jni_type("String"));
-        assert_eq!(Some("roast::jbyteArray"), rust_to_jni_type("Vec<u8>"));
+    fn java_convert_byte_slice_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("&[u8]".into()), vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native byte[] myfunc();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_byte_array_arg_and_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "reverse",
+            Some("Vec<u8>".into()),
+            vec![DerivedFnArg::Captured {
+                name: "input".into(),
+                ty: "Vec<u8>".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected =
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_reverse (env : roast :: \
+             JNIEnv , _class : roast :: JClass , input : roast :: jbyteArray) -> roast :: \
+             jbyteArray { roast :: convert :: convert_retval_vecu8 (& env , Entity :: reverse \
+             (roast :: convert :: convert_arg_jbytearray (& env , input))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_byte_array_arg_and_ret() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "reverse",
+            Some("Vec<u8>".into()),
+            vec![DerivedFnArg::Captured {
+                name: "input".into(),
+                ty: "Vec<u8>".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native byte[] reverse(byte[] input);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_registration_table() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "foo",
+            Some("bool".into()),
+            vec![
+                DerivedFnArg::Captured {
+                    name: "a".into(),
+                    ty: "i32".into(),
+                },
+                DerivedFnArg::Captured {
+                    name: "b".into(),
+                    ty: "String".into(),
+                },
+            ],
+        ));
+        fns.push(DerivedFn::new("get_bar", None, vec![]));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.generate_jni_registration_table());
+        let expected = "impl Entity { pub fn register_natives (env : & roast :: JNIEnv , class : \
+             roast :: JClass) { let methods : [roast :: NativeMethod ; 2usize] = [roast :: \
+             NativeMethod { name : \"foo\" . into () , sig : \"(ILjava/lang/String;)Z\" . into () \
+             , fn_ptr : Java_Entity_foo as * mut std :: ffi :: c_void } , roast :: NativeMethod \
+             { name : \"getBar\" . into () , sig : \"()V\" . into () , fn_ptr : \
+             Java_Entity_getBar as * mut std :: ffi :: c_void }] ; env . register_native_methods \
+             (class , & methods) . expect (\"Could not register native methods!\") ; } }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn ffi_convert_error_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("i32".into()), vec![]).with_error_return(true));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myfunc \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jint \
+             { roast :: convert :: convert_retval_result_box_dyn_error (& env , \
+             Entity :: myfunc () , roast :: convert :: convert_retval_i32) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_error_return_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new("myfunc", Some("i32".into()), vec![]).with_error_return(true));
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native int myfunc();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_callback_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            Some("i32".into()),
+            vec![DerivedFnArg::Callback {
+                name: "supplier".into(),
+                java_interface: "java.util.function.IntSupplier".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myFunc \
+             (env : roast :: JNIEnv , _class : roast :: JClass , supplier : roast :: JObject) \
+             -> roast :: jint { roast :: convert :: convert_retval_i32 (& env , Entity :: my_func \
+             (roast :: convert :: convert_arg_jcallback_int_supplier (& env , supplier))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn callback_arg_unsupported_interface() {
+        let fns = vec![DerivedFn::new(
+            "my_func",
+            Some("i32".into()),
+            vec![DerivedFnArg::Callback {
+                name: "supplier".into(),
+                java_interface: "java.util.function.LongSupplier".into(),
+            }],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let expected = "compile_error ! (\"Unsupported callback interface \
+             'java.util.function.LongSupplier' of argument 'supplier' in function 'my_func'\") ;";
+        assert_eq!(expected, format!("{}", derived.export_jni_ffi_tokens()));
+        assert!(matches!(
+            derived.export_java_syntax("mylib"),
+            Err(ConversionError::UnsupportedCallback { arg, .. }) if arg == "supplier"
+        ));
+    }
+
+    #[test]
+    fn java_convert_callback_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            Some("i32".into()),
+            vec![DerivedFnArg::Callback {
+                name: "my_supplier".into(),
+                java_interface: "java.util.function.IntSupplier".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native int myFunc(java.util.function.IntSupplier mySupplier);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_lossy_string_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            Some("String".into()),
+            vec![DerivedFnArg::LossyString {
+                name: "input".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_myFunc \
+             (env : roast :: JNIEnv , _class : roast :: JClass , input : roast :: JString) \
+             -> roast :: jstring { roast :: convert :: convert_retval_string (& env , Entity :: \
+             my_func (roast :: convert :: convert_arg_jstring_lossy (& env , input))) }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn convert_lossy_float_arg_value() {
+        let derived = DerivedEntity::new(
+            "Entity",
+            vec![DerivedFn::new(
+                "scale",
+                None,
+                vec![DerivedFnArg::LossyFloat {
+                    name: "factor".into(),
+                }],
+            )],
+        );
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_scale \
+             (env : roast :: JNIEnv , _class : roast :: JClass , factor : roast :: jdouble) \
+             { Entity :: scale (roast :: convert :: convert_arg_jdouble_to_f32_lossy (& env , \
+             factor)) }";
+        assert_eq!(expected, exported);
+        assert!(derived
+            .export_java_syntax("mylib")
+            .unwrap()
+            .contains("public static native void scale(double factor);"));
+    }
+
+    #[test]
+    fn java_convert_lossy_string_arg_value() {
+        let mut fns = vec![];
+        fns.push(DerivedFn::new(
+            "my_func",
+            Some("String".into()),
+            vec![DerivedFnArg::LossyString {
+                name: "my_input".into(),
+            }],
+        ));
+        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"// _class is unused; consider using a plain Java utility class
+public class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	public static native String myFunc(String myInput);
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn ffi_convert_native_handle_arg_value() {
+        let handle = |mutable| DerivedFnArg::NativeHandle {
+            name: "counter".into(),
+            mutable,
+        };
+        let fns = vec![
+            DerivedFn::new("total", Some("i64".into()), vec![handle(false)]),
+            DerivedFn::new("reset", None, vec![handle(true)]),
+        ];
+        let derived = DerivedEntity::new("Counter", fns).with_unsafe_allowed(true);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Counter_total \
+             (env : roast :: JNIEnv , _class : roast :: JClass , counter : roast :: jlong) \
+             -> roast :: jlong { roast :: convert :: convert_retval_i64 (& env , Counter :: \
+             total (unsafe { roast :: convert :: convert_handle_to_ref :: < Counter > \
+             (& env , counter) })) } \
+             # [no_mangle] pub extern \"system\" fn Java_Counter_reset \
+             (env : roast :: JNIEnv , _class : roast :: JClass , counter : roast :: jlong) \
+             { Counter :: reset (unsafe { roast :: convert :: convert_handle_to_mut_ref :: \
+             < Counter > (& env , counter) }) }";
+        assert_eq!(expected, exported);
+
+        let unsafe_reason = derived.fns[1].unsafe_reason();
+        assert_eq!(
+            Some("takes a mutable native handle, which java can alias from multiple threads"),
+            unsafe_reason
+        );
+    }
+
+    #[test]
+    fn ffi_convert_nullable_args() {
+        let arg = |name: &str, ty: &str| DerivedFnArg::Captured {
+            name: name.into(),
+            ty: ty.into(),
+        };
+        let fns = vec![DerivedFn::new(
+            "scaled",
+            Some("Option<f64>".into()),
+            vec![arg("value", "Option<i64>"), arg("label", "Option<String>")],
+        )];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_scaled \
+             (env : roast :: JNIEnv , _class : roast :: JClass , value : roast :: JObject , \
+             label : roast :: JString) -> roast :: jobject { roast :: convert :: \
+             convert_retval_option (& env , Entity :: scaled (roast :: convert :: \
+             convert_arg_jlong_nullable (& env , value) , roast :: convert :: \
+             convert_arg_jstring_nullable (& env , label))) }";
+        assert_eq!(expected, exported);
+
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.contains("public static native Double scaled(Long value, String label);"));
+        assert_eq!(
+            "(Ljava/lang/Long;Ljava/lang/String;)Ljava/lang/Double;",
+            derived.fns[0].jni_descriptor().unwrap()
+        );
     }
 
     #[test]
-    fn func_name_to_java_style() {
-        assert_eq!(
-            String::from("func"),
-            DerivedFn::new("func", None, vec![]).java_name()
-        );
-        assert_eq!(
-            String::from("myFuncName"),
-            DerivedFn::new("my_func_name", None, vec![]).java_name()
-        );
+    fn ffi_convert_enum_as_int() {
+        let direction = || DerivedFnArg::IntEnum {
+            name: "d".into(),
+            ty: "Direction".into(),
+        };
+        let fns = vec![
+            DerivedFn::new("opposite", Some("Direction".into()), vec![direction()])
+                .with_enum_as_int(true),
+            DerivedFn::new("parse", Some("Direction".into()), vec![])
+                .with_error_return(true)
+                .with_enum_as_int(true),
+        ];
+        let derived = DerivedEntity::new("Compass", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Compass_opposite \
+             (env : roast :: JNIEnv , _class : roast :: JClass , d : roast :: jint) \
+             -> roast :: jint { roast :: convert :: convert_retval_i32 (& env , \
+             Into :: < i32 > :: into (Compass :: opposite (Direction :: from (d)))) } \
+             # [no_mangle] pub extern \"system\" fn Java_Compass_parse \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jint \
+             { roast :: convert :: convert_retval_result_box_dyn_error (& env , \
+             Compass :: parse () . map (Into :: < i32 > :: into) , \
+             roast :: convert :: convert_retval_i32) }";
+        assert_eq!(expected, exported);
+
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java
+            .contains("public static native /* Direction */ int opposite(/* Direction */ int d);"));
+        assert_eq!("(I)I", derived.fns[0].jni_descriptor().unwrap());
     }
 
     #[test]
-    fn java_convert_no_methods() {
-        let derived = DerivedEntity::new("Entity", vec![]);
-        let expected = r#"public class Entity {
+    #[should_panic(expected = "#[roast(enum_as_int)] on reset requires an enum return type")]
+    fn enum_as_int_requires_return_type() {
+        DerivedFn::new("reset", None, vec![]).with_enum_as_int(true);
+    }
+
+    #[test]
+    fn java_convert_native_handle_arg_value() {
+        let fns = vec![DerivedFn::new(
+            "add_to",
+            None,
+            vec![
+                DerivedFnArg::NativeHandle {
+                    name: "native_ptr".into(),
+                    mutable: false,
+                },
+                DerivedFnArg::Captured {
+                    name: "amount".into(),
+                    ty: "i32".into(),
+                },
+            ],
+        )];
+        let derived = DerivedEntity::new("Counter", fns).with_utility_class(true);
+        let expected = r#"public final class Counter {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
+	private Counter() {
+	}
+
+	public static native void addTo(long nativePtr, int amount);
+
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        assert_eq!("(JI)V", derived.fns[0].jni_descriptor().unwrap());
     }
 
     #[test]
-    fn ffi_convert_no_methods() {
-        let derived = DerivedEntity::new("Entity", vec![]);
-        let tokens = derived.export_jni_ffi_tokens();
-        let exported = format!("{}", tokens);
-        assert!(exported.is_empty());
+    fn ffi_convert_collected_iterator() {
+        let fns = vec![
+            DerivedFn::new(
+                "range",
+                Some("impl Iterator<Item = i32>".into()),
+                vec![DerivedFnArg::Captured {
+                    name: "end".into(),
+                    ty: "i32".into(),
+                }],
+            )
+            .with_collect(true),
+            DerivedFn::new("bytes", Some("Box<dynIterator<Item=u8>>".into()), vec![])
+                .with_collect(true)
+                .with_error_return(true),
+        ];
+        let derived = DerivedEntity::new("Entity", fns);
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_range \
+             (env : roast :: JNIEnv , _class : roast :: JClass , end : roast :: jint) \
+             -> roast :: jintArray { roast :: convert :: convert_retval_veci32 (& env , \
+             Entity :: range (roast :: convert :: convert_arg_jint (& env , end)) \
+             . collect :: < Vec < i32 > > ()) } \
+             # [no_mangle] pub extern \"system\" fn Java_Entity_bytes \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jbyteArray { \
+             roast :: convert :: convert_retval_result_box_dyn_error (& env , Entity :: bytes () \
+             . map (| values | values . collect :: < Vec < u8 > > ()) , \
+             roast :: convert :: convert_retval_vecu8) }";
+        assert_eq!(expected, exported);
+        assert!(derived
+            .export_java_syntax("mylib")
+            .unwrap()
+            .contains("public static native int[] range(int end);"));
     }
 
     #[test]
-    fn java_convert_static_no_arg_no_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("foobar", None, vec![]));
-        let derived = DerivedEntity::new("Entity", fns);
-
-        let expected = r#"public class Entity {
+    #[should_panic(expected = "#[roast(collect)] on range requires an impl Iterator")]
+    fn collect_requires_iterator() {
+        DerivedFn::new("range", Some("Vec<i32>".into()), vec![]).with_collect(true);
+    }
 
-	static {
-		System.loadLibrary("mylib");
-	}
+    #[test]
+    fn ffi_convert_trait_object_return_value() {
+        let fns = vec![
+            DerivedFn::new("square", Some("Box<dynShape>".into()), vec![])
+                .with_trait_object("com.example.Shape"),
+        ];
+        let derived = DerivedEntity::new("Shapes", fns);
+        assert_eq!(
+            vec![(String::from("Shape"), "com.example.Shape")],
+            derived.trait_objects()
+        );
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Shapes_square \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jlong { \
+             roast :: convert :: convert_retval_trait_object (& env , Shapes :: square ()) }";
+        assert_eq!(expected, exported);
+        assert!(derived
+            .export_java_syntax("mylib")
+            .unwrap()
+            .contains("public static native /* com.example.Shape handle */ long square();"));
+    }
 
-	public static native void foobar();
+    #[test]
+    #[should_panic(
+        expected = "The java class com.example.Plugin of square must be named after \
+                               the trait Shape"
+    )]
+    fn trait_object_named_after_trait() {
+        DerivedFn::new("square", Some("Box<dyn Shape>".into()), vec![])
+            .with_trait_object("com.example.Plugin");
+    }
 
-}
-"#;
-        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    fn trait_object_test_fns() -> Vec<DerivedFn> {
+        vec![
+            DerivedFn::new(
+                "area",
+                Some("f64".into()),
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            ),
+            DerivedFn::new(
+                "set_label",
+                None,
+                vec![
+                    DerivedFnArg::SelfBorrow { mutable: true },
+                    DerivedFnArg::Captured {
+                        name: "label".into(),
+                        ty: "String".into(),
+                    },
+                ],
+            ),
+        ]
     }
 
     #[test]
-    fn ffi_convert_static_no_arg_no_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("foobar", None, vec![]));
-        let derived = DerivedEntity::new("Entity", fns);
+    fn ffi_convert_trait_object_class() {
+        let derived = DerivedEntity::new("Shape", trait_object_test_fns())
+            .with_trait_object("com.my_app.Shape")
+            .with_unsafe_allowed(true);
         let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn \
-                        Java_Entity_foobar ( _env : roast :: JNIEnv , _class : roast :: JClass ) \
-                        { Entity :: foobar ( ) }";
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_com_my_1app_Shape_area \
+             (env : roast :: JNIEnv , _class : roast :: JClass , native_handle : roast :: jlong) \
+             -> roast :: jdouble { roast :: convert :: convert_retval_f64 (& env , Shape :: area \
+             (& * * unsafe { roast :: convert :: convert_handle_to_ref :: < Box < dyn Shape > > \
+             (& env , native_handle) })) } \
+             # [no_mangle] pub extern \"system\" fn Java_com_my_1app_Shape_setLabel \
+             (env : roast :: JNIEnv , _class : roast :: JClass , native_handle : roast :: jlong , \
+             label : roast :: JString) { Shape :: set_label (& mut * * unsafe { roast :: convert \
+             :: convert_handle_to_mut_ref :: < Box < dyn Shape > > (& env , native_handle) } , \
+             roast :: convert :: convert_arg_jstring (& env , label)) } \
+             # [no_mangle] pub extern \"system\" fn Java_com_my_1app_Shape_destroy \
+             (env : roast :: JNIEnv , _class : roast :: JClass , native_handle : roast :: jlong) \
+             { unsafe { roast :: convert :: drop_handle :: < Box < dyn Shape >> \
+             (& env , native_handle) } }";
         assert_eq!(expected, exported);
     }
 
     #[test]
-    fn java_convert_no_arg_no_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foobar",
-            None,
-            vec![DerivedFnArg::SelfBorrow { mutable: false }],
-        ));
-        let derived = DerivedEntity::new("Entity", fns);
+    fn ffi_convert_inner_class() {
+        let derived = DerivedEntity::new(
+            "Stats_v2",
+            vec![DerivedFn::new("count", Some("i32".into()), vec![])],
+        )
+        .with_inner_class_of("Arrays");
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        assert!(exported.contains("fn Java_Arrays_00024Stats_1v2_count "));
+    }
 
-        let expected = r#"public class Entity {
+    #[test]
+    fn java_convert_inner_class() {
+        let inner = DerivedEntity::new(
+            "Stats",
+            vec![DerivedFn::new("sorted", Some("Vec<i32>".into()), vec![])
+                .with_java_collection(true)],
+        )
+        .with_inner_class_of("Arrays")
+        .with_utility_class(true);
+        let derived = DerivedEntity::new(
+            "Arrays",
+            vec![DerivedFn::new("count", Some("i32".into()), vec![])],
+        )
+        .with_inner_class(inner);
+        let expected = r#"import java.util.List;
+
+// _class is unused; consider using a plain Java utility class
+public class Arrays {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public native void foobar();
+	public static native int count();
+
+	public static final class Stats {
+
+		private Stats() {
+		}
+
+		public static native List<Integer> sorted();
+
+	}
 
 }
 "#;
@@ -443,104 +5949,141 @@ mod tests {
     }
 
     #[test]
-    fn ffi_convert_no_arg_no_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foobar",
-            None,
-            vec![DerivedFnArg::SelfBorrow { mutable: false }],
-        ));
-        let derived = DerivedEntity::new("Entity", fns);
-        let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn \
-                        Java_Entity_foobar ( _env : roast :: JNIEnv , _obj : roast :: JObject ) \
-                        { Entity :: foobar ( ) }";
-        assert_eq!(expected, exported);
-    }
-
-    #[test]
-    fn java_convert_static_no_arg_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("foobar", Some("i32".into()), vec![]));
-        let derived = DerivedEntity::new("Entity", fns);
+    fn java_convert_trait_object_class() {
+        let derived = DerivedEntity::new("Shape", trait_object_test_fns())
+            .with_trait_object("com.example.Shape");
+        let expected = r#"package com.example;
 
-        let expected = r#"public class Entity {
+public class Shape {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native int foobar();
+	private long nativeHandle;
+
+	public Shape(long nativeHandle) {
+		this.nativeHandle = nativeHandle;
+	}
+
+	public double area() {
+		return area(nativeHandle);
+	}
+
+	public void setLabel(String label) {
+		setLabel(nativeHandle, label);
+	}
+
+	public void destroy() {
+		if (nativeHandle != 0) {
+			destroy(nativeHandle);
+			nativeHandle = 0;
+		}
+	}
+
+	private static native double area(long nativeHandle);
+
+	private static native void setLabel(long nativeHandle, String label);
+
+	private static native void destroy(long nativeHandle);
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+
+        let destroy = DerivedFn::new(
+            "destroy",
+            None,
+            vec![DerivedFnArg::SelfBorrow { mutable: false }],
+        );
+        let clash = DerivedEntity::new("Shape", vec![destroy]).with_trait_object("Shape");
+        assert_eq!(
+            Err(vec![String::from(
+                "Shape::destroy clashes with the destroy method of the trait object class"
+            )]),
+            clash.validate()
+        );
     }
 
     #[test]
-    fn ffi_convert_static_no_arg_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("foobar", Some("i32".into()), vec![]));
+    fn tuple_return_types() {
+        let func = DerivedFn::new("f", Some("(i32,Vec<u8>)".into()), vec![]);
+        assert_eq!(Some(vec!["i32", "Vec<u8>"]), func.tuple_return_types());
+        assert_eq!(Some("tuple2".into()), func.sanitized_return_type());
+        assert_eq!("fNative", func.jni_method_name());
+        assert_eq!("()[Ljava/lang/Object;", func.jni_descriptor().unwrap());
+
+        let func = DerivedFn::new("f", Some("i32".into()), vec![]);
+        assert_eq!(None, func.tuple_return_types());
+        assert_eq!("f", func.jni_method_name());
+    }
+
+    #[test]
+    fn ffi_convert_tuple_return_value() {
+        let fns = vec![DerivedFn::new(
+            "div_mod",
+            Some("(i32,String)".into()),
+            vec![],
+        )];
         let derived = DerivedEntity::new("Entity", fns);
         let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected =
-            "# [ no_mangle ] pub extern \"system\" fn \
-             Java_Entity_foobar ( env : roast :: JNIEnv , _class : roast :: JClass ) -> \
-             roast :: jint { roast :: convert :: convert_retval_i32 ( & env , Entity :: foobar ( ) ) }";
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_divModNative \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jobjectArray \
+             { roast :: convert :: convert_retval_tuple2 (& env , Entity :: div_mod ()) }";
         assert_eq!(expected, exported);
     }
 
     #[test]
-    fn java_convert_static_arg_no_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foobar",
-            None,
+    fn java_convert_tuple_return_value() {
+        let fns = vec![DerivedFn::new(
+            "div_mod",
+            Some("(i32,String)".into()),
             vec![DerivedFnArg::Captured {
                 name: "a".into(),
-                ty: "i64".into(),
+                ty: "i32".into(),
             }],
-        ));
-        let derived = DerivedEntity::new("Entity", fns);
+        )];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
 
-        let expected = r#"public class Entity {
+        let expected = r#"public final class Entity {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native void foobar(long a);
+	private Entity() {
+	}
+
+	public static Tuple2<Integer, String> divMod(int a) {
+		Object[] result = divModNative(a);
+		return new Tuple2<>((Integer) result[0], (String) result[1]);
+	}
+
+	private static native Object[] divModNative(int a);
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
-    }
 
-    #[test]
-    fn ffi_convert_static_arg_no_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foobar",
-            None,
-            vec![DerivedFnArg::Captured {
-                name: "a".into(),
-                ty: "i64".into(),
-            }],
-        ));
-        let derived = DerivedEntity::new("Entity", fns);
-        let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_foobar \
-             ( env : roast :: JNIEnv , _class : roast :: JClass , a : roast :: jlong ) \
-             { Entity :: foobar ( roast :: convert :: convert_arg_jlong ( & env , a ) ) }";
-        assert_eq!(expected, exported);
+        let expected = "public final class Tuple2<A, B> {
+
+	public final A first;
+	public final B second;
+
+	public Tuple2(A first, B second) {
+		this.first = first;
+		this.second = second;
+	}
+}
+";
+        assert_eq!(Some(expected.into()), derived.export_java_tuple_syntax());
     }
 
     #[test]
-    fn java_convert_static_arg_and_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foobar",
-            Some("bool".into()),
+    fn java_convert_named_tuple_return_value() {
+        let fns = vec![DerivedFn::new(
+            "div",
+            Some("(i32,i32)".into()),
             vec![
                 DerivedFnArg::Captured {
                     name: "a".into(),
@@ -548,339 +6091,836 @@ mod tests {
                 },
                 DerivedFnArg::Captured {
                     name: "b".into(),
-                    ty: "i16".into(),
+                    ty: "i32".into(),
                 },
             ],
-        ));
-        let derived = DerivedEntity::new("Entity", fns);
+        )
+        .with_returns_named("quotient", "remainder")];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
 
-        let expected = r#"public class Entity {
+        let expected = r#"public final class Entity {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native boolean foobar(int a, short b);
+	private Entity() {
+	}
+
+	public static DivResult div(int a, int b) {
+		Object[] result = divNative(a, b);
+		return new DivResult((Integer) result[0], (Integer) result[1]);
+	}
+
+	private static native Object[] divNative(int a, int b);
+
+	public static final class DivResult {
+		public final int quotient;
+		public final int remainder;
+
+		DivResult(int quotient, int remainder) {
+			this.quotient = quotient;
+			this.remainder = remainder;
+		}
+	}
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        assert_eq!(None, derived.export_java_tuple_syntax());
     }
 
     #[test]
-    fn ffi_convert_static_arg_and_ret() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foobar",
-            Some("bool".into()),
-            vec![
-                DerivedFnArg::Captured {
-                    name: "a".into(),
-                    ty: "i32".into(),
-                },
-                DerivedFnArg::Captured {
-                    name: "b".into(),
-                    ty: "i16".into(),
-                },
-            ],
-        ));
+    fn java_convert_unsupported_tuple_return_value() {
+        let fns = vec![DerivedFn::new("f", Some("(i32,i32,i32)".into()), vec![])];
+        let derived = DerivedEntity::new("Entity", fns);
+        assert!(derived.export_java_syntax("mylib").is_err());
+    }
+
+    #[test]
+    fn ffi_convert_option_return_value() {
+        let fns = vec![
+            DerivedFn::new("find", Some("Option<i32>".into()), vec![]),
+            DerivedFn::new("lookup", Some("Option<String>".into()), vec![]).with_use_optional(true),
+        ];
         let derived = DerivedEntity::new("Entity", fns);
         let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_foobar \
-                        ( env : roast :: JNIEnv , _class : roast :: JClass , \
-                        a : roast :: jint , b : roast :: jshort ) -> roast :: jboolean \
-                        { roast :: convert :: convert_retval_bool ( & env , Entity :: foobar \
-                        ( roast :: convert :: convert_arg_jint ( & env , a ) , \
-                        roast :: convert :: convert_arg_jshort ( & env , b ) ) ) }";
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_find \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jobject \
+             { roast :: convert :: convert_retval_option (& env , Entity :: find ()) } \
+             # [no_mangle] pub extern \"system\" fn Java_Entity_lookup \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jobject \
+             { roast :: convert :: convert_retval_option_optional (& env , Entity :: lookup ()) }";
         assert_eq!(expected, exported);
+        assert_eq!(
+            "()Ljava/lang/Integer;",
+            derived.fns[0].jni_descriptor().unwrap()
+        );
+        assert_eq!(
+            "()Ljava/util/Optional;",
+            derived.fns[1].jni_descriptor().unwrap()
+        );
     }
 
     #[test]
-    fn java_convert_static_two_methods() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foo",
-            Some("bool".into()),
-            vec![
-                DerivedFnArg::Captured {
-                    name: "a".into(),
-                    ty: "i32".into(),
-                },
-                DerivedFnArg::Captured {
-                    name: "b".into(),
-                    ty: "i16".into(),
-                },
-            ],
+    fn java_convert_option_return_value() {
+        let fns = vec![
+            DerivedFn::new("find", Some("Option<i32>".into()), vec![]),
+            DerivedFn::new("lookup", Some("Option<String>".into()), vec![]).with_use_optional(true),
+        ];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
+
+        let expected = r#"import java.util.Optional;
+
+public final class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	private Entity() {
+	}
+
+	public static native Integer find();
+
+	public static native Optional<String> lookup();
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn java_convert_visibility() {
+        let this = || DerivedFnArg::SelfBorrow { mutable: false };
+        let fns = vec![
+            DerivedFn::new("area", Some("f64".into()), vec![this()]),
+            DerivedFn::new("count", Some("i32".into()), vec![])
+                .with_visibility(JavaVisibility::Protected),
+            DerivedFn::new("reset", None, vec![this()]).with_visibility(JavaVisibility::Package),
+            DerivedFn::new("scale", Some("f64".into()), vec![])
+                .with_visibility(JavaVisibility::Package),
+            DerivedFn::new("split", Some("(i32, i32)".into()), vec![])
+                .with_visibility(JavaVisibility::Protected),
+        ];
+        let derived = DerivedEntity::new("Entity", fns).with_thread_safe(true);
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.contains("\tpublic synchronized native double area();"));
+        assert!(java.contains("\tprotected static synchronized native int count();"));
+        assert!(java.contains("\tsynchronized native void reset();"));
+        assert!(java.contains("\tstatic synchronized native double scale();"));
+        assert!(java.contains("\tprotected static Tuple2<Integer, Integer> split() {"));
+        assert!(java.contains("\tprivate static synchronized native Object[] splitNative();"));
+
+        let exported = format!("{}", derived.export_jni_ffi_tokens());
+        assert!(exported.contains("fn Java_Entity_count "));
+        assert!(exported.contains("fn Java_Entity_reset "));
+    }
+
+    #[test]
+    fn java_convert_extra_java_body() {
+        let derived = DerivedEntity::new(
+            "Entity",
+            vec![DerivedFn::new("count", Some("i32".into()), vec![])],
+        )
+        .with_utility_class(true)
+        .with_extra_java_body(String::from(
+            "\tpublic static boolean isEmpty() {\n\t\treturn count() == 0;\n\t}\n",
+        ))
+        .with_extra_java_body(String::from(
+            "\tpublic static String describe() {\n\t\treturn count() + \" items\";\n\t}",
         ));
-        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
 
-        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"public final class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	private Entity() {
+	}
+
+	public static native int count();
+
+	public static boolean isEmpty() {
+		return count() == 0;
+	}
+
+	public static String describe() {
+		return count() + " items";
+	}
+
+}
+"#;
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+    }
+
+    #[test]
+    fn java_convert_android_keep() {
+        let fns = || {
+            vec![
+                DerivedFn::new("init", None, vec![]).with_android_keep(true),
+                DerivedFn::new("count", Some("i32".into()), vec![]),
+            ]
+        };
+        let derived = DerivedEntity::new("Entity", fns());
 
-        let expected = r#"public class Entity {
+        let expected = r#"import androidx.annotation.Keep;
+
+// _class is unused; consider using a plain Java utility class
+public class Entity {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native boolean foo(int a, short b);
+	@Keep
+	public static native void init();
 
-	public static native int bar();
+	public static native int count();
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+
+        let kept = DerivedEntity::new("Entity", fns()).with_android_keep(true);
+        let java = kept.export_java_syntax("mylib").unwrap();
+        assert!(java.starts_with("import androidx.annotation.Keep;\n\n"));
+        assert!(java.contains("\n@Keep\npublic class Entity {"));
+        assert!(!java.contains("\t@Keep"));
     }
 
     #[test]
-    fn ffi_convert_static_two_methods() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foo",
-            Some("bool".into()),
+    fn java_convert_check_return_value() {
+        let derived = DerivedEntity::new(
+            "Entity",
             vec![
-                DerivedFnArg::Captured {
-                    name: "a".into(),
-                    ty: "i32".into(),
-                },
-                DerivedFnArg::Captured {
-                    name: "b".into(),
-                    ty: "i16".into(),
-                },
+                DerivedFn::new("count", Some("i32".into()), vec![]).with_must_use(true),
+                DerivedFn::new("reset", None, vec![]),
+                DerivedFn::new("split", Some("(i32, i32)".into()), vec![]).with_must_use(true),
+            ],
+        )
+        .with_utility_class(true)
+        .with_annotation_imports(
+            vec![String::from(
+                "com.google.errorprone.annotations.CheckReturnValue",
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.starts_with("import com.google.errorprone.annotations.CheckReturnValue;\n"));
+        assert!(java.contains("\n\t@CheckReturnValue\n\tpublic static native int count();"));
+        assert!(java.contains("\n\tpublic static native void reset();"));
+        assert!(!java.contains("@CheckReturnValue\n\tpublic static native void reset"));
+        assert!(java
+            .contains("\n\t@CheckReturnValue\n\tpublic static Tuple2<Integer, Integer> split() {"));
+        assert!(java.contains("\n\tprivate static native Object[] splitNative();"));
+        assert_eq!(2, java.matches("@CheckReturnValue").count());
+    }
+
+    #[test]
+    fn java_convert_deprecated() {
+        let derived = DerivedEntity::new(
+            "Entity",
+            vec![
+                DerivedFn::new("count", Some("u64".into()), vec![])
+                    .with_deprecated(Some("use total instead".into())),
+                DerivedFn::new("reset", None, vec![]).with_deprecated(Some(String::new())),
+                DerivedFn::new("split", Some("(i32, i32)".into()), vec![])
+                    .with_deprecated(Some(String::new())),
             ],
+        )
+        .with_utility_class(true);
+
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.contains(
+            "\t * Long.parseUnsignedLong to work with them.\n\t *\n\
+             \t * @deprecated use total instead\n\t */\n\t@Deprecated\n\
+             \tpublic static native /* unsigned */ long count();"
         ));
-        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+        assert!(java.contains("\n\n\t@Deprecated\n\tpublic static native void reset();"));
+        assert!(
+            java.contains("\n\t@Deprecated\n\tpublic static Tuple2<Integer, Integer> split() {")
+        );
+        assert!(java.contains("\n\n\tprivate static native Object[] splitNative();"));
+        assert_eq!(3, java.matches("@Deprecated").count());
+    }
 
+    #[test]
+    fn ffi_convert_deprecated() {
+        let fns = vec![
+            DerivedFn::new("count", Some("i32".into()), vec![])
+                .with_deprecated(Some("use total instead".into())),
+            DerivedFn::new("reset", None, vec![]).with_deprecated(Some(String::new())),
+        ];
         let derived = DerivedEntity::new("Entity", fns);
         let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_foo \
-             ( env : roast :: JNIEnv , _class : roast :: JClass , a : roast :: jint , \
-             b : roast :: jshort ) -> roast :: jboolean { roast :: convert :: convert_retval_bool \
-             ( & env , Entity :: foo ( roast :: convert :: convert_arg_jint ( & env , a ) , \
-             roast :: convert :: convert_arg_jshort ( & env , b ) ) ) } \
-             # [ no_mangle ] pub extern \"system\" fn Java_Entity_bar ( env : roast :: JNIEnv , \
-             _class : roast :: JClass ) -> roast :: jint { roast :: convert :: convert_retval_i32 \
-             ( & env , Entity :: bar ( ) ) }";
+        let expected = "# [deprecated (note = \"use total instead\")] # [allow (deprecated)] \
+             # [no_mangle] pub extern \"system\" fn Java_Entity_count (env : roast :: JNIEnv , \
+             _class : roast :: JClass) -> roast :: jint { roast :: convert :: convert_retval_i32 \
+             (& env , Entity :: count ()) } # [deprecated] # [allow (deprecated)] # [no_mangle] \
+             pub extern \"system\" fn Java_Entity_reset (_env : roast :: JNIEnv , _class : \
+             roast :: JClass) { Entity :: reset () }";
         assert_eq!(expected, exported);
     }
 
     #[test]
-    fn java_convert_mixed_static_nonstatic_two_methods() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "foo",
-            Some("bool".into()),
+    fn swift_syntax() {
+        let fns = vec![
+            DerivedFn::new(
+                "add_int",
+                Some("i32".into()),
+                vec![
+                    DerivedFnArg::Captured {
+                        name: "a".into(),
+                        ty: "i32".into(),
+                    },
+                    DerivedFnArg::Captured {
+                        name: "b".into(),
+                        ty: "i32".into(),
+                    },
+                ],
+            ),
+            DerivedFn::new(
+                "reverse",
+                Some("String".into()),
+                vec![DerivedFnArg::Captured {
+                    name: "input".into(),
+                    ty: "String".into(),
+                }],
+            ),
+            DerivedFn::new("reset", None, vec![]),
+            DerivedFn::new("bytes", Some("Vec<u8>".into()), vec![]),
+            DerivedFn::new(
+                "len",
+                Some("i64".into()),
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            ),
+        ];
+        let derived = DerivedEntity::new("Entity", fns);
+
+        let expected = r#"// Generated by roast, do not edit. Swift support is experimental.
+
+@_silgen_name("roast_swift_Entity_add_int")
+private func roast_swift_Entity_add_int(_ a: Int32, _ b: Int32) -> Int32
+
+@_silgen_name("roast_swift_Entity_reverse")
+private func roast_swift_Entity_reverse(_ input: UnsafePointer<CChar>) -> UnsafeMutablePointer<CChar>
+
+@_silgen_name("roast_swift_Entity_reset")
+private func roast_swift_Entity_reset()
+
+// Returned strings are allocated by rust and have to be freed by
+// roast::swift::free_cstring, which this function calls.
+@_silgen_name("roast_swift_Entity_free_string")
+private func roast_swift_Entity_free_string(_ string: UnsafeMutablePointer<CChar>)
+
+public enum Entity {}
+
+extension Entity {
+
+    public static func addInt(a: Int32, b: Int32) -> Int32 {
+        return roast_swift_Entity_add_int(a, b)
+    }
+
+    public static func reverse(input: String) -> String {
+        let result = input.withCString { input in
+            roast_swift_Entity_reverse(input)
+        }
+        defer { roast_swift_Entity_free_string(result) }
+        return String(cString: result)
+    }
+
+    public static func reset() {
+        roast_swift_Entity_reset()
+    }
+
+}
+"#;
+        assert_eq!(expected, derived.export_swift_syntax());
+
+        let exported = format!("{}", derived.export_swift_ffi_tokens());
+        let expected = "# [cfg (target_vendor = \"apple\")] # [no_mangle] extern \"C\" fn \
+             roast_swift_Entity_add_int (a : i32 , b : i32) -> i32 { Entity :: add_int (a , b) } \
+             # [cfg (target_vendor = \"apple\")] # [no_mangle] unsafe extern \"C\" fn \
+             roast_swift_Entity_reverse (input : * const std :: os :: raw :: c_char) -> * mut \
+             std :: os :: raw :: c_char { roast :: swift :: convert_retval_cstring (Entity :: \
+             reverse (unsafe { roast :: swift :: convert_arg_cstring (input) })) } \
+             # [cfg (target_vendor = \"apple\")] # [no_mangle] extern \"C\" fn \
+             roast_swift_Entity_reset () { Entity :: reset () } \
+             # [cfg (target_vendor = \"apple\")] # [no_mangle] unsafe extern \"C\" fn \
+             roast_swift_Entity_free_string (string : * mut std :: os :: raw :: c_char) { \
+             unsafe { roast :: swift :: free_cstring (string) } }";
+        assert_eq!(expected, exported);
+    }
+
+    #[test]
+    fn java_convert_null_annotations() {
+        let fns = || {
             vec![
-                DerivedFnArg::Captured {
-                    name: "a".into(),
-                    ty: "i32".into(),
-                },
-                DerivedFnArg::Captured {
-                    name: "b".into(),
-                    ty: "i16".into(),
-                },
-                DerivedFnArg::SelfOwned { mutable: true },
-            ],
-        ));
-        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+                DerivedFn::new("find", Some("Option<i32>".into()), vec![]),
+                DerivedFn::new("lookup", Some("Option<String>".into()), vec![])
+                    .with_null_annotation(Some(NullAnnotation::NonNull)),
+                DerivedFn::new("name", Some("String".into()), vec![])
+                    .with_null_annotation(Some(NullAnnotation::NonNull)),
+                DerivedFn::new("count", Some("i32".into()), vec![])
+                    .with_null_annotation(Some(NullAnnotation::NonNull)),
+            ]
+        };
+        let imports = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        let derived = DerivedEntity::new("Entity", fns())
+            .with_utility_class(true)
+            .with_annotation_imports(imports(&[
+                "androidx.annotation.NonNull",
+                "androidx.annotation.Nullable",
+            ]));
 
-        let derived = DerivedEntity::new("Entity", fns);
+        let expected = r#"import androidx.annotation.NonNull;
+import androidx.annotation.Nullable;
 
-        let expected = r#"public class Entity {
+public final class Entity {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public native boolean foo(int a, short b);
+	private Entity() {
+	}
 
-	public static native int bar();
+	public static native @Nullable Integer find();
+
+	public static native @NonNull String lookup();
+
+	public static native @NonNull String name();
+
+	public static native int count();
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+
+        let jetbrains = DerivedEntity::new("Entity", fns())
+            .with_annotation_imports(imports(&["org.jetbrains.annotations.NotNull"]));
+        let java = jetbrains.export_java_syntax("mylib").unwrap();
+        assert!(java.starts_with("import org.jetbrains.annotations.NotNull;\n\n"));
+        assert!(java.contains("public static native Integer find();"));
+        assert!(java.contains("public static native @NotNull String name();"));
     }
 
     #[test]
-    fn ffi_convert_mixed_static_nonstatic_two_methods() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "get_foo_bar",
-            Some("bool".into()),
-            vec![
-                DerivedFnArg::Captured {
-                    name: "a".into(),
-                    ty: "i32".into(),
-                },
-                DerivedFnArg::Captured {
-                    name: "b".into(),
-                    ty: "i16".into(),
-                },
-                DerivedFnArg::SelfOwned { mutable: true },
-            ],
-        ));
-        fns.push(DerivedFn::new("bar", Some("i32".into()), vec![]));
+    fn kotlin_convert_option_return_value() {
+        let fns = vec![DerivedFn::new("find", Some("Option<i32>".into()), vec![])];
+        let derived = DerivedEntity::new("Entity", fns);
+        assert!(derived
+            .export_kotlin_expect_syntax()
+            .unwrap()
+            .contains("fun find(): Int?"));
 
+        let fns = vec![
+            DerivedFn::new("find", Some("Option<i32>".into()), vec![]).with_use_optional(true)
+        ];
         let derived = DerivedEntity::new("Entity", fns);
-        let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_getFooBar \
-             ( env : roast :: JNIEnv , _obj : roast :: JObject , a : roast :: jint , b : \
-             roast :: jshort ) -> roast :: jboolean { roast :: convert :: convert_retval_bool \
-             ( & env , Entity :: get_foo_bar ( roast :: convert :: convert_arg_jint ( & env , a ) \
-             , roast :: convert :: convert_arg_jshort ( & env , b ) ) ) } \
-             # [ no_mangle ] pub extern \"system\" fn Java_Entity_bar ( env : roast :: JNIEnv , \
-             _class : roast :: JClass ) -> roast :: jint { roast :: convert :: convert_retval_i32 \
-             ( & env , Entity :: bar ( ) ) }";
-        assert_eq!(expected, exported);
+        assert!(derived.export_kotlin_expect_syntax().is_err());
     }
 
     #[test]
-    fn ffi_convert_string_return_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("myfunc", Some("String".into()), vec![]));
+    fn ffi_convert_java_collection() {
+        let fns = vec![DerivedFn::new(
+            "sorted",
+            Some("Vec<i32>".into()),
+            vec![DerivedFnArg::Captured {
+                name: "values".into(),
+                ty: "Vec<i32>".into(),
+            }],
+        )
+        .with_java_collection(true)];
         let derived = DerivedEntity::new("Entity", fns);
         let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_myfunc \
-             ( env : roast :: JNIEnv , _class : roast :: JClass ) -> roast :: jstring \
-             { roast :: convert :: convert_retval_string ( & env , Entity :: myfunc ( ) ) }";
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_sorted \
+             (env : roast :: JNIEnv , _class : roast :: JClass , values : roast :: JObject) \
+             -> roast :: jobject { roast :: convert :: convert_retval_list_i32 (& env , \
+             Entity :: sorted (roast :: convert :: convert_arg_jlist_i32 (& env , values))) }";
         assert_eq!(expected, exported);
+        assert_eq!(
+            "(Ljava/util/List;)Ljava/util/List;",
+            derived.fns[0].jni_descriptor().unwrap()
+        );
+        assert!(derived.export_header_syntax().contains(
+            "jobject JNICALL Java_Entity_sorted(JNIEnv *env, jclass clazz, jobject values)"
+        ));
     }
 
     #[test]
-    fn java_convert_string_return_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("myfunc", Some("String".into()), vec![]));
-        let derived = DerivedEntity::new("Entity", fns);
+    fn java_convert_java_collection() {
+        let values = || {
+            vec![DerivedFnArg::Captured {
+                name: "values".into(),
+                ty: "Vec<i32>".into(),
+            }]
+        };
+        let fns = vec![
+            DerivedFn::new("sorted", Some("Vec<i32>".into()), values()).with_java_collection(true),
+            DerivedFn::new("reversed", Some("Vec<i32>".into()), values()),
+        ];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
 
-        let expected = r#"public class Entity {
+        let expected = r#"import java.util.List;
+
+public final class Entity {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native String myfunc();
+	private Entity() {
+	}
+
+	public static native List<Integer> sorted(List<Integer> values);
+
+	public static native int[] reversed(int[] values);
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        assert!(derived
+            .export_kotlin_expect_syntax()
+            .unwrap()
+            .contains("fun sorted(values: List<Int>): List<Int>"));
     }
 
     #[test]
-    fn ffi_convert_string_arg_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "my_func",
-            None,
-            vec![DerivedFnArg::Captured {
-                name: "my_var".into(),
-                ty: "String".into(),
-            }],
-        ));
+    fn ffi_convert_json_return_value() {
+        let fns = vec![
+            DerivedFn::new("origin", Some("Origin".into()), vec![]).with_json(true),
+            DerivedFn::new("pair", Some("(i32,i32)".into()), vec![]).with_json(true),
+        ];
         let derived = DerivedEntity::new("Entity", fns);
         let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_myFunc \
-             ( env : roast :: JNIEnv , _class : roast :: JClass , my_var : roast :: JString ) \
-             { Entity :: my_func ( roast :: convert :: convert_arg_jstring ( & env , my_var ) ) }";
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_origin \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jstring \
+             { roast :: convert :: convert_retval_json (& env , Entity :: origin ()) } \
+             # [no_mangle] pub extern \"system\" fn Java_Entity_pair \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jstring \
+             { roast :: convert :: convert_retval_json (& env , Entity :: pair ()) }";
         assert_eq!(expected, exported);
+        assert_eq!(
+            "()Ljava/lang/String;",
+            derived.fns[0].jni_descriptor().unwrap()
+        );
+        assert_eq!(
+            "()Ljava/lang/String;",
+            derived.fns[1].jni_descriptor().unwrap()
+        );
     }
 
     #[test]
-    fn java_convert_string_arg_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "my_func",
-            None,
-            vec![DerivedFnArg::Captured {
-                name: "my_var".into(),
-                ty: "String".into(),
-            }],
-        ));
-        let derived = DerivedEntity::new("Entity", fns);
-        let expected = r#"public class Entity {
+    fn java_convert_json_return_value() {
+        let fns = vec![
+            DerivedFn::new("origin", Some("Origin".into()), vec![]).with_json(true),
+            DerivedFn::new("origin_or_none", Some("Option<Origin>".into()), vec![]).with_json(true),
+        ];
+        let derived = DerivedEntity::new("Entity", fns).with_utility_class(true);
+
+        let expected = r#"public final class Entity {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native void myFunc(String myVar);
+	private Entity() {
+	}
+
+	public static native String origin();
+
+	public static native String originOrNone();
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
-    }
 
-    #[test]
-    fn ffi_convert_bytearray_arg_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "my_func",
-            None,
-            vec![DerivedFnArg::Captured {
-                name: "my_var".into(),
-                ty: "Vec<u8>".into(),
-            }],
-        ));
+        let fns = vec![DerivedFn::new("origin", Some("Origin".into()), vec![])];
         let derived = DerivedEntity::new("Entity", fns);
-        let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected =
-            "# [ no_mangle ] pub extern \"system\" fn Java_Entity_myFunc \
-             ( env : roast :: JNIEnv , _class : roast :: JClass , my_var : roast :: jbyteArray ) \
-             { Entity :: my_func ( roast :: convert :: convert_arg_jbytearray ( & env , my_var ) ) }";
-        assert_eq!(expected, exported);
+        assert!(derived.export_java_syntax("mylib").is_err());
     }
 
     #[test]
-    fn java_convert_bytearray_arg_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new(
-            "my_func",
-            None,
-            vec![DerivedFnArg::Captured {
-                name: "my_var".into(),
-                ty: "Vec<u8>".into(),
-            }],
-        ));
+    fn java_test_syntax() {
+        let fns = vec![
+            DerivedFn::new(
+                "add_int",
+                Some("i32".into()),
+                vec![
+                    DerivedFnArg::Captured {
+                        name: "a".into(),
+                        ty: "i32".into(),
+                    },
+                    DerivedFnArg::Captured {
+                        name: "data".into(),
+                        ty: "Vec<u8>".into(),
+                    },
+                ],
+            ),
+            DerivedFn::new("find", Some("Option<String>".into()), vec![]),
+            DerivedFn::new(
+                "reset",
+                None,
+                vec![DerivedFnArg::SelfBorrow { mutable: true }],
+            ),
+            DerivedFn::new(
+                "greet",
+                Some("String".into()),
+                vec![DerivedFnArg::LossyString {
+                    name: "name".into(),
+                }],
+            ),
+        ];
         let derived = DerivedEntity::new("Entity", fns);
-        let expected = r#"public class Entity {
 
-	static {
-		System.loadLibrary("mylib");
+        let expected = r#"import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertNotNull;
+
+public class EntityTest {
+
+	@Test
+	public void addInt() {
+		assertNotNull(Entity.addInt(0, new byte[0]));
 	}
 
-	public static native void myFunc(byte[] myVar);
+	@Test
+	public void find() {
+		Entity.find();
+	}
+
+	@Test
+	public void reset() {
+		new Entity().reset();
+	}
+
+	@Test
+	public void greet() {
+		assertNotNull(Entity.greet(""));
+	}
 
 }
 "#;
-        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        assert_eq!(expected, derived.export_java_test_syntax().unwrap());
     }
 
     #[test]
-    fn ffi_convert_bytearray_return_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("myfunc", Some("Vec<u8>".into()), vec![]));
+    fn validate_name_collisions() {
+        let int_arg = || {
+            vec![DerivedFnArg::Captured {
+                name: "x".into(),
+                ty: "i32".into(),
+            }]
+        };
+        let fns = vec![
+            DerivedFn::new("get_x", Some("i32".into()), vec![]),
+            DerivedFn::new("getX", Some("i32".into()), vec![]),
+            DerivedFn::new("set_x", None, int_arg()),
+            DerivedFn::new(
+                "set_x",
+                None,
+                vec![DerivedFnArg::Captured {
+                    name: "x".into(),
+                    ty: "String".into(),
+                }],
+            ),
+            DerivedFn::new("set_y", None, int_arg()),
+        ];
         let derived = DerivedEntity::new("Entity", fns);
-        let exported = format!("{}", derived.export_jni_ffi_tokens());
-        let expected = "# [ no_mangle ] pub extern \"system\" fn Java_Entity_myfunc \
-             ( env : roast :: JNIEnv , _class : roast :: JClass ) -> roast :: jbyteArray \
-             { roast :: convert :: convert_retval_vecu8 ( & env , Entity :: myfunc ( ) ) }";
-        assert_eq!(expected, exported);
+        assert_eq!(
+            Err(vec![
+                String::from("Entity::get_x and Entity::getX both map to the java method getX"),
+                String::from(
+                    "Entity::set_x and Entity::set_x overload the java method setX, which is \
+                     not supported by JNI"
+                ),
+            ]),
+            derived.validate()
+        );
+        assert!(matches!(
+            derived.export_java_syntax("mylib"),
+            Err(ConversionError::InvalidEntity { .. })
+        ));
     }
 
     #[test]
-    fn java_convert_bytearray_return_value() {
-        let mut fns = vec![];
-        fns.push(DerivedFn::new("myfunc", Some("Vec<u8>".into()), vec![]));
+    fn validate_java_keywords() {
+        let fns = vec![
+            DerivedFn::new("new", None, vec![]),
+            DerivedFn::new(
+                "configure",
+                None,
+                vec![DerivedFnArg::Captured {
+                    name: "default".into(),
+                    ty: "bool".into(),
+                }],
+            ),
+            DerivedFn::new("get_class", None, vec![]),
+        ];
         let derived = DerivedEntity::new("Entity", fns);
+        assert_eq!(
+            Err(vec![
+                String::from("Entity::new maps to the java keyword new"),
+                String::from("The argument default of Entity::configure is a java keyword"),
+            ]),
+            derived.validate()
+        );
 
-        let expected = r#"public class Entity {
+        let derived = DerivedEntity::new("Entity", vec![DerivedFn::new("reset", None, vec![])]);
+        assert_eq!(Ok(()), derived.validate());
+    }
+
+    #[test]
+    fn kotlin_signature() {
+        let types = [
+            ("i32", "Int"),
+            ("i64", "Long"),
+            ("f32", "Float"),
+            ("f64", "Double"),
+            ("bool", "Boolean"),
+            ("String", "String"),
+            ("Vec<u8>", "ByteArray"),
+        ];
+        for (rust_type, kotlin_type) in types {
+            let func = DerivedFn::new(
+                "echo",
+                Some(rust_type.into()),
+                vec![DerivedFnArg::Captured {
+                    name: "value".into(),
+                    ty: rust_type.into(),
+                }],
+            );
+            assert_eq!(
+                format!(
+                    "@JvmStatic external fun echo(value: {}): {}",
+                    kotlin_type, kotlin_type
+                ),
+                func.kotlin_signature().unwrap()
+            );
+        }
+
+        let func = DerivedFn::new(
+            "reset_all",
+            None,
+            vec![DerivedFnArg::SelfBorrow { mutable: true }],
+        );
+        assert_eq!("external fun resetAll()", func.kotlin_signature().unwrap());
+
+        let func = DerivedFn::new(
+            "call",
+            None,
+            vec![DerivedFnArg::Callback {
+                name: "supplier".into(),
+                java_interface: "java.util.function.IntSupplier".into(),
+            }],
+        );
+        assert!(func.kotlin_signature().is_err());
+    }
+
+    #[test]
+    fn strict_violations() {
+        let derived = DerivedEntity::new("Entity", vec![]);
+        assert_eq!(
+            vec!["Entity derives RoastExport but has no public methods to export"],
+            derived.strict_violations()
+        );
+
+        let fns = vec![
+            DerivedFn::new("documented", None, vec![]).with_documented(true),
+            DerivedFn::new("undocumented", None, vec![]),
+            DerivedFn::new("camelCase", None, vec![]).with_documented(true),
+        ];
+        let derived = DerivedEntity::new("Entity", fns);
+        assert_eq!(
+            vec![
+                "Entity::undocumented has no doc comment",
+                "Entity::camelCase is not snake case"
+            ],
+            derived.strict_violations()
+        );
+    }
+
+    #[test]
+    fn ffi_convert_strict_mode() {
+        let derived = DerivedEntity::new("Entity", vec![DerivedFn::new("foo", None, vec![])]);
+        let expected = "# [no_mangle] pub extern \"system\" fn Java_Entity_foo \
+             (_env : roast :: JNIEnv , _class : roast :: JClass) { Entity :: foo () }";
+        assert_eq!(expected, format!("{}", derived.export_jni_ffi_tokens()));
+
+        let derived = derived.with_strict_mode(true);
+        let expected = "compile_error ! (\"Entity::foo has no doc comment (#[roast(strict)])\") ; \
+             # [no_mangle] pub extern \"system\" fn Java_Entity_foo \
+             (_env : roast :: JNIEnv , _class : roast :: JClass) { Entity :: foo () }";
+        assert_eq!(expected, format!("{}", derived.export_jni_ffi_tokens()));
+    }
+
+    #[test]
+    fn java_convert_cfg_java() {
+        let fns = vec![
+            DerivedFn::new(
+                "battery_level",
+                Some("i32".into()),
+                vec![DerivedFnArg::SelfBorrow { mutable: false }],
+            )
+            .with_cfg_java("android"),
+            DerivedFn::new("common", None, vec![]),
+            DerivedFn::new(
+                "vibrate",
+                None,
+                vec![DerivedFnArg::Captured {
+                    name: "millis".into(),
+                    ty: "i32".into(),
+                }],
+            )
+            .with_cfg_java("android"),
+        ];
+        let derived = DerivedEntity::new("Device", fns);
+
+        let expected = r#"public class Device {
 
 	static {
 		System.loadLibrary("mylib");
 	}
 
-	public static native byte[] myfunc();
+	// @available android
+	public native int batteryLevel();
+
+	public static native void common();
+
+	// @available android
+	public static native void vibrate(int millis);
 
 }
 "#;
         assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+
+        let expected = r#"public final class DeviceAndroid {
+
+	private DeviceAndroid() {
+	}
+
+	public static boolean isAvailable() {
+		return System.getProperty("java.vendor", "").contains("Android");
+	}
+
+	public static int batteryLevel(Device device) {
+		return device.batteryLevel();
+	}
+
+	public static void vibrate(int millis) {
+		Device.vibrate(millis);
+	}
+
+}
+"#;
+        let classes = derived.export_java_platform_syntax().unwrap();
+        assert_eq!(1, classes.len());
+        assert_eq!("DeviceAndroid", classes[0].0);
+        assert_eq!(expected, classes[0].1);
     }
 }
@@ -2,16 +2,27 @@ extern crate proc_macro;
 
 mod entity;
 
-use entity::{DerivedEntity, DerivedFn, DerivedFnArg};
+use entity::{
+    DerivedConst, DerivedEntity, DerivedEnum, DerivedFn, DerivedFnArg, DerivedVariant,
+    JavaVisibility, LibLoader, MetadataFn, NamingStrategy, NullAnnotation,
+};
 use inflector::Inflector;
+use log::debug;
 use proc_macro::TokenStream;
-use quote::ToTokens;
+use proc_macro2::Span;
+use quote::{quote_spanned, ToTokens};
+use serde_json::json;
+use std::collections::{BTreeSet, HashMap};
 use std::env;
 use std::fs;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
-use syn::{parse_file, DeriveInput, FnArg, ImplItem, Item, Pat, ReturnType, Type, Visibility};
+use std::path::{Path, PathBuf};
+use syn::{
+    parse_file, Attribute, Data, DeriveInput, Expr, ExprLit, Fields, FnArg, GenericArgument,
+    ImplItem, ImplItemMethod, Item, Lit, Meta, NestedMeta, Pat, PathArguments, ReturnType,
+    Signature, TraitItem, TraitItemMethod, Type, TypePath, UnOp, Visibility,
+};
 use walkdir::WalkDir;
 
 #[proc_macro_derive(RoastExport)]
@@ -20,14 +31,317 @@ pub fn roast_export(input: TokenStream) -> TokenStream {
 
     let identifier_name = format!("{}", input.ident).to_pascal_case();
 
-    let methods = methods_for_ident(&identifier_name);
-    let entity = DerivedEntity::new(&identifier_name, methods);
-    let token_stream = entity.export_jni_ffi_tokens();
-    write_java_class(&entity);
+    let crate_root = match crate_root(
+        env::var("CARGO_MANIFEST_DIR").ok(),
+        env::var("OUT_DIR").ok(),
+    ) {
+        Ok(crate_root) => crate_root,
+        Err(message) => {
+            return quote_spanned! {input.ident.span()=> compile_error!(#message); }.into()
+        }
+    };
+    let (files, parse_errors) = source_files(&crate_root, &extra_source_paths());
+    let attrs = struct_attrs_for_ident(&format!("{}", input.ident), &files);
+    if has_roast_flag(&attrs, "strict_parse") {
+        if let Some((path, e)) = parse_errors.first() {
+            panic!("Unable to parse file {:?}: {}", path, e);
+        }
+    }
+    let entity = inner_classes_for_ident(&format!("{}", input.ident), &files)
+        .into_iter()
+        .map(|(inner, inner_attrs)| derived_entity(&inner, &inner_attrs, &files))
+        .fold(
+            derived_entity(&identifier_name, &attrs, &files),
+            DerivedEntity::with_inner_class,
+        );
+    let mut token_stream = entity.export_jni_ffi_tokens();
+    token_stream.extend(metadata_tracking_tokens(metadata_path().as_deref()));
+    // strict mode turns this into a compile error already
+    if has_roast_flag(&attrs, "warn_if_empty")
+        && !has_roast_flag(&attrs, "strict")
+        && entity.is_empty()
+    {
+        token_stream.extend(compile_warning(
+            input.ident.span(),
+            &format!(
+                "{} derives RoastExport but has no public methods to export",
+                entity.name()
+            ),
+        ));
+    }
+    let float_warnings = if has_roast_flag(&attrs, "allow_mixed_floats") {
+        vec![]
+    } else {
+        entity.float_precision_warnings()
+    };
+    for warning in entity
+        .duplicate_warnings()
+        .into_iter()
+        .chain(float_warnings)
+    {
+        token_stream.extend(compile_warning(input.ident.span(), &warning));
+    }
+    if has_roast_flag(&attrs, "thread_safe") {
+        token_stream.extend(entity.generate_thread_safety_assertion());
+    }
+    if has_roast_flag(&attrs, "register_natives") {
+        token_stream.extend(entity.generate_jni_registration_table());
+    }
+    for (trait_name, java_class) in entity.trait_objects() {
+        let trait_entity =
+            DerivedEntity::new(&trait_name, trait_methods_for_ident(&trait_name, &files))
+                .with_trait_object(java_class)
+                .with_unsafe_allowed(has_roast_flag(&attrs, "unsafe_allowed"))
+                .with_lib_loader(entity.lib_loader());
+        let trait_entity = match entity.lib_name() {
+            Some(lib_name) => trait_entity.with_lib_name(lib_name),
+            None => trait_entity,
+        };
+        token_stream.extend(trait_entity.export_jni_ffi_tokens());
+        write_java_class(&trait_entity);
+        write_jni_symbols(&trait_entity);
+    }
+    // inner classes are written as part of their outer class
+    if entity.inner_class_of().is_none() {
+        write_java_class(&entity);
+    }
+    write_c_header(&entity);
+    write_jni_symbols(&entity);
+    write_export_metadata(&entity);
+    token_stream.extend(quote::quote! {
+        const _: Option<&str> = option_env!("ROAST_EXPERIMENTAL_SWIFT");
+    });
+    if experimental_swift() {
+        token_stream.extend(entity.export_swift_ffi_tokens());
+        write_swift_extension(&entity);
+    }
+    if env::var("ROAST_GENERATE_TESTS").as_deref() == Ok("1") {
+        write_java_test_class(&entity);
+    }
+    if has_roast_flag(&attrs, "kotlin_mpp") {
+        write_kotlin_class(&entity);
+    }
     //panic!("{}", token_stream);
     token_stream.into()
 }
 
+/// Exports a rust enum with data variants as a java 17 sealed interface,
+/// with a record for each variant and a `fromJson` factory which reads the
+/// enum serialized by serde, like from a `#[roast(json)]` return value.
+///
+/// Since sealed interfaces and records need java 17, the enum has to opt in
+/// with `#[roast(min_java = 17)]`.
+#[proc_macro_derive(RoastEnumExport)]
+pub fn roast_enum_export(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let span = input.ident.span();
+    let name = input.ident.to_string();
+
+    let crate_root = match crate_root(
+        env::var("CARGO_MANIFEST_DIR").ok(),
+        env::var("OUT_DIR").ok(),
+    ) {
+        Ok(crate_root) => crate_root,
+        Err(message) => return quote_spanned! {span=> compile_error!(#message); }.into(),
+    };
+    let (files, _) = source_files(&crate_root, &extra_source_paths());
+    let attrs = struct_attrs_for_ident(&name, &files);
+    if !matches!(min_java_version(&attrs), Some(version) if version >= 17) {
+        let message = format!(
+            "{} derives RoastEnumExport, which generates a sealed interface and records, \
+             add #[roast(min_java = 17)] to confirm java 17 is available",
+            name
+        );
+        return quote_spanned! {span=> compile_error!(#message); }.into();
+    }
+    let derived = match derived_enum(&input) {
+        Ok(derived) => derived,
+        Err(message) => return quote_spanned! {span=> compile_error!(#message); }.into(),
+    };
+    let derived = match roast_value(&attrs, "package") {
+        Some(package) => derived.with_package(&package),
+        None => derived,
+    };
+
+    let java = match derived.export_java_syntax() {
+        Ok(java) => java,
+        Err(e) => {
+            let message = e.to_string();
+            return quote_spanned! {span=> compile_error!(#message); }.into();
+        }
+    };
+    // the same layout as the struct classes, see `write_java_class`
+    let path = Path::new(&java_output_dir()).join(derived.java_source_path());
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    write_if_changed(&path, &java);
+    TokenStream::new()
+}
+
+/// Builds the enum description from the variants of the derive input, where
+/// only unit variants and variants with named fields are supported.
+fn derived_enum(input: &DeriveInput) -> Result<DerivedEnum, String> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(format!(
+                "RoastEnumExport on {} requires an enum",
+                input.ident
+            ))
+        }
+    };
+    let mut variants = vec![];
+    for variant in &data.variants {
+        let name = variant.ident.to_string();
+        variants.push(match &variant.fields {
+            Fields::Unit => DerivedVariant::unit(&name),
+            Fields::Named(named) => DerivedVariant::new(
+                &name,
+                named
+                    .named
+                    .iter()
+                    .map(|f| {
+                        let ty = match &f.ty {
+                            Type::Path(p) => type_path_to_string(p),
+                            ty => tokens_to_string(ty),
+                        };
+                        (f.ident.as_ref().unwrap().to_string(), ty)
+                    })
+                    .collect(),
+            ),
+            Fields::Unnamed(_) => {
+                return Err(format!(
+                    "RoastEnumExport does not support the tuple variant {}::{}, \
+                     name its fields instead",
+                    input.ident, name
+                ))
+            }
+        });
+    }
+    Ok(DerivedEnum::new(&input.ident.to_string(), variants))
+}
+
+/// Returns the java version of `#[roast(min_java = 17)]`, which may be given
+/// as a number or a string.
+fn min_java_version(attrs: &[Attribute]) -> Option<u32> {
+    roast_attrs(attrs).iter().find_map(|n| match n {
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("min_java") => match &nv.lit {
+            Lit::Int(i) => i.base10_parse().ok(),
+            Lit::Str(s) => s.value().parse().ok(),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Builds the entity of a struct from its methods and constants in the
+/// source files and its `#[roast(...)]` attributes.
+fn derived_entity(
+    identifier_name: &str,
+    attrs: &[Attribute],
+    files: &[(PathBuf, syn::File)],
+) -> DerivedEntity {
+    let filter = method_filter(attrs);
+    let traits = roast_values(attrs, "export_trait_impl");
+    let methods = match metadata_path() {
+        Some(path) => metadata_methods_for_ident(identifier_name, &path, filter.as_ref()),
+        None => methods_for_ident(identifier_name, files, &traits, filter.as_ref()),
+    };
+    let constants = constants_for_ident(identifier_name, files);
+    let annotations = annotation_imports(attrs, &methods);
+    let entity = DerivedEntity::new(identifier_name, methods)
+        .with_constants(constants)
+        .with_annotation_imports(annotations)
+        .with_utility_class(has_roast_flag(attrs, "utility_class"))
+        .with_unsafe_allowed(has_roast_flag(attrs, "unsafe_allowed"))
+        .with_thread_safe(has_roast_flag(attrs, "thread_safe"))
+        .with_strict_mode(has_roast_flag(attrs, "strict"))
+        .with_android_keep(has_roast_flag(attrs, "android_keep"))
+        .with_naming(naming_strategy(attrs, identifier_name))
+        .with_lib_loader(lib_loader(attrs, identifier_name));
+    let entity = roast_values(attrs, "java_body")
+        .into_iter()
+        .fold(entity, DerivedEntity::with_extra_java_body);
+    // inner classes live in the package of their outer class
+    let package = match roast_value(attrs, "inner_class_of") {
+        Some(outer) => roast_value(&struct_attrs_for_ident(&outer, files), "package"),
+        None => roast_value(attrs, "package"),
+    };
+    let entity = match package {
+        Some(package) => entity.with_package(&package),
+        None => entity,
+    };
+    let entity = match roast_value(attrs, "inner_class_of") {
+        Some(outer) => entity.with_inner_class_of(&outer.to_pascal_case()),
+        None => entity,
+    };
+    match roast_value(attrs, "lib_name") {
+        Some(lib_name) => entity.with_lib_name(&lib_name),
+        None => entity,
+    }
+}
+
+/// Marker attribute to customize how a method is exported.
+///
+/// The attribute itself does not modify the annotated item, its arguments
+/// are picked up when the `RoastExport` derive scans the source files. The
+/// only exception are `#[roast(...)]` attributes on method arguments, which
+/// are stripped since rust does not allow macro attributes in that position.
+#[proc_macro_attribute]
+pub fn roast(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    match syn::parse::<ImplItemMethod>(item.clone()) {
+        Ok(mut m) => {
+            for input in m.sig.inputs.iter_mut() {
+                if let FnArg::Typed(a) = input {
+                    a.attrs.retain(|attr| !attr.path.is_ident("roast"));
+                }
+            }
+            m.into_token_stream().into()
+        }
+        Err(_) => item,
+    }
+}
+
+/// Marks a trait whose methods are exported under the java class of every
+/// struct implementing it, like a mixin of native methods.
+///
+/// The attribute doesn't modify the trait, the implementations are picked
+/// up when the `RoastExport` derive scans the source files.
+#[proc_macro_attribute]
+pub fn roast_trait(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+/// Decides which of the public methods of a struct are exported.
+trait RoastFilter {
+    fn should_include(&self, func: &DerivedFn) -> bool;
+}
+
+impl<F: Fn(&DerivedFn) -> bool> RoastFilter for F {
+    fn should_include(&self, func: &DerivedFn) -> bool {
+        self(func)
+    }
+}
+
+/// Only includes methods whose name starts with the given prefix.
+struct PrefixFilter(String);
+
+impl RoastFilter for PrefixFilter {
+    fn should_include(&self, func: &DerivedFn) -> bool {
+        func.name().starts_with(&self.0)
+    }
+}
+
+/// Builds the method filter from the struct attributes.
+///
+/// With `#[roast(filter_prefix = "...")]` only the methods starting with
+/// the prefix are exported, otherwise all public methods are.
+fn method_filter(attrs: &[Attribute]) -> Box<dyn RoastFilter> {
+    match roast_value(attrs, "filter_prefix") {
+        Some(prefix) => Box::new(PrefixFilter(prefix)),
+        None => Box::new(|_: &DerivedFn| true),
+    }
+}
+
 /// Extracts a list of methods for a given identifier.
 ///
 /// This function is hacky, because we don't have stable support
@@ -36,69 +350,371 @@ pub fn roast_export(input: TokenStream) -> TokenStream {
 /// methods. This is error prone and limited, but will work for
 /// now. As soon as we get custom attributes we should switch over
 /// to that since its much better suited for this task.
-fn methods_for_ident(ident: &str) -> Vec<DerivedFn> {
-    let rootdir = env::var("CARGO_MANIFEST_DIR").unwrap();
-
+///
+/// Until then the scan misses everything the compiler expands, like
+/// `include!` of generated files (see `ROAST_EXTRA_RS_FILES`) and impl
+/// blocks produced by other macros. The plan is an attribute macro on the
+/// impl block, like `#[roast::export] impl HelloWorld { ... }`, which
+/// receives the expanded methods directly and makes the file scan
+/// unnecessary.
+///
+/// The methods are sorted by name and source file path, so the generated
+/// code does not depend on the order in which the files are discovered.
+/// Methods defined more than once are exported only once, the definitions
+/// after the first are recorded on it for a compile warning.
+/// Only the public methods accepted by the filter are returned, plus all
+/// methods of the impl blocks for the given traits.
+///
+/// Impls of a `#[roast_trait]` export the methods of the trait definition
+/// instead, which includes the default methods the impl doesn't override.
+fn methods_for_ident(
+    ident: &str,
+    files: &[(PathBuf, syn::File)],
+    traits: &[String],
+    filter: &dyn RoastFilter,
+) -> Vec<DerivedFn> {
+    let roast_traits = roast_trait_methods(files);
     let mut methods = vec![];
-    for entry in WalkDir::new(rootdir) {
-        let e = entry.expect("could not decode entry");
-        if e.file_name().to_str().unwrap().ends_with(".rs") {
-            let mut file = File::open(&e.path())
-                .unwrap_or_else(|_| panic!("Unable to open file at path {:?}", &e.path()));
-            let mut src = String::new();
-            file.read_to_string(&mut src)
-                .unwrap_or_else(|_| panic!("Unable to read file at path {:?}", &e.path()));
-            let syntax = parse_file(&src).expect("Unable to parse file");
-            for item in syntax.items {
-                if let Item::Impl(i) = item {
-                    if let Type::Path(p) = *i.self_ty {
-                        let mut found = false;
-                        for segment in p.path.segments {
-                            let segment_ident = format!("{}", segment.ident);
-                            if ident == segment_ident {
-                                found = true;
+    for (path, syntax) in files {
+        for item in &syntax.items {
+            if let Item::Impl(i) = item {
+                if let Type::Path(p) = &*i.self_ty {
+                    if !path_matches_ident(ident, &p.path) {
+                        continue;
+                    }
+                    let trait_name = i
+                        .trait_
+                        .as_ref()
+                        .and_then(|(_, path, _)| path.segments.last())
+                        .map(|s| s.ident.to_string());
+                    let trait_path = i.trait_.as_ref().map(|(_, path, _)| tokens_to_string(path));
+                    if let Some(trait_methods) = trait_name.and_then(|n| roast_traits.get(&n)) {
+                        for m in trait_methods {
+                            let func = derived_fn(ident, &m.attrs, &m.sig)
+                                .with_trait_path(trait_path.as_deref().unwrap_or_default());
+                            if filter.should_include(&func) {
+                                methods.push((path, func));
                             }
                         }
-                        if !found {
-                            continue;
+                        continue;
+                    }
+                    let trait_exported = match &i.trait_ {
+                        Some((_, path, _)) => {
+                            let name = path.segments.last().map(|s| s.ident.to_string());
+                            traits.iter().any(|t| Some(t) == name.as_ref())
                         }
+                        None => false,
+                    };
+                    if i.trait_.is_some() && !trait_exported {
+                        continue;
+                    }
 
-                        for impl_item in i.items {
-                            if let ImplItem::Method(m) = impl_item {
-                                if let Visibility::Public(_) = m.vis {
-                                    let mut args: Vec<DerivedFnArg> = vec![];
-                                    for arg in m.sig.inputs.iter() {
-                                        if let FnArg::Typed(a) = arg {
-                                            let name = match &*a.pat {
-                                                Pat::Ident(p) => format!("{}", p.ident),
-                                                _ => panic!("unsupported arg signature in name"),
-                                            };
-                                            let ty = match &*a.ty {
-                                                Type::Path(p) => tokens_to_string(
-                                                    &p.path.segments.first().unwrap(),
-                                                ),
-                                                _ => panic!("unsupported arg signature in type"),
-                                            };
-                                            args.push(DerivedFnArg::Captured { name, ty });
-                                        }
-                                        if let FnArg::Receiver(r) = arg {
-                                            if r.reference.is_some() {
-                                                args.push(DerivedFnArg::SelfBorrow {
-                                                    mutable: r.mutability.is_some(),
-                                                })
-                                            } else {
-                                                args.push(DerivedFnArg::SelfOwned {
-                                                    mutable: r.mutability.is_some(),
-                                                })
-                                            }
-                                        }
-                                    }
-                                    methods.push(DerivedFn::new(
-                                        &format!("{}", &m.sig.ident),
-                                        extract_return_type(&m.sig.output),
-                                        args,
-                                    ));
+                    for impl_item in &i.items {
+                        if let ImplItem::Method(m) = impl_item {
+                            if trait_exported || matches!(m.vis, Visibility::Public(_)) {
+                                let mut func = derived_fn(ident, &m.attrs, &m.sig);
+                                if let Some(trait_path) = &trait_path {
+                                    func = func.with_trait_path(trait_path);
                                 }
+                                if filter.should_include(&func) {
+                                    methods.push((path, func));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    methods.sort_by(|(path_a, fn_a), (path_b, fn_b)| {
+        fn_a.name()
+            .cmp(fn_b.name())
+            .then_with(|| path_a.cmp(path_b))
+    });
+    let mut sources = HashMap::new();
+    let mut exported: Vec<DerivedFn> = vec![];
+    for (path, m) in methods {
+        let src = sources
+            .entry(path)
+            .or_insert_with(|| fs::read_to_string(path).ok());
+        let line = src.as_deref().and_then(|src| fn_line(src, m.name()));
+        let m = m.with_source_location(&display_path(path), line);
+        // java has no overloads by rust name, the first definition wins
+        match exported.pop() {
+            Some(first) if first.name() == m.name() => {
+                let location = m.source_location().unwrap_or_default();
+                exported.push(first.with_duplicate_location(&location));
+            }
+            Some(previous) => exported.extend(vec![previous, m]),
+            None => exported.push(m),
+        }
+    }
+    exported
+}
+
+/// Returns the metadata file set with `ROAST_METADATA_PATH`, relative to the
+/// crate root, which replaces the source scan of the methods.
+///
+/// An empty value counts as unset, so `roast analyze` can turn off a path
+/// set in the `[env]` of the cargo config.
+fn metadata_path() -> Option<PathBuf> {
+    let path = env::var("ROAST_METADATA_PATH")
+        .ok()
+        .filter(|path| !path.is_empty())?;
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    Some(Path::new(&manifest_dir).join(path))
+}
+
+/// Makes cargo rebuild the crate when `ROAST_METADATA_PATH` or the file it
+/// points to changes, since neither is tracked when only the derive reads
+/// them.
+fn metadata_tracking_tokens(path: Option<&Path>) -> proc_macro2::TokenStream {
+    let mut tokens = quote::quote! {
+        const _: Option<&str> = option_env!("ROAST_METADATA_PATH");
+    };
+    if let Some(path) = path {
+        let path = path.display().to_string();
+        tokens.extend(quote::quote! { const _: &str = include_str!(#path); });
+    }
+    tokens
+}
+
+/// Reads the methods of a struct from a metadata file instead of scanning
+/// the source files, which avoids the file system walk during expansion.
+///
+/// The file maps the struct names to their methods in the serialized
+/// format of `DerivedFn`, like the `OUT_DIR/roast_export.json` written by a
+/// build with the source scan. The filter still applies and the methods are
+/// sorted by name.
+fn metadata_methods_for_ident(
+    ident: &str,
+    path: &Path,
+    filter: &dyn RoastFilter,
+) -> Vec<DerivedFn> {
+    let json = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Unable to read ROAST_METADATA_PATH {:?}: {}", path, e));
+    let mut metadata: HashMap<String, Vec<MetadataFn>> = serde_json::from_str(&json)
+        .unwrap_or_else(|e| panic!("Unable to parse ROAST_METADATA_PATH {:?}: {}", path, e));
+    let mut methods: Vec<DerivedFn> = metadata
+        .remove(ident)
+        .unwrap_or_else(|| {
+            panic!(
+                "ROAST_METADATA_PATH {:?} has no methods for {}",
+                path, ident
+            )
+        })
+        .into_iter()
+        .map(DerivedFn::from)
+        .filter(|func| filter.should_include(func))
+        .collect();
+    methods.sort_by(|a, b| a.name().cmp(b.name()));
+    methods
+}
+
+/// Finds the line of the definition of the given function in the source
+/// text, since the spans of the parsed files don't carry line numbers.
+///
+/// This is an approximation, the first `fn <name>` wins.
+fn fn_line(src: &str, name: &str) -> Option<u32> {
+    let needle = format!("fn {}", name);
+    let defines = |line: &str| {
+        line.match_indices(&needle).any(|(i, _)| {
+            !line[i + needle.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+        })
+    };
+    src.lines().position(defines).map(|index| index as u32 + 1)
+}
+
+/// Returns the path relative to the crate root for error messages, or the
+/// path itself if it is outside of the crate, like `ROAST_EXTRA_RS_FILES`.
+fn display_path(path: &Path) -> String {
+    env::var("CARGO_MANIFEST_DIR")
+        .ok()
+        .and_then(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+/// Collects the methods of all traits marked with `#[roast_trait]`, by the
+/// name of the trait.
+fn roast_trait_methods(files: &[(PathBuf, syn::File)]) -> HashMap<String, Vec<&TraitItemMethod>> {
+    let mut traits = HashMap::new();
+    for (_path, syntax) in files {
+        for item in &syntax.items {
+            if let Item::Trait(t) = item {
+                let marked = t.attrs.iter().any(|a| {
+                    a.path.segments.last().map(|s| s.ident == "roast_trait") == Some(true)
+                });
+                if !marked {
+                    continue;
+                }
+                let methods = t.items.iter().filter_map(|trait_item| match trait_item {
+                    TraitItem::Method(m) => Some(m),
+                    _ => None,
+                });
+                traits.insert(t.ident.to_string(), methods.collect());
+            }
+        }
+    }
+    traits
+}
+
+/// Builds the description of a method from its attributes and signature,
+/// where the identifier is the struct or trait the method belongs to.
+fn derived_fn(ident: &str, attrs: &[Attribute], sig: &Signature) -> DerivedFn {
+    let mut args: Vec<DerivedFnArg> = vec![];
+    for arg in sig.inputs.iter() {
+        if let FnArg::Typed(a) = arg {
+            let name = match &*a.pat {
+                Pat::Ident(p) => format!("{}", p.ident),
+                _ => panic!("unsupported arg signature in name"),
+            };
+            if let Some(java_interface) = roast_value(&a.attrs, "callback") {
+                args.push(DerivedFnArg::Callback {
+                    name,
+                    java_interface,
+                });
+                continue;
+            }
+            if has_roast_flag(&a.attrs, "lossy") {
+                match &*a.ty {
+                    Type::Path(p) if type_path_to_string(p) == "String" => {
+                        args.push(DerivedFnArg::LossyString { name })
+                    }
+                    Type::Path(p) if type_path_to_string(p) == "f32" => {
+                        args.push(DerivedFnArg::LossyFloat { name })
+                    }
+                    _ => panic!(
+                        "#[roast(lossy)] on {} requires a String or f32 argument",
+                        name
+                    ),
+                }
+                continue;
+            }
+            if has_roast_flag(&a.attrs, "handle") {
+                let mutable = match &*a.ty {
+                    Type::Reference(r) => r.mutability.is_some(),
+                    _ => panic!(
+                        "#[roast(handle)] on {} requires a &{} \
+                         or &mut {} argument",
+                        name, ident, ident
+                    ),
+                };
+                args.push(DerivedFnArg::NativeHandle { name, mutable });
+                continue;
+            }
+            if has_roast_flag(&a.attrs, "enum_as_int") {
+                let ty = match &*a.ty {
+                    Type::Path(p) => type_path_to_string(p),
+                    _ => panic!(
+                        "#[roast(enum_as_int)] on {} requires an enum argument",
+                        name
+                    ),
+                };
+                args.push(DerivedFnArg::IntEnum { name, ty });
+                continue;
+            }
+            let ty = match &*a.ty {
+                Type::Path(p) => type_path_to_string(p),
+                Type::Reference(r) => {
+                    let borrow = if r.mutability.is_some() { "&mut " } else { "&" };
+                    match &*r.elem {
+                        Type::Path(p) => format!("{}{}", borrow, type_path_to_string(p)),
+                        elem => format!("{}{}", borrow, tokens_to_string(elem)),
+                    }
+                }
+                _ => panic!("unsupported arg signature in type"),
+            };
+            args.push(DerivedFnArg::Captured { name, ty });
+        }
+        if let FnArg::Receiver(r) = arg {
+            if r.reference.is_some() {
+                args.push(DerivedFnArg::SelfBorrow {
+                    mutable: r.mutability.is_some(),
+                })
+            } else {
+                args.push(DerivedFnArg::SelfOwned {
+                    mutable: r.mutability.is_some(),
+                })
+            }
+        }
+    }
+    let name = format!("{}", &sig.ident);
+    let error_return = has_roast_flag(attrs, "error_return");
+    let return_type = if error_return {
+        Some(extract_result_ok_type(&sig.output).unwrap_or_else(|| {
+            panic!(
+                "#[roast(error_return)] on {} requires a \
+                 Result<T, Box<dyn Error>> return type",
+                name
+            )
+        }))
+    } else {
+        extract_return_type(&sig.output)
+    };
+    let mut func = DerivedFn::new(&name, return_type, args)
+        .with_error_return(error_return)
+        .with_use_optional(has_roast_flag(attrs, "use_optional"))
+        .with_java_collection(has_roast_flag(attrs, "java_collection"))
+        .with_json(has_roast_flag(attrs, "json"))
+        .with_collect(has_roast_flag(attrs, "collect"))
+        .with_enum_as_int(has_roast_flag(attrs, "enum_as_int"))
+        .with_documented(attrs.iter().any(|a| a.path.is_ident("doc")))
+        .with_android_keep(has_roast_flag(attrs, "android_keep"))
+        .with_must_use(attrs.iter().any(|a| a.path.is_ident("must_use")))
+        .with_deprecated(deprecation_note(attrs))
+        .with_null_annotation(null_annotation(attrs, &name))
+        .with_visibility(java_visibility(attrs, &name));
+    if let Some(java_class) = roast_value(attrs, "trait_object") {
+        func = func.with_trait_object(&java_class);
+    }
+    if let Some(platform) = roast_value(attrs, "cfg_java") {
+        func = func.with_cfg_java(&platform);
+    }
+    let returns_named = roast_list(attrs, "returns_named");
+    if !returns_named.is_empty() {
+        if func.tuple_return_types().is_none() {
+            panic!(
+                "#[roast(returns_named(...))] on {} requires a tuple return type",
+                name
+            );
+        }
+        let field = |key: &str| {
+            returns_named
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.clone())
+                .unwrap_or_else(|| {
+                    panic!(
+                        "#[roast(returns_named(...))] on {} \
+                         requires a {} name",
+                        name, key
+                    )
+                })
+        };
+        func = func.with_returns_named(&field("first"), &field("second"));
+    }
+    func
+}
+
+/// Extracts the methods of a trait definition for a given identifier, which
+/// can be called on a trait object.
+///
+/// Only methods taking `&self` or `&mut self` are returned, sorted by name.
+fn trait_methods_for_ident(ident: &str, files: &[(PathBuf, syn::File)]) -> Vec<DerivedFn> {
+    let mut methods = vec![];
+    for (_path, syntax) in files {
+        for item in &syntax.items {
+            if let Item::Trait(t) = item {
+                if t.ident != ident {
+                    continue;
+                }
+                for trait_item in &t.items {
+                    if let TraitItem::Method(m) = trait_item {
+                        if let Some(FnArg::Receiver(r)) = m.sig.inputs.first() {
+                            if r.reference.is_some() {
+                                methods.push(derived_fn(ident, &m.attrs, &m.sig));
                             }
                         }
                     }
@@ -106,35 +722,664 @@ fn methods_for_ident(ident: &str) -> Vec<DerivedFn> {
             }
         }
     }
+    methods.sort_by(|a, b| a.name().cmp(b.name()));
     methods
 }
 
+/// Reads `#[roast(nullable)]` or `#[roast(nonnull)]` of the method with the
+/// given name, which can't be combined.
+fn null_annotation(attrs: &[Attribute], name: &str) -> Option<NullAnnotation> {
+    match (
+        has_roast_flag(attrs, "nullable"),
+        has_roast_flag(attrs, "nonnull"),
+    ) {
+        (true, true) => panic!(
+            "#[roast(nullable)] and #[roast(nonnull)] on {} exclude each other",
+            name
+        ),
+        (true, false) => Some(NullAnnotation::Nullable),
+        (false, true) => Some(NullAnnotation::NonNull),
+        (false, false) => None,
+    }
+}
+
+/// Returns the note of a `#[deprecated]` attribute, which is empty for the
+/// plain form, or `None` if there is no such attribute.
+///
+/// Both `#[deprecated = "note"]` and `#[deprecated(since = "...", note =
+/// "...")]` are supported.
+fn deprecation_note(attrs: &[Attribute]) -> Option<String> {
+    let attr = attrs.iter().find(|a| a.path.is_ident("deprecated"))?;
+    let note = match attr.parse_meta() {
+        Ok(Meta::NameValue(nv)) => match nv.lit {
+            Lit::Str(s) => Some(s.value()),
+            _ => None,
+        },
+        Ok(Meta::List(l)) => l.nested.into_iter().find_map(|n| match n {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("note") => match nv.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        }),
+        _ => None,
+    };
+    Some(note.unwrap_or_default())
+}
+
+/// Reads `#[roast(visibility = "...")]` of the method with the given name,
+/// which is `public` unless set to `protected` or `package`.
+fn java_visibility(attrs: &[Attribute], name: &str) -> JavaVisibility {
+    match roast_value(attrs, "visibility").as_deref() {
+        None | Some("public") => JavaVisibility::Public,
+        Some("protected") => JavaVisibility::Protected,
+        Some("package") => JavaVisibility::Package,
+        Some(other) => panic!(
+            "#[roast(visibility = \"{}\")] on {} is not one of public, protected or package",
+            other, name
+        ),
+    }
+}
+
+/// Reads `#[roast(naming = "...")]` of the struct with the given name, which
+/// is `camel` unless set to `acronym_camel` or `verbatim`.
+fn naming_strategy(attrs: &[Attribute], name: &str) -> NamingStrategy {
+    match roast_value(attrs, "naming").as_deref() {
+        None | Some("camel") => NamingStrategy::Camel,
+        Some("acronym_camel") => NamingStrategy::AcronymCamel,
+        Some("verbatim") => NamingStrategy::Verbatim,
+        Some(other) => panic!(
+            "#[roast(naming = \"{}\")] on {} is not one of camel, acronym_camel or verbatim",
+            other, name
+        ),
+    }
+}
+
+/// Reads `#[roast(lib_loader = "...")]` of the struct with the given name,
+/// which is `system` unless set to `manual` or `resource`.
+fn lib_loader(attrs: &[Attribute], name: &str) -> LibLoader {
+    match roast_value(attrs, "lib_loader").as_deref() {
+        None | Some("system") => LibLoader::System,
+        Some("manual") => LibLoader::Manual,
+        Some("resource") => LibLoader::Resource,
+        Some(other) => panic!(
+            "#[roast(lib_loader = \"{}\")] on {} is not one of system, manual or resource",
+            other, name
+        ),
+    }
+}
+
+/// Collects the annotations to import for the methods of a struct.
+///
+/// `#[must_use]` methods are annotated with `@CheckReturnValue`, which is
+/// the error prone one unless `#[roast(check_return_value = "javax")]`
+/// picks the JSR 305 one.
+fn annotation_imports(attrs: &[Attribute], methods: &[DerivedFn]) -> BTreeSet<String> {
+    let mut imports = null_annotation_imports(attrs, methods);
+    if methods.iter().any(DerivedFn::must_use) {
+        let import = match roast_value(attrs, "check_return_value").as_deref() {
+            None | Some("errorprone") => "com.google.errorprone.annotations.CheckReturnValue",
+            Some("javax") => "javax.annotation.CheckReturnValue",
+            Some(other) => panic!(
+                "#[roast(check_return_value = \"{}\")] is not supported, \
+                 use errorprone or javax",
+                other
+            ),
+        };
+        imports.insert(String::from(import));
+    }
+    imports
+}
+
+/// Collects the null annotations to import for the methods of a struct.
+///
+/// Annotations are only used if a method is annotated explicitly or the
+/// struct picks the annotations with `#[roast(null_annotations = "...")]`,
+/// which is either `jetbrains` (the default) or `androidx`. Then `Option<T>`
+/// return values are annotated as `@Nullable` as well.
+fn null_annotation_imports(attrs: &[Attribute], methods: &[DerivedFn]) -> BTreeSet<String> {
+    let flavor = roast_value(attrs, "null_annotations");
+    if flavor.is_none() && methods.iter().all(|m| m.null_annotation().is_none()) {
+        return BTreeSet::new();
+    }
+    let (package, non_null) = match flavor.as_deref() {
+        None | Some("jetbrains") => ("org.jetbrains.annotations", "NotNull"),
+        Some("androidx") => ("androidx.annotation", "NonNull"),
+        Some(other) => panic!(
+            "#[roast(null_annotations = \"{}\")] is not supported, use jetbrains or androidx",
+            other
+        ),
+    };
+    methods
+        .iter()
+        .filter_map(DerivedFn::return_null_annotation)
+        .map(|annotation| match annotation {
+            NullAnnotation::Nullable => format!("{}.Nullable", package),
+            NullAnnotation::NonNull => format!("{}.{}", package, non_null),
+        })
+        .collect()
+}
+
+/// Extracts the public associated constants for a given identifier.
+///
+/// Only constants of a supported type with a literal value are returned,
+/// sorted by name like the methods. All other constants are skipped.
+fn constants_for_ident(ident: &str, files: &[(PathBuf, syn::File)]) -> Vec<DerivedConst> {
+    let mut constants = vec![];
+    for (_path, syntax) in files {
+        for item in &syntax.items {
+            if let Item::Impl(i) = item {
+                match &*i.self_ty {
+                    Type::Path(p) if path_matches_ident(ident, &p.path) => (),
+                    _ => continue,
+                }
+                for impl_item in &i.items {
+                    if let ImplItem::Const(c) = impl_item {
+                        if let Visibility::Public(_) = c.vis {
+                            let ty = match &c.ty {
+                                Type::Path(p) => type_path_to_string(p),
+                                Type::Reference(r) => format!("&{}", tokens_to_string(&r.elem)),
+                                _ => continue,
+                            };
+                            if let Some(value) = const_literal(&c.expr) {
+                                let name = format!("{}", c.ident);
+                                let constant = DerivedConst::new(&name, &ty, &value);
+                                if constant.java_type().is_some() {
+                                    constants.push(constant);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    constants.sort_by(|a, b| a.name().cmp(b.name()));
+    constants
+}
+
+/// Turns a literal constant expression into its plain value, like `-42`
+/// for `-42i64`. Strings are returned without quotes or escapes.
+fn const_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(l) => match &l.lit {
+            Lit::Int(i) => Some(i.base10_digits().into()),
+            Lit::Float(f) => Some(f.base10_digits().into()),
+            Lit::Bool(b) => Some(b.value.to_string()),
+            Lit::Str(s) => Some(s.value()),
+            _ => None,
+        },
+        Expr::Unary(u) if matches!(u.op, UnOp::Neg(_)) => match &*u.expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            }) => Some(format!("-{}", i.base10_digits())),
+            Expr::Lit(ExprLit {
+                lit: Lit::Float(f), ..
+            }) => Some(format!("-{}", f.base10_digits())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Checks if any segment of the path is the given identifier.
+fn path_matches_ident(ident: &str, path: &syn::Path) -> bool {
+    path.segments.iter().any(|s| s.ident == ident)
+}
+
+/// Returns the root directory of the crate being compiled, which is scanned
+/// for the methods to export.
+///
+/// Fails if the derive doesn't run inside a cargo build, since without
+/// `OUT_DIR` nothing can be generated, or if `CARGO_MANIFEST_DIR` has no
+/// `Cargo.toml`, so that a misconfigured build doesn't pick up the impl
+/// blocks of an unrelated directory.
+fn crate_root(manifest_dir: Option<String>, out_dir: Option<String>) -> Result<String, String> {
+    let manifest_dir = manifest_dir.ok_or_else(|| {
+        String::from("RoastExport needs CARGO_MANIFEST_DIR, it must be compiled by cargo")
+    })?;
+    if out_dir.is_none() {
+        return Err(String::from(
+            "RoastExport needs OUT_DIR, it must be compiled by cargo with a build script",
+        ));
+    }
+    if !Path::new(&manifest_dir).join("Cargo.toml").is_file() {
+        return Err(format!(
+            "CARGO_MANIFEST_DIR {} has no Cargo.toml, so it is not a rust crate to scan for \
+             RoastExport methods",
+            manifest_dir
+        ));
+    }
+    Ok(manifest_dir)
+}
+
+/// A source file which could not be parsed, together with the parse error.
+type ParseFailure = (PathBuf, syn::Error);
+
+/// Loads and parses all rust source files below the given directory, which
+/// is the root of the crate being compiled, plus the given extra files.
+///
+/// Files which can't be parsed, like ones with macro syntax unknown to syn,
+/// are skipped and returned together with their error, so that
+/// `#[roast(strict_parse)]` can turn them into a build failure.
+fn source_files(
+    rootdir: &str,
+    extra_paths: &[PathBuf],
+) -> (Vec<(PathBuf, syn::File)>, Vec<ParseFailure>) {
+    let paths = WalkDir::new(rootdir)
+        .into_iter()
+        .map(|entry| entry.expect("could not decode entry"))
+        .filter(|e| e.file_name().to_str().unwrap().ends_with(".rs"))
+        .map(|e| e.path().to_path_buf())
+        .chain(extra_paths.iter().cloned());
+    let mut files = vec![];
+    let mut errors = vec![];
+    for path in paths {
+        let mut file =
+            File::open(&path).unwrap_or_else(|_| panic!("Unable to open file at path {:?}", &path));
+        let mut src = String::new();
+        file.read_to_string(&mut src)
+            .unwrap_or_else(|_| panic!("Unable to read file at path {:?}", &path));
+        match parse_file(&src) {
+            Ok(syntax) => files.push((path, syntax)),
+            Err(err) => {
+                debug!("Skipping unparseable file {:?}: {}", path, err);
+                errors.push((path, err));
+            }
+        }
+    }
+    (files, errors)
+}
+
+/// Returns the newline-separated paths of `ROAST_EXTRA_RS_FILES`, which are
+/// scanned in addition to the crate sources.
+///
+/// The scan can't follow `include!` since it only sees the source text, so
+/// files included from outside of the crate, like generated code, need to
+/// be listed here.
+fn extra_source_paths() -> Vec<PathBuf> {
+    env::var("ROAST_EXTRA_RS_FILES")
+        .map(|paths| parse_extra_source_paths(&paths))
+        .unwrap_or_default()
+}
+
+fn parse_extra_source_paths(paths: &str) -> Vec<PathBuf> {
+    paths
+        .lines()
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Finds the structs marked with `#[roast(inner_class_of = "...")]` for the
+/// given outer identifier, together with their attributes.
+///
+/// They are sorted by name, so the nested classes keep their order.
+fn inner_classes_for_ident(
+    ident: &str,
+    files: &[(PathBuf, syn::File)],
+) -> Vec<(String, Vec<Attribute>)> {
+    let mut inner_classes = vec![];
+    for (_path, syntax) in files {
+        for item in &syntax.items {
+            if let Item::Struct(s) = item {
+                if roast_value(&s.attrs, "inner_class_of").as_deref() == Some(ident) {
+                    let name = format!("{}", s.ident);
+                    let attrs = struct_attrs_for_ident(&name, files);
+                    inner_classes.push((name.to_pascal_case(), attrs));
+                }
+            }
+        }
+    }
+    inner_classes.sort_by(|a, b| a.0.cmp(&b.0));
+    inner_classes.dedup_by(|a, b| a.0 == b.0);
+    inner_classes
+}
+
+/// Extracts the attributes of the struct or enum definition for a given
+/// identifier.
+///
+/// Similar to the methods, these are taken from the source files since
+/// the derive input does not contain attribute macros which have already
+/// been expanded, like `#[roast(...)]` when placed above the derive.
+fn struct_attrs_for_ident(ident: &str, files: &[(PathBuf, syn::File)]) -> Vec<Attribute> {
+    let mut attrs = vec![];
+    for (_path, syntax) in files {
+        for item in &syntax.items {
+            match item {
+                Item::Struct(s) if s.ident == ident => attrs.extend(s.attrs.iter().cloned()),
+                Item::Enum(e) if e.ident == ident => attrs.extend(e.attrs.iter().cloned()),
+                _ => (),
+            }
+        }
+    }
+    attrs
+}
+
 fn extract_return_type(ty: &ReturnType) -> Option<String> {
     match ty {
         ReturnType::Default => None,
         ReturnType::Type(_, t) => match **t {
-            Type::Path(ref p) => Some(tokens_to_string(&p.path.segments.first().unwrap())),
+            Type::Path(ref p) => Some(type_path_to_string(p)),
+            Type::Reference(ref r) => match *r.elem {
+                Type::Path(ref p) => Some(format!("&{}", type_path_to_string(p))),
+                ref elem => Some(format!("&{}", tokens_to_string(elem))),
+            },
+            Type::ImplTrait(ref i) => Some(tokens_to_string(i)),
+            Type::Tuple(ref t) if t.elems.is_empty() => None,
+            Type::Tuple(ref t) => Some(format!(
+                "({})",
+                t.elems
+                    .iter()
+                    .map(|e| match e {
+                        Type::Path(p) => type_path_to_string(p),
+                        e => tokens_to_string(e),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )),
             _ => panic!("Unable to extract return type {:?}", ty),
         },
     }
 }
 
+/// Extracts the `T` out of a `Result<T, E>` return type.
+fn extract_result_ok_type(ty: &ReturnType) -> Option<String> {
+    let path = match ty {
+        ReturnType::Type(_, t) => match **t {
+            Type::Path(ref p) => &p.path,
+            _ => return None,
+        },
+        ReturnType::Default => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(a) => match a.args.first()? {
+            GenericArgument::Type(Type::Path(p)) => Some(type_path_to_string(p)),
+            GenericArgument::Type(t) => Some(tokens_to_string(t)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Turns a type path into the string used for the type mappings.
+///
+/// Only the last segment is used, so path-qualified types like
+/// `std::string::String` map the same way as a bare `String`.
+fn type_path_to_string(p: &TypePath) -> String {
+    tokens_to_string(p.path.segments.last().unwrap())
+}
+
+/// Returns all nested items of the `#[roast(...)]` attributes in the list.
+fn roast_attrs(attrs: &[Attribute]) -> Vec<NestedMeta> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("roast"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(l)) => Some(l.nested),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Returns the string value of an item like `#[roast(key = "value")]`.
+fn roast_value(attrs: &[Attribute], key: &str) -> Option<String> {
+    roast_values(attrs, key).into_iter().next()
+}
+
+/// Returns the string values of all items like `#[roast(key = "value")]`,
+/// for keys which may be given more than once.
+fn roast_values(attrs: &[Attribute], key: &str) -> Vec<String> {
+    roast_attrs(attrs)
+        .iter()
+        .filter_map(|n| match n {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident(key) => match &nv.lit {
+                Lit::Str(s) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the string values of a list like `#[roast(key(a = "x", b = "y"))]`.
+fn roast_list(attrs: &[Attribute], key: &str) -> Vec<(String, String)> {
+    roast_attrs(attrs)
+        .iter()
+        .filter_map(|n| match n {
+            NestedMeta::Meta(Meta::List(l)) if l.path.is_ident(key) => Some(l.nested.clone()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|n| match n {
+            NestedMeta::Meta(Meta::NameValue(nv)) => match (nv.path.get_ident(), &nv.lit) {
+                (Some(ident), Lit::Str(s)) => Some((ident.to_string(), s.value())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks if a flag like `#[roast(error_return)]` is present in the list.
+fn has_roast_flag(attrs: &[Attribute], flag: &str) -> bool {
+    roast_attrs(attrs).iter().any(|n| match n {
+        NestedMeta::Meta(Meta::Path(p)) => p.is_ident(flag),
+        _ => false,
+    })
+}
+
+/// Emits a warning with the given message at compile time.
+///
+/// Proc macros have no stable way to emit warnings yet, so this generates
+/// a deprecated item and uses it right away, which makes the compiler print
+/// the deprecation note as a warning. The span needs to point into the user
+/// code, otherwise the compiler suppresses the warning as macro-internal.
+fn compile_warning(span: Span, message: &str) -> proc_macro2::TokenStream {
+    quote_spanned! {span=>
+        const _: () = {
+            #[deprecated(note = #message)]
+            #[allow(non_upper_case_globals)]
+            const roast_warning: () = ();
+            roast_warning
+        };
+    }
+}
+
+/// Returns the name of the native library to load, which is the package name
+/// unless the struct sets `#[roast(lib_name = "...")]`.
+fn native_lib_name(entity: &DerivedEntity) -> String {
+    match entity.lib_name() {
+        Some(lib_name) => lib_name.into(),
+        None => env::var("CARGO_PKG_NAME").unwrap(),
+    }
+}
+
 fn write_java_class(entity: &DerivedEntity) {
-    let out_dir = env::var("OUT_DIR").unwrap();
-    let java_dir = format!("{}/java", &out_dir);
+    let java_dir = java_output_dir();
     if !Path::new(&java_dir).exists() {
-        fs::create_dir(&java_dir).unwrap();
+        fs::create_dir_all(&java_dir).unwrap();
     }
 
-    let package_name = env::var("CARGO_PKG_NAME").unwrap();
-    let exported = match entity.export_java_syntax(&package_name) {
+    let exported = match entity.export_java_syntax(&native_lib_name(entity)) {
         Ok(p) => p,
         Err(e) => panic!("{}", e),
     };
-    let path = format!("{}/{}.java", java_dir, entity.name());
+    // the sources follow the directory layout of their package
+    let write = |class_name: &str, content: &str| {
+        let path = Path::new(&java_dir).join(entity.java_source_path(class_name));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_if_changed(&path, content);
+    };
+    write(entity.java_class_name(), &exported);
+
+    let platform_classes = match entity.export_java_platform_syntax() {
+        Ok(p) => p,
+        Err(e) => panic!("{}", e),
+    };
+    for (class_name, content) in platform_classes {
+        write(&class_name, &content);
+    }
+
+    if let Some(tuple) = entity.export_java_tuple_syntax() {
+        write("Tuple2", &tuple);
+    }
+    if let Some(loader) = entity.export_java_native_lib_loader_syntax() {
+        write("NativeLibLoader", &loader);
+    }
+}
+
+/// Writes the file unless it already has the given content.
+///
+/// The build script watches a `ROAST_JAVA_OUTPUT_DIR` inside the crate, so
+/// rewriting unchanged classes on every build would trigger the next one.
+fn write_if_changed(path: &Path, content: &str) {
+    if fs::read_to_string(path).ok().as_deref() != Some(content) {
+        fs::write(path, content.as_bytes()).unwrap();
+    }
+}
+
+/// The directory the java classes are written to, `ROAST_JAVA_OUTPUT_DIR`
+/// if the build script set one and `OUT_DIR/java` otherwise.
+fn java_output_dir() -> String {
+    env::var("ROAST_JAVA_OUTPUT_DIR")
+        .unwrap_or_else(|_| format!("{}/java", env::var("OUT_DIR").unwrap()))
+}
+
+/// Writes the JUnit 5 test stubs into `OUT_DIR/java_tests`, from where they
+/// can be copied into the test suite of the java project.
+fn write_java_test_class(entity: &DerivedEntity) {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let test_dir = format!("{}/java_tests", &out_dir);
+    fs::create_dir_all(&test_dir).unwrap();
+    let exported = match entity.export_java_test_syntax() {
+        Ok(p) => p,
+        Err(e) => panic!("{}", e),
+    };
+    let path =
+        Path::new(&test_dir).join(entity.java_source_path(&format!("{}Test", entity.name())));
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
     fs::write(&path, exported.as_bytes()).unwrap();
 }
 
+/// Writes the C header declaring the JNI functions into `OUT_DIR/c`, where
+/// `roast build --with-c-header` picks it up.
+fn write_c_header(entity: &DerivedEntity) {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let c_dir = format!("{}/c", &out_dir);
+    fs::create_dir_all(&c_dir).unwrap();
+    let path = format!("{}/{}.h", c_dir, entity.name());
+    fs::write(&path, entity.export_header_syntax().as_bytes()).unwrap();
+}
+
+/// Returns true if the experimental swift bindings are generated, which
+/// `roast build --experimental-swift` turns on with
+/// `ROAST_EXPERIMENTAL_SWIFT=1`.
+///
+/// The derive output tracks the variable with `option_env!`, so changing it
+/// rebuilds the crate.
+fn experimental_swift() -> bool {
+    env::var("ROAST_EXPERIMENTAL_SWIFT").as_deref() == Ok("1")
+}
+
+/// Writes the experimental swift extension into `OUT_DIR/swift`, where
+/// `roast build --experimental-swift` picks it up.
+fn write_swift_extension(entity: &DerivedEntity) {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let swift_dir = format!("{}/swift", &out_dir);
+    fs::create_dir_all(&swift_dir).unwrap();
+    let path = format!("{}/{}.swift", swift_dir, entity.name());
+    fs::write(&path, entity.export_swift_syntax().as_bytes()).unwrap();
+}
+
+/// Merges the methods of the entity into `OUT_DIR/roast_export.json`, which
+/// can be handed to later builds with `ROAST_METADATA_PATH`.
+fn write_export_metadata(entity: &DerivedEntity) {
+    let path = format!("{}/roast_export.json", env::var("OUT_DIR").unwrap());
+    let mut metadata: serde_json::Map<String, serde_json::Value> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    metadata.extend(entity.export_metadata());
+    fs::write(&path, serde_json::to_string_pretty(&metadata).unwrap()).unwrap();
+}
+
+/// Writes the JNI symbols of the entity as json into `OUT_DIR/symbols`, from
+/// where `roast build` collects them into `roast-symbols.json`.
+fn write_jni_symbols(entity: &DerivedEntity) {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let symbols_dir = format!("{}/symbols", &out_dir);
+    fs::create_dir_all(&symbols_dir).unwrap();
+    let path = format!("{}/{}.json", symbols_dir, entity.name());
+    fs::write(&path, jni_symbols_json(entity).to_string()).unwrap();
+}
+
+/// Converts the JNI symbols of the entity into a json array.
+fn jni_symbols_json(entity: &DerivedEntity) -> serde_json::Value {
+    entity
+        .jni_symbols()
+        .into_iter()
+        .map(|s| {
+            json!({
+                "symbol": s.symbol,
+                "java_class": s.java_class,
+                "java_method": s.java_method,
+                "descriptor": s.descriptor,
+                "rust_function": s.rust_function,
+            })
+        })
+        .collect()
+}
+
+/// Writes the kotlin multiplatform `expect` and `actual` declarations into
+/// the `commonMain` and `jvmMain` directories of `OUT_DIR/kotlin`.
+///
+/// If the entity has getter and setter pairs, a data class holding their
+/// values is written to `commonMain` as well, and the `NativeLibLoader` of
+/// `#[roast(lib_loader = "resource")]` to `jvmMain`.
+fn write_kotlin_class(entity: &DerivedEntity) {
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let expect = match entity.export_kotlin_expect_syntax() {
+        Ok(p) => p,
+        Err(e) => panic!("{}", e),
+    };
+    let actual = match entity.export_kotlin_actual_syntax(&native_lib_name(entity)) {
+        Ok(p) => p,
+        Err(e) => panic!("{}", e),
+    };
+
+    for (source_set, content) in &[("commonMain", expect), ("jvmMain", actual)] {
+        let kotlin_dir = format!("{}/kotlin/{}", &out_dir, source_set);
+        fs::create_dir_all(&kotlin_dir).unwrap();
+        let path = format!("{}/{}.kt", kotlin_dir, entity.name());
+        fs::write(&path, content.as_bytes()).unwrap();
+    }
+
+    match entity.export_kotlin_data_class_syntax() {
+        Ok(Some(data_class)) => {
+            let path = format!("{}/kotlin/commonMain/{}Data.kt", &out_dir, entity.name());
+            fs::write(&path, data_class.as_bytes()).unwrap();
+        }
+        Ok(None) => (),
+        Err(e) => panic!("{}", e),
+    }
+
+    // the actual object loads the library with the java class as well
+    if let Some(loader) = entity.export_java_native_lib_loader_syntax() {
+        let path = Path::new(&out_dir)
+            .join("kotlin/jvmMain")
+            .join(entity.java_source_path("NativeLibLoader"));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, loader.as_bytes()).unwrap();
+    }
+}
+
 /// Helper method which turns everything that can be converted into tokens into a String.
 ///
 /// Note that it tries to be semi-intelling on removing whitespace so the output actually
@@ -144,3 +1389,754 @@ fn tokens_to_string<I: ToTokens>(input: &I) -> String {
     input.to_tokens(&mut ts);
     format!("{}", ts).replace(' ', "")
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn derive_from_source(src: &str) -> DerivedEntity {
+        let files = vec![(PathBuf::from("lib.rs"), parse_file(src).unwrap())];
+        DerivedEntity::new(
+            "Entity",
+            methods_for_ident("Entity", &files, &[], &|_: &DerivedFn| true),
+        )
+        .with_constants(constants_for_ident("Entity", &files))
+    }
+
+    #[test]
+    fn path_qualified_types() {
+        let bare =
+            derive_from_source("impl Entity { pub fn foo(a: String, b: Vec<u8>) -> String { a } }");
+        let qualified = derive_from_source(
+            "impl Entity { pub fn foo(a: std::string::String, b: std::vec::Vec<u8>) \
+             -> std::string::String { a } }",
+        );
+        assert_eq!(
+            bare.export_java_syntax("mylib").unwrap(),
+            qualified.export_java_syntax("mylib").unwrap()
+        );
+        assert_eq!(
+            format!("{}", bare.export_jni_ffi_tokens()),
+            format!("{}", qualified.export_jni_ffi_tokens())
+        );
+    }
+
+    #[test]
+    fn str_return_types() {
+        let derived = derive_from_source(
+            "impl Entity { pub fn greeting() -> &'static str { \"Hello\" } \
+             pub fn trimmed(input: &str) -> &str { input.trim() } }",
+        );
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.contains("public static native String greeting();"));
+        assert!(java.contains("public static native String trimmed(String input);"));
+        assert_eq!(
+            "# [no_mangle] pub extern \"system\" fn Java_Entity_greeting \
+             (env : roast :: JNIEnv , _class : roast :: JClass) -> roast :: jstring { \
+             roast :: convert :: convert_retval_str (& env , Entity :: greeting ()) } \
+             # [no_mangle] pub extern \"system\" fn Java_Entity_trimmed \
+             (env : roast :: JNIEnv , _class : roast :: JClass , input : roast :: JString) \
+             -> roast :: jstring { roast :: convert :: convert_retval_str (& env , \
+             Entity :: trimmed (roast :: convert :: convert_arg_jstring (& env , input) \
+             . as_str ())) }",
+            format!("{}", derived.export_jni_ffi_tokens())
+        );
+    }
+
+    #[test]
+    fn methods_sorted_independent_of_file_order() {
+        let a = (
+            PathBuf::from("src/a.rs"),
+            parse_file("impl Entity { pub fn foo() {} pub fn bar(a: i32) {} }").unwrap(),
+        );
+        let b = (
+            PathBuf::from("src/b.rs"),
+            parse_file("impl Entity { pub fn baz() -> i32 { 1 } pub fn bar(b: bool) {} }").unwrap(),
+        );
+
+        let all = |_: &DerivedFn| true;
+        let forward = methods_for_ident("Entity", &[a.clone(), b.clone()], &[], &all);
+        let backward = methods_for_ident("Entity", &[b, a], &[], &all);
+        assert_eq!(format!("{:?}", forward), format!("{:?}", backward));
+
+        let names: Vec<&str> = forward.iter().map(|m| m.name()).collect();
+        assert_eq!(vec!["bar", "baz", "foo"], names);
+    }
+
+    #[test]
+    fn enum_variants() {
+        let input: DeriveInput =
+            syn::parse_str("#[roast(min_java = 17)] enum Shape { Circle { radius: f64 }, Empty }")
+                .unwrap();
+        assert_eq!(Some(17), min_java_version(&input.attrs));
+        assert!(derived_enum(&input).is_ok());
+
+        let tuple: DeriveInput = syn::parse_str("enum Shape { Circle(f64) }").unwrap();
+        assert_eq!(None, min_java_version(&tuple.attrs));
+        assert_eq!(
+            "RoastEnumExport does not support the tuple variant Shape::Circle, name its fields \
+             instead",
+            derived_enum(&tuple).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn methods_from_metadata_file() {
+        let files = vec![(
+            PathBuf::from("src/lib.rs"),
+            parse_file(
+                "impl Entity { pub fn add(a: i32, b: i32) -> i32 { a + b } \
+                 #[roast(error_return)] pub fn parse(s: &str) -> Result<u64, Box<dyn Error>> {} }",
+            )
+            .unwrap(),
+        )];
+        let all = |_: &DerivedFn| true;
+        let scanned = DerivedEntity::new("Entity", methods_for_ident("Entity", &files, &[], &all));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("roast_export.json");
+        fs::write(
+            &path,
+            serde_json::to_string(&scanned.export_metadata()).unwrap(),
+        )
+        .unwrap();
+        let read = DerivedEntity::new("Entity", metadata_methods_for_ident("Entity", &path, &all));
+        assert_eq!(
+            format!("{}", scanned.export_jni_ffi_tokens()),
+            format!("{}", read.export_jni_ffi_tokens())
+        );
+
+        // everything but the name is optional
+        fs::write(
+            &path,
+            r#"{"Entity": [{"name": "add", "return_type": "i32", "args": [
+                {"Captured": {"name": "a", "ty": "i32"}},
+                {"Captured": {"name": "b", "ty": "i32"}}
+            ]}]}"#,
+        )
+        .unwrap();
+        let methods = metadata_methods_for_ident("Entity", &path, &all);
+        assert_eq!(1, methods.len());
+        assert_eq!(
+            format!(
+                "{}",
+                derive_from_source("impl Entity { pub fn add(a: i32, b: i32) -> i32 { a + b } }")
+                    .export_jni_ffi_tokens()
+            ),
+            format!(
+                "{}",
+                DerivedEntity::new("Entity", methods).export_jni_ffi_tokens()
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "missing field `name`")]
+    fn metadata_file_requires_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("roast_export.json");
+        fs::write(&path, r#"{"Entity": [{"return_type": "i32"}]}"#).unwrap();
+        metadata_methods_for_ident("Entity", &path, &|_: &DerivedFn| true);
+    }
+
+    #[test]
+    fn metadata_file_tracked() {
+        assert_eq!(
+            "const _ : Option < & str > = option_env ! (\"ROAST_METADATA_PATH\") ;",
+            metadata_tracking_tokens(None).to_string()
+        );
+        assert!(
+            metadata_tracking_tokens(Some(Path::new("/crate/roast_export.json")))
+                .to_string()
+                .ends_with("const _ : & str = include_str ! (\"/crate/roast_export.json\") ;")
+        );
+    }
+
+    #[test]
+    fn crate_root_requires_cargo_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_dir = dir.path().display().to_string();
+        let out_dir = Some(String::from("/target/out"));
+
+        let missing_manifest = crate_root(Some(manifest_dir.clone()), out_dir.clone());
+        assert!(missing_manifest.unwrap_err().contains("has no Cargo.toml"));
+        assert!(crate_root(None, out_dir.clone()).is_err());
+
+        fs::write(dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+        assert!(crate_root(Some(manifest_dir.clone()), None)
+            .unwrap_err()
+            .contains("OUT_DIR"));
+        assert_eq!(
+            Ok(manifest_dir.clone()),
+            crate_root(Some(manifest_dir), out_dir)
+        );
+    }
+
+    #[test]
+    fn write_if_changed_keeps_unchanged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Entity.java");
+        write_if_changed(&path, "class Entity {}");
+        let past = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+
+        write_if_changed(&path, "class Entity {}");
+        assert_eq!(past, fs::metadata(&path).unwrap().modified().unwrap());
+        write_if_changed(&path, "class Entity { int x; }");
+        assert_eq!(
+            "class Entity { int x; }",
+            fs::read_to_string(&path).unwrap()
+        );
+        assert_ne!(past, fs::metadata(&path).unwrap().modified().unwrap());
+    }
+
+    #[test]
+    fn duplicate_methods_keep_first() {
+        let files = vec![
+            (
+                PathBuf::from("src/b.rs"),
+                parse_file("impl Entity { pub fn bar(b: bool) {} }").unwrap(),
+            ),
+            (
+                PathBuf::from("src/a.rs"),
+                parse_file("impl Entity { pub fn bar(a: i32) {} pub fn foo() {} }").unwrap(),
+            ),
+        ];
+        let methods = methods_for_ident("Entity", &files, &[], &|_: &DerivedFn| true);
+        assert_eq!(2, methods.len());
+        assert_eq!(Some(String::from("src/a.rs")), methods[0].source_location());
+
+        let entity = DerivedEntity::new("Entity", methods);
+        assert_eq!(
+            vec![String::from(
+                "Entity::bar is defined more than once, at src/a.rs and src/b.rs, \
+                 only the first is exported"
+            )],
+            entity.duplicate_warnings()
+        );
+    }
+
+    #[test]
+    fn methods_filtered_by_prefix() {
+        let files = vec![(
+            PathBuf::from("lib.rs"),
+            parse_file(
+                "#[roast(filter_prefix = \"api_\")] struct Entity {} \
+                 impl Entity { pub fn api_foo() {} pub fn helper() {} pub fn api_bar() {} }",
+            )
+            .unwrap(),
+        )];
+        let attrs = struct_attrs_for_ident("Entity", &files);
+        let methods = methods_for_ident("Entity", &files, &[], method_filter(&attrs).as_ref());
+
+        let names: Vec<&str> = methods.iter().map(|m| m.name()).collect();
+        assert_eq!(vec!["api_bar", "api_foo"], names);
+    }
+
+    #[test]
+    fn constants_exported() {
+        let derived = derive_from_source(
+            "impl Entity { \
+             pub const MAX: i32 = 1_000; \
+             pub const MIN: i64 = -5i64; \
+             pub const RATIO: f64 = 0.5; \
+             pub const ENABLED: bool = true; \
+             pub const NAME: &'static str = \"say \\\"hi\\\"\"; \
+             pub const COMPUTED: i32 = 1 + 2; \
+             pub const UNSUPPORTED: u64 = 1; \
+             const PRIVATE: i32 = 1; \
+             }",
+        );
+        let expected = "public class Entity {
+
+	public static final boolean ENABLED = true;
+	public static final int MAX = 1000;
+	public static final long MIN = -5L;
+	public static final String NAME = \"say \\\"hi\\\"\";
+	public static final double RATIO = 0.5;
+
+	static {
+		System.loadLibrary(\"mylib\");
+	}
+
+}
+";
+        assert_eq!(expected, derived.export_java_syntax("mylib").unwrap());
+        assert_eq!(
+            "const _ : () = { let _ = Entity :: ENABLED ; let _ = Entity :: MAX ; \
+             let _ = Entity :: MIN ; let _ = Entity :: NAME ; let _ = Entity :: RATIO ; } ;",
+            format!("{}", derived.export_jni_ffi_tokens())
+        );
+    }
+
+    #[test]
+    fn trait_impl_methods_exported() {
+        let files = vec![(
+            PathBuf::from("lib.rs"),
+            parse_file(
+                "#[roast(export_trait_impl = \"Computable\")] struct Calculator {} \
+                 impl Calculator { pub fn reset() {} } \
+                 impl Computable for Calculator { fn compute(&self, n: i32) -> i64 { 0 } } \
+                 impl Debug for Calculator { fn fmt(&self) {} }",
+            )
+            .unwrap(),
+        )];
+        let attrs = struct_attrs_for_ident("Calculator", &files);
+        let traits = roast_values(&attrs, "export_trait_impl");
+        let methods = methods_for_ident("Calculator", &files, &traits, &|_: &DerivedFn| true);
+
+        let names: Vec<&str> = methods.iter().map(|m| m.name()).collect();
+        assert_eq!(vec!["compute", "reset"], names);
+        let derived = DerivedEntity::new("Calculator", methods);
+        let java = derived.export_java_syntax("mylib").unwrap();
+        assert!(java.contains("public static native long compute(long handle, int n);"));
+        let ffi = format!("{}", derived.export_jni_ffi_tokens());
+        assert!(ffi.contains(
+            "< Calculator as Computable > :: compute (unsafe { roast :: convert :: \
+             convert_handle_to_ref :: < Calculator > (& env , handle) } , roast :: convert :: \
+             convert_arg_jint (& env , n))"
+        ));
+    }
+
+    #[test]
+    fn unsupported_type_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        fs::write(
+            &path,
+            "pub struct Entity {}\n\
+             \n\
+             impl Entity {\n\
+             \x20   pub fn get() -> i32 { 0 }\n\
+             \x20   pub fn get_value() -> MyStruct { MyStruct }\n\
+             }\n",
+        )
+        .unwrap();
+        let (files, _) = source_files(dir.path().to_str().unwrap(), &[]);
+        let methods = methods_for_ident("Entity", &files, &[], &|_: &DerivedFn| true);
+        let error = DerivedEntity::new("Entity", methods)
+            .export_java_syntax("mylib")
+            .unwrap_err();
+        assert_eq!(
+            format!(
+                "Unsupported return type 'MyStruct' at {}:5 in function 'get_value'",
+                path.display()
+            ),
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn jni_symbols_as_json() {
+        let entity = DerivedEntity::new(
+            "Primitive",
+            vec![DerivedFn::new("is_even", Some("bool".into()), vec![])],
+        );
+        assert_eq!(
+            json!([{
+                "symbol": "Java_Primitive_isEven",
+                "java_class": "Primitive",
+                "java_method": "isEven",
+                "descriptor": "()Z",
+                "rust_function": "Primitive::is_even",
+            }]),
+            jni_symbols_json(&entity)
+        );
+    }
+
+    #[test]
+    fn fn_line_found() {
+        let src = "fn get_value_or() {}\nfn get_value() {}\n";
+        assert_eq!(Some(2), fn_line(src, "get_value"));
+        assert_eq!(Some(1), fn_line(src, "get_value_or"));
+        assert_eq!(None, fn_line(src, "get"));
+    }
+
+    #[test]
+    fn roast_trait_methods_exported() {
+        let files = vec![(
+            PathBuf::from("lib.rs"),
+            parse_file(
+                "#[roast_trait] pub trait Versioned { fn version() -> i32; \
+                 fn version_name() -> String { String::new() } } \
+                 pub trait Hidden { fn hidden() -> i32; } \
+                 struct Calculator {} \
+                 impl Versioned for Calculator { fn version() -> i32 { 2 } \
+                 fn helper(value: u8) -> u8 { value } } \
+                 impl Hidden for Calculator { fn hidden() -> i32 { 0 } }",
+            )
+            .unwrap(),
+        )];
+        let methods = methods_for_ident("Calculator", &files, &[], &|_: &DerivedFn| true);
+
+        let names: Vec<&str> = methods.iter().map(|m| m.name()).collect();
+        assert_eq!(vec!["version", "version_name"], names);
+    }
+
+    #[test]
+    fn roast_trait_in_other_module() {
+        let files = vec![
+            (
+                PathBuf::from("lib.rs"),
+                parse_file(
+                    "struct Calculator {} \
+                     impl ops::Versioned for Calculator { fn version() -> i32 { 2 } }",
+                )
+                .unwrap(),
+            ),
+            (
+                PathBuf::from("ops.rs"),
+                parse_file("#[roast_trait] pub trait Versioned { fn version() -> i32; }").unwrap(),
+            ),
+        ];
+        let methods = methods_for_ident("Calculator", &files, &[], &|_: &DerivedFn| true);
+
+        let ffi = format!(
+            "{}",
+            DerivedEntity::new("Calculator", methods).export_jni_ffi_tokens()
+        );
+        assert!(ffi.contains("< Calculator as ops :: Versioned > :: version ()"));
+    }
+
+    #[test]
+    fn trait_object_methods_exported() {
+        let files = vec![(
+            PathBuf::from("lib.rs"),
+            parse_file(
+                "pub trait Shape { fn name() -> String; fn area(&self) -> f64; \
+                 fn scale(&mut self, factor: f64); fn into_area(self) -> f64; } \
+                 impl Shapes { #[roast(trait_object = \"com.example.Shape\")] \
+                 pub fn square(side: f64) -> Box<dyn Shape> { Box::new(Square(side)) } }",
+            )
+            .unwrap(),
+        )];
+        let methods = methods_for_ident("Shapes", &files, &[], &|_: &DerivedFn| true);
+        let entity = DerivedEntity::new("Shapes", methods);
+        assert_eq!(
+            vec![(String::from("Shape"), "com.example.Shape")],
+            entity.trait_objects()
+        );
+
+        let trait_methods = trait_methods_for_ident("Shape", &files);
+        let names: Vec<&str> = trait_methods.iter().map(|m| m.name()).collect();
+        assert_eq!(vec!["area", "scale"], names);
+    }
+
+    #[test]
+    fn visibility_attribute() {
+        let method = |attrs: &str| {
+            let file =
+                parse_file(&format!("impl Entity {{ {} pub fn foo() {{}} }}", attrs)).unwrap();
+            methods_for_ident(
+                "Entity",
+                &[(PathBuf::from("lib.rs"), file)],
+                &[],
+                &|_: &DerivedFn| true,
+            )
+        };
+        let java = |attrs: &str| {
+            DerivedEntity::new("Entity", method(attrs))
+                .export_java_syntax("mylib")
+                .unwrap()
+        };
+        assert!(java("").contains("\tpublic static native void foo();"));
+        assert!(java("#[roast(visibility = \"protected\")]")
+            .contains("\tprotected static native void foo();"));
+        assert!(java("#[roast(visibility = \"package\")]").contains("\tstatic native void foo();"));
+    }
+
+    #[test]
+    fn must_use_attribute() {
+        let java = |struct_attrs: &str, method_attrs: &str| {
+            let source = format!(
+                "{} pub struct Entity {{}} \
+                 impl Entity {{ {} pub fn count() -> i32 {{ 0 }} }}",
+                struct_attrs, method_attrs
+            );
+            let files = vec![(PathBuf::from("lib.rs"), parse_file(&source).unwrap())];
+            let attrs = struct_attrs_for_ident("Entity", &files);
+            derived_entity("Entity", &attrs, &files)
+                .export_java_syntax("mylib")
+                .unwrap()
+        };
+        let plain = java("", "");
+        assert!(!plain.contains("CheckReturnValue"));
+
+        let annotated = java("", "#[must_use]");
+        assert!(
+            annotated.starts_with("import com.google.errorprone.annotations.CheckReturnValue;\n")
+        );
+        assert!(annotated.contains("\t@CheckReturnValue\n\tpublic static native int count();"));
+
+        let javax = java(
+            "#[roast(check_return_value = \"javax\")]",
+            "#[must_use = \"the count is not cached\"]",
+        );
+        assert!(javax.starts_with("import javax.annotation.CheckReturnValue;\n"));
+        assert!(javax.contains("\t@CheckReturnValue\n"));
+    }
+
+    #[test]
+    fn deprecated_attribute() {
+        let java = |method_attrs: &str| {
+            let source = format!(
+                "pub struct Entity {{}} impl Entity {{ {} pub fn count() -> i32 {{ 0 }} }}",
+                method_attrs
+            );
+            let files = vec![(PathBuf::from("lib.rs"), parse_file(&source).unwrap())];
+            derived_entity("Entity", &[], &files)
+                .export_java_syntax("mylib")
+                .unwrap()
+        };
+        assert!(!java("").contains("Deprecated"));
+
+        let plain = java("#[deprecated]");
+        assert!(plain.contains("\n\n\t@Deprecated\n\tpublic static native int count();"));
+        assert!(!plain.contains("/**"));
+
+        let noted = java("#[deprecated(since = \"0.2.0\", note = \"use total instead\")]");
+        assert!(noted.contains(
+            "\t/**\n\t * @deprecated use total instead\n\t */\n\t@Deprecated\n\
+             \tpublic static native int count();"
+        ));
+        assert!(java("#[deprecated = \"use total instead\"]").contains("@deprecated use total"));
+    }
+
+    #[test]
+    fn naming_attribute() {
+        let files = |attrs: &str| {
+            let source = format!(
+                "{} pub struct Entity {{}} impl Entity {{ pub fn get_http_response() {{}} }}",
+                attrs
+            );
+            vec![(PathBuf::from("lib.rs"), parse_file(&source).unwrap())]
+        };
+        let java = |attrs: &str| {
+            let files = files(attrs);
+            let attrs = struct_attrs_for_ident("Entity", &files);
+            derived_entity("Entity", &attrs, &files)
+                .export_java_syntax("mylib")
+                .unwrap()
+        };
+        assert!(java("").contains(" getHttpResponse();"));
+        assert!(java("#[roast(naming = \"camel\")]").contains(" getHttpResponse();"));
+        assert!(java("#[roast(naming = \"acronym_camel\")]").contains(" getHTTPResponse();"));
+        assert!(java("#[roast(naming = \"verbatim\")]").contains(" get_http_response();"));
+    }
+
+    #[test]
+    #[should_panic(expected = "#[roast(naming = \"kebab\")] on Entity is not one of")]
+    fn unknown_naming() {
+        naming_strategy(&[syn::parse_quote!(#[roast(naming = "kebab")])], "Entity");
+    }
+
+    #[test]
+    #[should_panic(expected = "#[roast(lossy)] on count requires a String or f32 argument")]
+    fn lossy_non_string_arg() {
+        let file = parse_file("impl Entity { #[roast] pub fn foo(#[roast(lossy)] count: i32) {} }")
+            .unwrap();
+        methods_for_ident(
+            "Entity",
+            &[(PathBuf::from("lib.rs"), file)],
+            &[],
+            &|_: &DerivedFn| true,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "#[roast(returns_named(...))] on foo requires a tuple return type")]
+    fn returns_named_non_tuple() {
+        let file = parse_file(
+            "impl Entity { #[roast(returns_named(first = \"a\", second = \"b\"))] \
+             pub fn foo() -> i32 { 0 } }",
+        )
+        .unwrap();
+        methods_for_ident(
+            "Entity",
+            &[(PathBuf::from("lib.rs"), file)],
+            &[],
+            &|_: &DerivedFn| true,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "#[roast(visibility = \"private\")] on foo is not one of")]
+    fn unknown_visibility() {
+        let file = parse_file("impl Entity { #[roast(visibility = \"private\")] pub fn foo() {} }")
+            .unwrap();
+        methods_for_ident(
+            "Entity",
+            &[(PathBuf::from("lib.rs"), file)],
+            &[],
+            &|_: &DerivedFn| true,
+        );
+    }
+
+    #[test]
+    fn inner_classes_found() {
+        let files = vec![(
+            PathBuf::from("lib.rs"),
+            parse_file(
+                "#[roast(package = \"com.example\")] pub struct Arrays {} \
+                 #[roast(inner_class_of = \"Arrays\")] #[roast(utility_class)] \
+                 pub struct stats {} \
+                 #[roast(inner_class_of = \"Other\")] pub struct Ignored {} \
+                 impl Stats { pub fn max(values: Vec<i32>) -> i32 { 0 } }",
+            )
+            .unwrap(),
+        )];
+        let inner_classes = inner_classes_for_ident("Arrays", &files);
+        assert_eq!(1, inner_classes.len());
+        let (name, attrs) = &inner_classes[0];
+        assert_eq!("Stats", name);
+
+        let entity = derived_entity(name, attrs, &files);
+        assert_eq!(Some("Arrays"), entity.inner_class_of());
+        assert_eq!(Some("com.example"), entity.java_package());
+        assert!(!entity.is_empty());
+    }
+
+    #[test]
+    fn struct_and_impl_in_separate_files() {
+        let files = vec![
+            (
+                PathBuf::from("src/types.rs"),
+                parse_file(
+                    "#[roast(utility_class)] #[derive(RoastExport)] pub struct Entity {} \
+                     impl Entity {}",
+                )
+                .unwrap(),
+            ),
+            (
+                PathBuf::from("src/impl_entity.rs"),
+                parse_file(
+                    "use crate::types::Entity; \
+                     impl Entity { pub fn add(a: i32, b: i32) -> i32 { a + b } fn helper() {} }",
+                )
+                .unwrap(),
+            ),
+            (
+                PathBuf::from("src/more.rs"),
+                parse_file("impl crate::types::Entity { pub fn reset() {} }").unwrap(),
+            ),
+        ];
+        let attrs = struct_attrs_for_ident("Entity", &files);
+        let methods = methods_for_ident("Entity", &files, &[], method_filter(&attrs).as_ref());
+
+        let names: Vec<&str> = methods.iter().map(|m| m.name()).collect();
+        assert_eq!(vec!["add", "reset"], names);
+        let java = DerivedEntity::new("Entity", methods)
+            .with_utility_class(has_roast_flag(&attrs, "utility_class"))
+            .export_java_syntax("mylib")
+            .unwrap();
+        let expected = r#"public final class Entity {
+
+	static {
+		System.loadLibrary("mylib");
+	}
+
+	private Entity() {
+	}
+
+	public static native int add(int a, int b);
+
+	public static native void reset();
+
+}
+"#;
+        assert_eq!(expected, java);
+    }
+
+    #[test]
+    fn null_annotations_imported() {
+        let files = |struct_attrs: &str| {
+            let src = format!(
+                "{} struct Entity {{}} \
+                 impl Entity {{ pub fn find() -> Option<String> {{ None }} \
+                 #[roast(nonnull)] pub fn name() -> String {{ String::new() }} \
+                 pub fn count() -> i32 {{ 0 }} }}",
+                struct_attrs
+            );
+            vec![(PathBuf::from("lib.rs"), parse_file(&src).unwrap())]
+        };
+        let imports = |files: &[(PathBuf, syn::File)]| {
+            let attrs = struct_attrs_for_ident("Entity", files);
+            let methods = methods_for_ident("Entity", files, &[], &|_: &DerivedFn| true);
+            annotation_imports(&attrs, &methods)
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                "org.jetbrains.annotations.NotNull",
+                "org.jetbrains.annotations.Nullable"
+            ],
+            imports(&files(""))
+        );
+        assert_eq!(
+            vec![
+                "androidx.annotation.NonNull",
+                "androidx.annotation.Nullable"
+            ],
+            imports(&files("#[roast(null_annotations = \"androidx\")]"))
+        );
+
+        let unannotated = vec![(
+            PathBuf::from("lib.rs"),
+            parse_file("impl Entity { pub fn find() -> Option<String> { None } }").unwrap(),
+        )];
+        assert!(imports(&unannotated).is_empty());
+    }
+
+    #[test]
+    fn lib_name_overrides_package_name() {
+        let entity = DerivedEntity::new("Entity", vec![]);
+        assert_eq!(env!("CARGO_PKG_NAME"), native_lib_name(&entity));
+        assert_eq!("foo_jni", native_lib_name(&entity.with_lib_name("foo_jni")));
+    }
+
+    #[test]
+    fn unparseable_files_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("good.rs"),
+            "impl Entity { pub fn foo() {} }",
+        )
+        .unwrap();
+        fs::write(dir.path().join("bad.rs"), "impl Entity { pub fn foo( }").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not rust").unwrap();
+
+        let (files, errors) = source_files(dir.path().to_str().unwrap(), &[]);
+        assert_eq!(
+            vec![dir.path().join("good.rs")],
+            files.into_iter().map(|(p, _)| p).collect::<Vec<_>>()
+        );
+        assert_eq!(1, errors.len());
+        assert_eq!(dir.path().join("bad.rs"), errors[0].0);
+    }
+
+    #[test]
+    fn extra_source_files_scanned() {
+        let dir = tempfile::tempdir().unwrap();
+        let generated = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("lib.rs"), "pub struct Entity;").unwrap();
+        let extra = generated.path().join("generated.rs");
+        fs::write(&extra, "impl Entity { pub fn generated() {} }").unwrap();
+
+        let paths = parse_extra_source_paths(&format!("\n  {}  \n\n", extra.display()));
+        assert_eq!(vec![extra.clone()], paths);
+        let (files, errors) = source_files(dir.path().to_str().unwrap(), &paths);
+        assert!(errors.is_empty());
+        assert_eq!(
+            vec![dir.path().join("lib.rs"), extra],
+            files.into_iter().map(|(p, _)| p).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Declarative macros for the patterns which come up all the time in
+//! hand-written JNI glue code, for everything that goes beyond what
+//! `#[derive(RoastExport)]` generates.
+//!
+//! All macros are re-exported by the `roast` crate.
+
+#[doc(hidden)]
+pub use jni as __jni;
+
+/// Unwraps the result of a JNI call or returns from the surrounding function.
+///
+/// If the call failed, a `java.lang.RuntimeException` carrying the error
+/// message is thrown, unless a java exception is pending already. The
+/// surrounding function then returns the given default, or
+/// `Default::default()` if there is none, which the JVM ignores since an
+/// exception is pending.
+///
+/// ```ignore
+/// let length = jni_try!(env, env.get_array_length(array), 0);
+/// ```
+#[macro_export]
+macro_rules! jni_try {
+    ($env:expr, $result:expr) => {
+        $crate::jni_try!($env, $result, Default::default())
+    };
+    ($env:expr, $result:expr, $default:expr) => {
+        match $result {
+            Ok(v) => v,
+            Err(e) => {
+                let env: &$crate::__jni::JNIEnv = &$env;
+                if !env.exception_check().unwrap_or(true) {
+                    let _ = env.throw_new("java/lang/RuntimeException", e.to_string());
+                }
+                return $default;
+            }
+        }
+    };
+}
+
+/// Creates a java string, or returns `null` from the surrounding function
+/// with a pending exception if that fails.
+///
+/// The surrounding function therefore needs to return a raw JNI object like
+/// `jstring`, otherwise pass the value to return as a third argument.
+///
+/// ```ignore
+/// jni_string!(env, "Hello, World!").into_inner()
+/// ```
+#[macro_export]
+macro_rules! jni_string {
+    ($env:expr, $value:expr) => {
+        $crate::jni_string!($env, $value, std::ptr::null_mut())
+    };
+    ($env:expr, $value:expr, $default:expr) => {
+        $crate::jni_try!($env, $env.new_string($value), $default)
+    };
+}
+
+/// Calls a java method on an object by name and type descriptor.
+///
+/// The arguments are converted into `JValue`s, so rust primitives and
+/// objects can be passed as they are. Evaluates to the `Result` of the call,
+/// which can be unwrapped with `jni_try!`.
+///
+/// ```ignore
+/// let upper = jni_try!(env, jni_call!(env, input, "toUpperCase", "()Ljava/lang/String;"));
+/// let index = jni_try!(env, jni_call!(env, input, "indexOf", "(I)I", 'a' as jint));
+/// ```
+#[macro_export]
+macro_rules! jni_call {
+    ($env:expr, $obj:expr, $method:expr, $sig:expr $(, $arg:expr)* $(,)?) => {
+        $env.call_method(
+            $obj,
+            $method,
+            $sig,
+            &[$($crate::__jni::objects::JValue::from($arg)),*],
+        )
+    };
+}
@@ -0,0 +1,83 @@
+//! Compares calling a native method which the JVM binds through its
+//! `Java_<Class>_<method>` symbol with one bound through `RegisterNatives`.
+//!
+//! The benchmark compiles the generated java classes with `javac` and starts
+//! an in-process JVM, so it needs a JDK. Cargo doesn't build the `cdylib`
+//! for benchmarks, so run `cargo build --release -p roast_testlab` before
+//! `cargo bench -p roast_testlab`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jni::objects::{JClass, JStaticMethodID, JValue};
+use jni::signature::{JavaType, Primitive};
+use jni::{InitArgsBuilder, JNIEnv, JNIVersion, JavaVM};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Compiles the generated java classes into a temporary class path.
+fn compile_java_classes() -> PathBuf {
+    let java_dir = match option_env!("ROAST_JAVA_OUTPUT_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("OUT_DIR")).join("java"),
+    };
+    let class_dir = env::temp_dir().join("roast_jni_dispatch");
+    let status = Command::new("javac")
+        .arg("-d")
+        .arg(&class_dir)
+        .arg(java_dir.join("StaticDispatch.java"))
+        .arg(java_dir.join("RegisteredDispatch.java"))
+        .status()
+        .expect("Could not run javac");
+    assert!(status.success(), "javac failed with {}", status);
+    class_dir
+}
+
+/// Starts the JVM with the native library next to the benchmark executable,
+/// where `cargo build --release` puts the library as well.
+fn start_jvm() -> JavaVM {
+    let class_dir = compile_java_classes();
+    let exe = env::current_exe().expect("Could not find the benchmark executable");
+    let lib_dir = exe.parent().expect("Could not find the deps directory");
+    let args = InitArgsBuilder::new()
+        .version(JNIVersion::V8)
+        .option(&format!("-Djava.class.path={}", class_dir.display()))
+        .option(&format!("-Djava.library.path={}", lib_dir.display()))
+        .build()
+        .expect("Invalid JVM arguments");
+    JavaVM::new(args).expect("Could not start the JVM")
+}
+
+/// Looks up the static `addInt(int, int)` of the given class.
+fn add_int<'a>(env: &JNIEnv<'a>, class: &str) -> (JClass<'a>, JStaticMethodID<'a>) {
+    let class = env.find_class(class).expect("Could not find class");
+    let method = env
+        .get_static_method_id(class, "addInt", "(II)I")
+        .expect("Could not find addInt");
+    (class, method)
+}
+
+fn jni_dispatch(c: &mut Criterion) {
+    let jvm = start_jvm();
+    let env = jvm.attach_current_thread().expect("Could not attach thread");
+
+    let mut group = c.benchmark_group("add_int");
+    for class in &["StaticDispatch", "RegisteredDispatch"] {
+        let (class_ref, method) = add_int(&env, class);
+        group.bench_function(*class, |b| {
+            b.iter(|| {
+                env.call_static_method_unchecked(
+                    class_ref,
+                    method,
+                    JavaType::Primitive(Primitive::Int),
+                    &[JValue::Int(black_box(20)), JValue::Int(black_box(22))],
+                )
+                .and_then(|v| v.i())
+                .expect("addInt failed")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, jni_dispatch);
+criterion_main!(benches);
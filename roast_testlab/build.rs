@@ -1,7 +1 @@
-extern crate roast;
-
-use roast::build::BuildConfig;
-
-fn main() {
-    roast::build::build(BuildConfig::default());
-}
+roast::roast_build!();
@@ -0,0 +1,7 @@
+/// Exported from this file, while its methods are implemented in
+/// `grinder_impl.rs`.
+#[roast(utility_class)]
+#[derive(Debug, RoastExport)]
+pub struct Grinder {}
+
+impl Grinder {}
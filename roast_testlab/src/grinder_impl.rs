@@ -0,0 +1,11 @@
+use crate::grinder::Grinder;
+
+impl Grinder {
+    pub fn grind(beans: i32, coarseness: i32) -> i32 {
+        beans * Self::particles_per_bean(coarseness)
+    }
+
+    fn particles_per_bean(coarseness: i32) -> i32 {
+        100 / coarseness.max(1)
+    }
+}
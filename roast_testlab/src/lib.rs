@@ -1,10 +1,22 @@
 #[macro_use]
 extern crate roast;
 
+use serde_derive::Serialize;
+use std::error::Error;
+
+mod grinder;
+mod grinder_impl;
+
+/// Milliseconds since the unix epoch.
+type Timestamp = i64;
+
+#[roast(utility_class)]
 #[derive(Debug, RoastExport)]
 struct Primitive {}
 
 impl Primitive {
+    pub const DAYS_PER_WEEK: i32 = 7;
+
     pub fn add_int(a: i32, b: i32) -> i32 {
         a + b
     }
@@ -12,12 +24,60 @@ impl Primitive {
     pub fn compare_bool(a: bool, b: bool) -> bool {
         a == b
     }
+
+    pub fn next_char(c: u16) -> u16 {
+        c + 1
+    }
+
+    pub fn unsigned_to_string(value: u64) -> String {
+        value.to_string()
+    }
+
+    pub fn parse_unsigned(value: String) -> u64 {
+        value.parse().unwrap_or_default()
+    }
+
+    pub fn add_days(timestamp: Timestamp, days: i32) -> Timestamp {
+        timestamp + i64::from(days) * 24 * 60 * 60 * 1000
+    }
+
+    pub fn scaled(value: Option<i64>, factor: Option<f64>, negate: Option<bool>) -> Option<f64> {
+        let scaled = value? as f64 * factor.unwrap_or(1.0);
+        Some(if negate.unwrap_or(false) { -scaled } else { scaled })
+    }
+
+    #[roast]
+    pub fn narrow(#[roast(lossy)] value: f32, offset: f64) -> f64 {
+        f64::from(value) + offset
+    }
+
+    pub fn or_zero(value: Option<i32>) -> i32 {
+        value.unwrap_or(0)
+    }
+
+    #[roast(error_return)]
+    pub fn checked_div(a: i32, b: i32) -> Result<i32, Box<dyn Error>> {
+        a.checked_div(b).ok_or_else(|| "division by zero".into())
+    }
+
+    #[roast(returns_named(first = "quotient", second = "remainder"))]
+    pub fn div_rem(a: i32, b: i32) -> (i32, i32) {
+        (a / b, a % b)
+    }
+
+    #[roast(cfg_java = "android")]
+    pub fn density_scale(dpi: i32) -> f32 {
+        dpi as f32 / 160.0
+    }
 }
 
+#[roast(thread_safe)]
 #[derive(Debug, RoastExport)]
 struct Strings {}
 
 impl Strings {
+    pub const GREETING: &'static str = "Hello";
+
     pub fn hello_world() -> String {
         String::from("Hello, World!")
     }
@@ -29,6 +89,71 @@ impl Strings {
     pub fn count_chars(chars_to_count: String) -> i32 {
         chars_to_count.chars().count() as i32
     }
+
+    pub fn name() -> &'static str {
+        "roast"
+    }
+
+    pub fn greeting() -> &'static str {
+        Self::GREETING
+    }
+
+    #[deprecated(since = "0.1.0", note = "use greeting instead")]
+    pub fn salutation() -> &'static str {
+        Self::GREETING
+    }
+
+    pub fn trimmed(input: &str) -> &str {
+        input.trim()
+    }
+
+    pub fn format(template: String, value: String) -> String {
+        template.replacen("{}", &value, 1)
+    }
+
+    pub fn strip_roast(input: String) -> Result<String, String> {
+        input
+            .strip_prefix("roast:")
+            .map(String::from)
+            .ok_or_else(|| format!("'{}' does not start with 'roast:'", input))
+    }
+
+    pub fn greet_optional(name: Option<String>) -> String {
+        format!("Hello, {}", name.as_deref().unwrap_or("stranger"))
+    }
+
+    #[roast]
+    pub fn sanitize(#[roast(lossy)] input: String) -> String {
+        input
+    }
+
+    pub fn split_half(input: String) -> (String, i32) {
+        let half = input.chars().count() / 2;
+        (input.chars().take(half).collect(), half as i32)
+    }
+
+    pub fn index_of(input: String, c: u16) -> Option<i32> {
+        input.encode_utf16().position(|u| u == c).map(|i| i as i32)
+    }
+
+    #[roast(use_optional)]
+    pub fn first_word(input: String) -> Option<String> {
+        input.split_whitespace().next().map(String::from)
+    }
+
+    #[roast(json)]
+    pub fn describe(input: String) -> Word {
+        Word {
+            length: input.chars().count() as i32,
+            text: input,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Word {
+    text: String,
+    length: i32,
 }
 
 #[derive(Debug, RoastExport)]
@@ -39,4 +164,334 @@ impl Arrays {
         input.reverse();
         input
     }
+
+    pub fn magic_bytes() -> &'static [u8] {
+        b"roast"
+    }
+
+    pub fn checksum(data: &[u8]) -> i32 {
+        data.iter().map(|b| i32::from(*b)).sum()
+    }
+
+    pub fn zero_fill(buf: &mut [u8]) {
+        buf.fill(0);
+    }
+
+    pub fn scale_floats(values: Vec<f32>, factor: f32) -> Vec<f32> {
+        values.into_iter().map(|v| v * factor).collect()
+    }
+
+    pub fn negate_bools(flags: Vec<bool>) -> Vec<bool> {
+        flags.into_iter().map(|f| !f).collect()
+    }
+
+    pub fn increment_unsigned(values: Vec<u32>) -> Vec<u32> {
+        values.into_iter().map(|v| v.wrapping_add(1)).collect()
+    }
+
+    pub fn sort_ints(mut values: Vec<i32>) -> Vec<i32> {
+        values.sort_unstable();
+        values
+    }
+
+    #[roast(java_collection)]
+    pub fn sort_list(mut values: Vec<i32>) -> Vec<i32> {
+        values.sort_unstable();
+        values
+    }
+
+    #[roast(collect)]
+    pub fn range(start: i32, end: i32) -> impl Iterator<Item = i32> {
+        start..end
+    }
+}
+
+#[roast(inner_class_of = "Arrays")]
+#[derive(Debug, RoastExport)]
+struct Stats {}
+
+impl Stats {
+    pub fn max(values: Vec<i32>) -> i32 {
+        values.into_iter().max().unwrap_or(0)
+    }
+}
+
+#[roast(package = "rs.roast.roastery")]
+#[derive(Debug, RoastExport)]
+struct Roastery {}
+
+impl Roastery {
+    pub fn bags_per_batch(batches: i32) -> i32 {
+        batches * 12
+    }
+}
+
+#[derive(Debug, RoastExport)]
+struct Callbacks {}
+
+impl Callbacks {
+    #[roast]
+    pub fn call_twice(
+        #[roast(callback = "java.util.function.IntSupplier")] supplier: Box<dyn Fn() -> i32>,
+    ) -> i32 {
+        supplier() + supplier()
+    }
+}
+
+#[derive(Debug, RoastExport)]
+struct Wrapper(i32);
+
+impl Wrapper {
+    pub fn doubled(value: i32) -> i32 {
+        let wrapper = Wrapper(value);
+        wrapper.0 * 2
+    }
+}
+
+#[derive(Debug, RoastExport)]
+struct Accumulator {
+    total: i64,
+}
+
+impl Accumulator {
+    pub fn create(start: i64) -> i64 {
+        sptr::Strict::expose_addr(Box::into_raw(Box::new(Accumulator { total: start }))) as i64
+    }
+
+    #[roast]
+    pub fn total(#[roast(handle)] accumulator: &Accumulator) -> i64 {
+        accumulator.total
+    }
+
+    pub fn release(handle: i64) {
+        let accumulator = sptr::from_exposed_addr_mut::<Accumulator>(handle as usize);
+        drop(unsafe { Box::from_raw(accumulator) });
+    }
+}
+
+pub trait Shape {
+    fn area(&self) -> f64;
+
+    fn describe(&self, precision: i32) -> String;
+}
+
+struct Square(f64);
+
+impl Shape for Square {
+    fn area(&self) -> f64 {
+        self.0 * self.0
+    }
+
+    fn describe(&self, precision: i32) -> String {
+        format!("square with side {:.*}", precision as usize, self.0)
+    }
+}
+
+#[derive(Debug, RoastExport)]
+struct Shapes {}
+
+impl Shapes {
+    #[roast(trait_object = "rs.roast.shapes.Shape")]
+    pub fn square(side: f64) -> Box<dyn Shape> {
+        Box::new(Square(side))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    North = 0,
+    South = 1,
+    East = 2,
+    West = 3,
+}
+
+impl From<i32> for Direction {
+    fn from(value: i32) -> Self {
+        match value.rem_euclid(4) {
+            0 => Direction::North,
+            1 => Direction::South,
+            2 => Direction::East,
+            _ => Direction::West,
+        }
+    }
+}
+
+impl From<Direction> for i32 {
+    fn from(direction: Direction) -> Self {
+        direction as i32
+    }
+}
+
+#[roast(naming = "acronym_camel")]
+#[derive(Debug, RoastExport)]
+struct Encoding {}
+
+impl Encoding {
+    pub fn to_json_array(values: Vec<i32>) -> String {
+        let items: Vec<String> = values.iter().map(ToString::to_string).collect();
+        format!("[{}]", items.join(","))
+    }
+
+    #[roast(json)]
+    pub fn brew(shots: i32) -> Drink {
+        match shots {
+            0 => Drink::Water,
+            1 => Drink::Espresso { shots },
+            _ => Drink::Latte {
+                milk: String::from("oat \"barista\""),
+                size_ml: f64::from(shots) * 120.5,
+            },
+        }
+    }
+}
+
+#[roast(min_java = 17)]
+#[derive(Debug, Serialize, RoastEnumExport)]
+enum Drink {
+    Espresso { shots: i32 },
+    Latte { milk: String, size_ml: f64 },
+    Water,
+}
+
+#[derive(Debug, RoastExport)]
+struct Compass {}
+
+impl Compass {
+    #[roast(enum_as_int)]
+    pub fn opposite(#[roast(enum_as_int)] d: Direction) -> Direction {
+        match d {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+}
+
+trait Computable {
+    fn compute(&self, n: i32) -> i64;
+}
+
+#[roast(export_trait_impl = "Computable")]
+#[derive(Debug, RoastExport)]
+struct Calculator {
+    offset: i64,
+}
+
+impl Calculator {
+    pub fn create(offset: i64) -> i64 {
+        sptr::Strict::expose_addr(Box::into_raw(Box::new(Calculator { offset }))) as i64
+    }
+
+    pub fn release(handle: i64) {
+        let calculator = sptr::from_exposed_addr_mut::<Calculator>(handle as usize);
+        drop(unsafe { Box::from_raw(calculator) });
+    }
+}
+
+impl Computable for Calculator {
+    fn compute(&self, n: i32) -> i64 {
+        (1..=i64::from(n)).product::<i64>() + self.offset
+    }
+}
+
+#[roast_trait]
+trait Versioned {
+    fn version() -> i32;
+
+    fn version_name() -> String {
+        format!("v{}", Self::version())
+    }
+}
+
+impl Versioned for Calculator {
+    fn version() -> i32 {
+        2
+    }
+}
+
+/// Hand-written JNI glue for the `Glue` test class, which uses the helper
+/// macros instead of `RoastExport`.
+#[no_mangle]
+pub extern "system" fn Java_Glue_shout(
+    env: roast::JNIEnv,
+    _class: roast::JClass,
+    input: roast::JString,
+) -> roast::jstring {
+    let upper = roast::jni_try!(
+        env,
+        roast::jni_call!(env, input, "toUpperCase", "()Ljava/lang/String;"),
+        std::ptr::null_mut()
+    );
+    let upper = roast::jni_try!(env, upper.l(), std::ptr::null_mut());
+    let upper: String = roast::jni_try!(
+        env,
+        env.get_string(upper.into()),
+        std::ptr::null_mut()
+    )
+    .into();
+    roast::jni_string!(env, format!("{}!", upper)).into_inner()
+}
+
+/// Runs the `Runnable` on a new rust thread and waits for it to finish,
+/// which exercises the `ffi_utils` helpers.
+#[no_mangle]
+pub extern "system" fn Java_Glue_runOnThread(
+    env: roast::JNIEnv,
+    _class: roast::JClass,
+    task: roast::JObject,
+) {
+    let vm = roast::jni_try!(env, env.get_java_vm());
+    let task = roast::global_ref(&env, task);
+    let result = std::thread::spawn(move || {
+        roast::with_jni_env(&vm, |env| {
+            roast::call_void_method(env, task.as_obj(), "run", "()V", &[])
+        })
+    })
+    .join()
+    .expect("Runnable thread panicked");
+    roast::jni_try!(env, result);
+}
+
+/// Exports the same method as `RegisteredDispatch`, but lets the JVM look up
+/// the `Java_StaticDispatch_addInt` symbol, see `benches/jni_dispatch.rs`.
+#[roast(utility_class)]
+#[derive(Debug, RoastExport)]
+struct StaticDispatch {}
+
+impl StaticDispatch {
+    pub fn add_int(a: i32, b: i32) -> i32 {
+        a + b
+    }
+}
+
+#[roast(utility_class)]
+#[roast(register_natives)]
+#[derive(Debug, RoastExport)]
+struct RegisteredDispatch {}
+
+impl RegisteredDispatch {
+    pub fn add_int(a: i32, b: i32) -> i32 {
+        a + b
+    }
+}
+
+/// Binds the natives of `RegisteredDispatch` when the library is loaded.
+///
+/// Not every user of the library has the class on its class path, so the
+/// registration is skipped if it can't be found instead of failing the load.
+#[no_mangle]
+pub extern "system" fn JNI_OnLoad(vm: jni::JavaVM, _: *mut std::ffi::c_void) -> roast::jint {
+    let env = match vm.get_env() {
+        Ok(env) => env,
+        Err(_) => return roast::JNI_ERR,
+    };
+    match env.find_class("RegisteredDispatch") {
+        Ok(class) => RegisteredDispatch::register_natives(&env, class),
+        // find_class leaves a NoClassDefFoundError pending
+        Err(_) => {
+            let _ = env.exception_clear();
+        }
+    }
+    roast::JNI_VERSION_1_8
 }
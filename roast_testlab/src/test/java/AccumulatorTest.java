@@ -0,0 +1,17 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class AccumulatorTest {
+
+    @Test
+    public void totalFromHandle() {
+        long handle = Accumulator.create(42);
+        try {
+            assertEquals(42, Accumulator.total(handle));
+        } finally {
+            Accumulator.release(handle);
+        }
+    }
+
+}
\ No newline at end of file
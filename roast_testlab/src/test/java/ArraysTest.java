@@ -1,6 +1,12 @@
 import org.junit.jupiter.api.Test;
 
+import java.util.ArrayList;
+import java.util.List;
+import java.util.Random;
+
 import static org.junit.jupiter.api.Assertions.assertArrayEquals;
+import static org.junit.jupiter.api.Assertions.assertEquals;
+import static org.junit.jupiter.api.Assertions.assertThrows;
 
 public class ArraysTest {
 
@@ -13,4 +19,94 @@ public class ArraysTest {
         assertArrayEquals(expected, output);
     }
 
+    @Test
+    public void magicBytesTest() {
+        byte[] expected = new byte[] { 'r', 'o', 'a', 's', 't' };
+        assertArrayEquals(expected, Arrays.magicBytes());
+    }
+
+    @Test
+    public void checksumTest() {
+        assertEquals(6, Arrays.checksum(new byte[] { 1, 2, 3 }));
+        assertEquals(0, Arrays.checksum(new byte[0]));
+    }
+
+    @Test
+    public void zeroFillTest() {
+        byte[] buf = new byte[] { 1, -2, 3 };
+        Arrays.zeroFill(buf);
+        assertArrayEquals(new byte[] { 0, 0, 0 }, buf);
+    }
+
+    @Test
+    public void scaleFloatsRoundTripTest() {
+        Random random = new Random(42);
+        for (int i = 0; i < 100; i++) {
+            float[] input = new float[random.nextInt(64)];
+            float factor = random.nextFloat() * 10 - 5;
+            float[] expected = new float[input.length];
+            for (int j = 0; j < input.length; j++) {
+                input[j] = random.nextFloat() * 1000 - 500;
+                expected[j] = input[j] * factor;
+            }
+
+            assertArrayEquals(input, Arrays.scaleFloats(input, 1.0f));
+            assertArrayEquals(expected, Arrays.scaleFloats(input, factor));
+        }
+    }
+
+    @Test
+    public void scaleFloatsNullTest() {
+        assertThrows(NullPointerException.class, () -> Arrays.scaleFloats(null, 1.0f));
+    }
+
+    @Test
+    public void negateBoolsTest() {
+        boolean[] input = new boolean[] { true, false, false, true };
+        boolean[] expected = new boolean[] { false, true, true, false };
+
+        assertArrayEquals(expected, Arrays.negateBools(input));
+        assertArrayEquals(new boolean[0], Arrays.negateBools(new boolean[0]));
+    }
+
+    @Test
+    public void incrementUnsignedTest() {
+        // u32 values above i32::MAX show up as negative numbers, so -2 is
+        // u32::MAX - 1, which increments to u32::MAX (-1) and wraps to 0.
+        int[] input = new int[] { 0, Integer.MAX_VALUE, -2, -1 };
+        int[] expected = new int[] { 1, Integer.MIN_VALUE, -1, 0 };
+
+        assertArrayEquals(expected, Arrays.incrementUnsigned(input));
+    }
+
+    @Test
+    public void sortIntsTest() {
+        int[] input = new int[] { 3, -1, 2 };
+        int[] expected = new int[] { -1, 2, 3 };
+
+        assertArrayEquals(expected, Arrays.sortInts(input));
+        assertArrayEquals(new int[0], Arrays.sortInts(new int[0]));
+    }
+
+    @Test
+    public void sortListTest() {
+        List<Integer> input = java.util.Arrays.asList(3, -1, 2);
+
+        assertEquals(java.util.Arrays.asList(-1, 2, 3), Arrays.sortList(input));
+        assertEquals(new ArrayList<Integer>(), Arrays.sortList(new ArrayList<>()));
+        assertThrows(NullPointerException.class, () -> Arrays.sortList(null));
+    }
+
+    @Test
+    public void rangeTest() {
+        assertArrayEquals(new int[] {2, 3, 4}, Arrays.range(2, 5));
+        assertArrayEquals(new int[0], Arrays.range(5, 2));
+    }
+
+    @Test
+    public void innerClassTest() {
+        assertEquals(7, Arrays.Stats.max(new int[] {3, 7, -1}));
+        assertEquals(0, Arrays.Stats.max(new int[0]));
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,24 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class CalculatorTest {
+
+    @Test
+    public void compute() {
+        long handle = Calculator.create(10);
+        try {
+            assertEquals(11, Calculator.compute(handle, 0));
+            assertEquals(130, Calculator.compute(handle, 5));
+        } finally {
+            Calculator.release(handle);
+        }
+    }
+
+    @Test
+    public void versioned() {
+        assertEquals(2, Calculator.version());
+        assertEquals("v2", Calculator.versionName());
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+import org.junit.jupiter.api.Test;
+
+import java.util.concurrent.atomic.AtomicInteger;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class CallbacksTest {
+
+    @Test
+    public void callTwice() {
+        AtomicInteger counter = new AtomicInteger();
+        assertEquals(3, Callbacks.callTwice(counter::incrementAndGet));
+        assertEquals(2, counter.get());
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,20 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class CompassTest {
+
+    private static final int NORTH = 0;
+    private static final int SOUTH = 1;
+    private static final int EAST = 2;
+    private static final int WEST = 3;
+
+    @Test
+    public void oppositeDirection() {
+        assertEquals(SOUTH, Compass.opposite(NORTH));
+        assertEquals(NORTH, Compass.opposite(SOUTH));
+        assertEquals(WEST, Compass.opposite(EAST));
+        assertEquals(EAST, Compass.opposite(WEST));
+    }
+
+}
\ No newline at end of file
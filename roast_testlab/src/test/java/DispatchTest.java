@@ -0,0 +1,17 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class DispatchTest {
+
+    @Test
+    public void staticDispatch() {
+        assertEquals(42, StaticDispatch.addInt(20, 22));
+    }
+
+    @Test
+    public void registeredDispatch() {
+        assertEquals(42, RegisteredDispatch.addInt(20, 22));
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+import static org.junit.jupiter.api.Assertions.assertThrows;
+
+public class EncodingTest {
+
+    @Test
+    public void acronymNaming() {
+        assertEquals("[]", Encoding.toJSONArray(new int[0]));
+        assertEquals("[1,-2,3]", Encoding.toJSONArray(new int[] {1, -2, 3}));
+    }
+
+    @Test
+    public void sealedEnumFromJson() {
+        assertEquals(new Drink.Water(), Drink.fromJson(Encoding.brew(0)));
+        assertEquals(new Drink.Espresso(1), Drink.fromJson(Encoding.brew(1)));
+        assertEquals(new Drink.Latte("oat \"barista\"", 241.0), Drink.fromJson(Encoding.brew(2)));
+        assertThrows(IllegalArgumentException.class, () -> Drink.fromJson("\"Tea\""));
+    }
+
+}
\ No newline at end of file
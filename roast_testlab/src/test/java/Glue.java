@@ -0,0 +1,11 @@
+public class Glue {
+
+    static {
+        System.loadLibrary("roast_testlab");
+    }
+
+    public static native String shout(String input);
+
+    public static native void runOnThread(Runnable task);
+
+}
\ No newline at end of file
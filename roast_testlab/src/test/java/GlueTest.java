@@ -0,0 +1,30 @@
+import org.junit.jupiter.api.Test;
+
+import java.util.concurrent.atomic.AtomicReference;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+import static org.junit.jupiter.api.Assertions.assertNotEquals;
+import static org.junit.jupiter.api.Assertions.assertNotNull;
+import static org.junit.jupiter.api.Assertions.assertThrows;
+
+public class GlueTest {
+
+    @Test
+    public void shout() {
+        assertEquals("ROAST!", Glue.shout("roast"));
+    }
+
+    @Test
+    public void shoutNull() {
+        assertThrows(RuntimeException.class, () -> Glue.shout(null));
+    }
+
+    @Test
+    public void runOnThread() {
+        AtomicReference<Thread> thread = new AtomicReference<>();
+        Glue.runOnThread(() -> thread.set(Thread.currentThread()));
+        assertNotNull(thread.get());
+        assertNotEquals(Thread.currentThread(), thread.get());
+    }
+
+}
\ No newline at end of file
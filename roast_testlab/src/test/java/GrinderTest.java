@@ -0,0 +1,13 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class GrinderTest {
+
+    @Test
+    public void grind() {
+        assertEquals(500, Grinder.grind(5, 1));
+        assertEquals(40, Grinder.grind(2, 5));
+    }
+
+}
\ No newline at end of file
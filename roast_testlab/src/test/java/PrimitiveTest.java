@@ -1,9 +1,21 @@
 import org.junit.jupiter.api.Test;
 
+import java.lang.reflect.Modifier;
+
 import static org.junit.jupiter.api.Assertions.assertEquals;
+import static org.junit.jupiter.api.Assertions.assertFalse;
+import static org.junit.jupiter.api.Assertions.assertNull;
+import static org.junit.jupiter.api.Assertions.assertThrows;
+import static org.junit.jupiter.api.Assertions.assertTrue;
 
 public class PrimitiveTest {
 
+    @Test
+    public void utilityClass() throws NoSuchMethodException {
+        assertTrue(Modifier.isFinal(Primitive.class.getModifiers()));
+        assertTrue(Modifier.isPrivate(Primitive.class.getDeclaredConstructor().getModifiers()));
+    }
+
     @Test
     public void addInt() {
         assertEquals(0, Primitive.addInt(0, 0));
@@ -18,4 +30,67 @@ public class PrimitiveTest {
         assertEquals(false, Primitive.compareBool(false, true));
     }
 
+    @Test
+    public void nextChar() {
+        assertEquals('b', Primitive.nextChar('a'));
+    }
+
+    @Test
+    public void addDays() {
+        long now = System.currentTimeMillis();
+        assertEquals(now + 2 * 24 * 60 * 60 * 1000L, Primitive.addDays(now, 2));
+    }
+
+    @Test
+    public void checkedDiv() {
+        assertEquals(5, Primitive.checkedDiv(10, 2));
+        RuntimeException ex = assertThrows(RuntimeException.class, () -> Primitive.checkedDiv(1, 0));
+        assertEquals("division by zero", ex.getMessage());
+    }
+
+    @Test
+    public void divRem() {
+        Primitive.DivRemResult result = Primitive.divRem(17, 5);
+        assertEquals(3, result.quotient);
+        assertEquals(2, result.remainder);
+    }
+
+    @Test
+    public void narrow() {
+        assertEquals((double) 0.1f, Primitive.narrow(0.1, 0.0));
+        assertEquals(Double.POSITIVE_INFINITY, Primitive.narrow(Double.MAX_VALUE, 1.0));
+    }
+
+    @Test
+    public void constants() {
+        assertEquals(7, Primitive.DAYS_PER_WEEK);
+    }
+
+    @Test
+    public void platformClass() {
+        assertFalse(PrimitiveAndroid.isAvailable());
+        assertEquals(2.0f, PrimitiveAndroid.densityScale(320));
+    }
+
+    @Test
+    public void unsignedRoundtrip() {
+        long[] values = new long[] { 0L, 1L, -1L, Long.MIN_VALUE };
+        for (long value : values) {
+            String unsigned = Long.toUnsignedString(value);
+            assertEquals(unsigned, Primitive.unsignedToString(value));
+            assertEquals(value, Primitive.parseUnsigned(unsigned));
+        }
+        assertEquals("18446744073709551615", Primitive.unsignedToString(-1L));
+        assertEquals("9223372036854775808", Primitive.unsignedToString(Long.MIN_VALUE));
+    }
+
+    @Test
+    public void nullableArguments() {
+        assertEquals(5, Primitive.orZero(5));
+        assertEquals(0, Primitive.orZero(null));
+        assertEquals(-5.0, Primitive.scaled(2L, 2.5, true));
+        assertEquals(2.0, Primitive.scaled(2L, null, null));
+        assertNull(Primitive.scaled(null, 2.5, false));
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+import org.junit.jupiter.api.Test;
+import rs.roast.roastery.Roastery;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class RoasteryTest {
+
+    @Test
+    public void packagedClass() {
+        assertEquals("rs.roast.roastery", Roastery.class.getPackage().getName());
+        assertEquals(24, Roastery.bagsPerBatch(2));
+    }
+
+}
@@ -0,0 +1,20 @@
+import org.junit.jupiter.api.Test;
+import rs.roast.shapes.Shape;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class ShapesTest {
+
+    @Test
+    public void squareTraitObject() {
+        Shape square = new Shape(Shapes.square(1.5));
+        try {
+            assertEquals(2.25, square.area());
+            assertEquals("square with side 1.50", square.describe(2));
+        } finally {
+            square.destroy();
+        }
+        square.destroy();
+    }
+
+}
\ No newline at end of file
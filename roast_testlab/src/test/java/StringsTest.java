@@ -1,9 +1,20 @@
 import org.junit.jupiter.api.Test;
 
+import java.lang.reflect.Modifier;
+import java.util.Optional;
+
 import static org.junit.jupiter.api.Assertions.assertEquals;
+import static org.junit.jupiter.api.Assertions.assertNull;
+import static org.junit.jupiter.api.Assertions.assertThrows;
+import static org.junit.jupiter.api.Assertions.assertTrue;
 
 public class StringsTest {
 
+    @Test
+    public void threadSafe() throws NoSuchMethodException {
+        assertTrue(Modifier.isSynchronized(Strings.class.getMethod("helloWorld").getModifiers()));
+    }
+
     @Test
     public void helloWorld() {
         assertEquals("Hello, World!", Strings.helloWorld());
@@ -19,4 +30,77 @@ public class StringsTest {
         assertEquals(5, Strings.countChars("roast"));
     }
 
+    @Test
+    public void name() {
+        assertEquals("roast", Strings.name());
+    }
+
+    @Test
+    public void greeting() {
+        assertEquals(Strings.GREETING, Strings.greeting());
+        assertEquals("roast", Strings.trimmed("  roast "));
+    }
+
+    @Test
+    public void sanitizeLoneSurrogate() {
+        assertEquals("a\uFFFDb", Strings.sanitize("a\uD800b"));
+        assertEquals("roast \uD83D\uDE00", Strings.sanitize("roast \uD83D\uDE00"));
+    }
+
+    @Test
+    public void splitHalf() {
+        Tuple2<String, Integer> result = Strings.splitHalf("roasted");
+        assertEquals("roa", result.first);
+        assertEquals(3, (int) result.second);
+    }
+
+    @Test
+    public void indexOf() {
+        assertEquals(2, (int) Strings.indexOf("roast", 'a'));
+        assertNull(Strings.indexOf("roast", 'x'));
+    }
+
+    @Test
+    public void firstWord() {
+        assertEquals(Optional.of("artisan"), Strings.firstWord("artisan roasted java"));
+        assertEquals(Optional.empty(), Strings.firstWord("   "));
+    }
+
+    @Test
+    public void constants() {
+        assertEquals("Hello", Strings.GREETING);
+    }
+
+    @Test
+    public void describeTest() {
+        assertEquals("{\"text\":\"roast\",\"length\":5}", Strings.describe("roast"));
+    }
+
+    @Test
+    public void nullableString() {
+        assertEquals("Hello, roast", Strings.greetOptional("roast"));
+        assertEquals("Hello, stranger", Strings.greetOptional(null));
+    }
+
+    @Test
+    public void formatTest() {
+        assertEquals("Hello, roast!", Strings.format("Hello, {}!", "roast"));
+        assertEquals("ünïcödé ✓", Strings.format("{} ✓", "ünïcödé"));
+    }
+
+    @Test
+    public void resultString() {
+        assertEquals("dark", Strings.stripRoast("roast:dark"));
+        IllegalArgumentException e = assertThrows(
+                IllegalArgumentException.class, () -> Strings.stripRoast("light"));
+        assertEquals("'light' does not start with 'roast:'", e.getMessage());
+    }
+
+    @Test
+    @SuppressWarnings("deprecation")
+    public void deprecated() throws NoSuchMethodException {
+        assertEquals("Hello", Strings.salutation());
+        assertTrue(Strings.class.getMethod("salutation").isAnnotationPresent(Deprecated.class));
+    }
+
 }
\ No newline at end of file
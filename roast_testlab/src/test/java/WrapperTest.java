@@ -0,0 +1,13 @@
+import org.junit.jupiter.api.Test;
+
+import static org.junit.jupiter.api.Assertions.assertEquals;
+
+public class WrapperTest {
+
+    @Test
+    public void doubled() {
+        assertEquals(0, Wrapper.doubled(0));
+        assertEquals(42, Wrapper.doubled(21));
+    }
+
+}
\ No newline at end of file